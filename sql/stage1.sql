@@ -20,6 +20,51 @@ CREATE TABLE IF NOT EXISTS recipes (
 CREATE TABLE IF NOT EXISTS elements_holding(
 	name TEXT,
 	num REAL NOT NULL DEFAULT 0.0,
+	-- unix seconds of the last change, NULL if never changed by the tool.
+	last_updated INTEGER,
 	FOREIGN KEY (name) REFERENCES elements(name)
 		ON UPDATE CASCADE ON DELETE CASCADE
 	);
+
+-- one holding row per element, the holdings are upserted on it.
+CREATE UNIQUE INDEX IF NOT EXISTS elements_holding_name ON elements_holding(name);
+
+CREATE TABLE IF NOT EXISTS element_tags(
+	name TEXT,
+	tag TEXT,
+	PRIMARY KEY (name, tag),
+	FOREIGN KEY (name) REFERENCES elements(name)
+		ON UPDATE CASCADE ON DELETE CASCADE
+	);
+
+CREATE TABLE IF NOT EXISTS holding_presets(
+	preset_name TEXT,
+	element TEXT,
+	num REAL NOT NULL DEFAULT 0.0,
+	PRIMARY KEY (preset_name, element),
+	FOREIGN KEY (element) REFERENCES elements(name)
+		ON UPDATE CASCADE ON DELETE CASCADE
+	);
+
+-- calc_weight's results, valid while the holdings hash to holdings_hash.
+CREATE TABLE IF NOT EXISTS weight_cache(
+	element TEXT,
+	alpha REAL NOT NULL,
+	holdings_hash INTEGER NOT NULL,
+	weight REAL NOT NULL,
+	PRIMARY KEY (element, alpha),
+	FOREIGN KEY (element) REFERENCES elements(name)
+		ON UPDATE CASCADE ON DELETE CASCADE
+	);
+
+-- the fewest intermediates connecting two elements, valid until the recipes change.
+CREATE TABLE IF NOT EXISTS distance_cache(
+	from_element TEXT,
+	to_element TEXT,
+	steps INTEGER NOT NULL,
+	PRIMARY KEY (from_element, to_element),
+	FOREIGN KEY (from_element) REFERENCES elements(name)
+		ON UPDATE CASCADE ON DELETE CASCADE,
+	FOREIGN KEY (to_element) REFERENCES elements(name)
+		ON UPDATE CASCADE ON DELETE CASCADE
+	);
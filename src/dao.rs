@@ -1,6 +1,9 @@
+use std::collections::{HashMap, HashSet};
+
 use sqlx::{any::AnyRow, Row, AnyPool};
 
 use crate::recipes::{Element, ElementHandle};
+use crate::pathes::RelativesCache;
 
 #[derive(Debug, )]
 pub enum Errors {
@@ -9,6 +12,13 @@ pub enum Errors {
     },
     FetchedZeroRow(String),
     ElementNotFound(String),
+    InvalidBackup(String),
+    PresetNotFound(String),
+    CyclicRecipe {
+        name: String,
+        component: String,
+    },
+    DuplicateRecipe(String),
     SqlxError(sqlx::Error)
 }
 
@@ -35,12 +45,49 @@ impl std::fmt::Display for Errors {
             Errors::FetchedZeroRow(e) => {
                 write!(f, "Fetch zero rows: {e}")
             }
+            Errors::InvalidBackup(e) => {
+                write!(f, "Invalid backup: {e}")
+            }
+            Errors::PresetNotFound(e) => {
+                write!(f, "Preset: {e}")
+            }
+            Errors::CyclicRecipe { name, component } => {
+                write!(f, "Cyclic recipe: {component} is made of {name}")
+            }
+            Errors::DuplicateRecipe(name) => {
+                write!(f, "Duplicate recipe: {name} has the same recipe already")
+            }
         }
     }
 }
 
+/// The most edits a misspelling takes from the names `DAO::suggest_elements` gives for it.
+pub const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+/// the stored name of the element spelled like the parameter `param` ignoring case,
+/// the exact spelling first among case duplicates. The parameter is bound with `lookup_name`.
+fn stored_name(param: &str) -> String {
+    format!("(SELECT name FROM elements WHERE lower(name)=lower({param}) ORDER BY name<>{param}, name LIMIT 1)")
+}
+
+/// `ele`'s name without the surrounding whitespace users type.
+fn lookup_name(ele: &ElementHandle) -> String {
+    ele.get_name().trim().to_string()
+}
+
+/// A row whose foreign key finds no parent, see `DAO::check_foreign_keys`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub parent: String,
+    /// `rowid N` from sqlite, or `column = value` from the other backends.
+    pub row: String,
+}
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct DAO {
     database: AnyPool,
+    relatives_cache: RelativesCache,
 }
 
 impl DAO {
@@ -57,11 +104,37 @@ impl DAO {
 
         #[cfg(debug_assertions)]
         eprintln!("{_a:?}");
-        Self {
-            database
+        let dao = Self {
+            database,
+            relatives_cache: RelativesCache::default(),
+        };
+        dao.migrate().await.expect("Migrating the database failed.");
+        dao
+    }
+
+    /// add the columns newer versions of `stage1.sql` create to a database created by an older one,
+    /// `CREATE TABLE IF NOT EXISTS` leaves an existing table as it is.
+    pub async fn migrate(&self) -> Result<(), Errors> {
+        let mut columns = vec![];
+        for row in sqlx::query("PRAGMA table_info(elements_holding)")
+            .fetch_all(&self.database)
+            .await? {
+            columns.push(row.try_get::<String, _>("name")?);
         }
+        // no table yet, `stage1.sql` creates it whole.
+        if !columns.is_empty() && !columns.iter().any(|a| a == "last_updated") {
+            sqlx::raw_sql("ALTER TABLE elements_holding ADD COLUMN last_updated INTEGER")
+                .execute(&self.database)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub fn relatives_cache(&self) -> &RelativesCache {
+        &self.relatives_cache
     }
 
+    /// every mod's name, `UNKNOWN_MOD` for the elements without one.
     pub async fn list_mods(&self) -> Result<Vec<String>, Errors> {
         let res =
             sqlx::query(
@@ -72,7 +145,8 @@ impl DAO {
         let mut v = vec![];
         for x in res {
             v.push(
-                x.try_get::<String, _>("belongs_to_mod")?
+                x.try_get::<Option<String>, _>("belongs_to_mod")?
+                    .unwrap_or(crate::pathes::UNKNOWN_MOD.to_string())
             );
         }
         Ok(v)
@@ -101,6 +175,22 @@ impl DAO {
         Ok(v)
     }
 
+    /// every element's relatives, as `pathes::get_relatives` gives them one by one,
+    /// from a query of all the elements and one of all the recipes.
+    pub async fn load_full_adjacency(&self) -> Result<HashMap<ElementHandle, HashSet<ElementHandle>>, Errors> {
+        let mut adjacency = self.list_elements().await?
+            .into_iter()
+            .map(|e| (ElementHandle::from(e.name), HashSet::new()))
+            .collect::<HashMap<_, _>>();
+        for (name, ca, cb) in self.list_recipes().await? {
+            for component in [ca, cb] {
+                adjacency.entry(name.clone()).or_default().insert(component.clone());
+                adjacency.entry(component).or_default().insert(name.clone());
+            }
+        }
+        Ok(adjacency)
+    }
+
     pub async fn list_elements_holding(&self) -> Result<Vec<(ElementHandle, f64)>, Errors> {
         let res =
             sqlx::query(
@@ -143,35 +233,109 @@ impl DAO {
     }
 
     pub async fn does_element_exists(&self, ele: &ElementHandle) -> Result<bool, Errors> {
-        let res =
-            sqlx::query(
-                "SELECT count(*) as count_ FROM elements WHERE name=$1"
-            )
-            .bind(ele.get_name())
-            .fetch_one(&self.database)
+        Ok(self.find_element(ele).await?.is_some())
+    }
+
+    /// the element spelled like `ele` ignoring case and the surrounding whitespace, as stored.
+    /// The exact spelling is preferred among case duplicates.
+    pub async fn find_element(&self, ele: &ElementHandle) -> Result<Option<ElementHandle>, Errors> {
+        let res = sqlx::query(
+            "SELECT name FROM elements WHERE lower(name)=lower($1) ORDER BY name<>$1, name LIMIT 1"
+        )
+            .bind(lookup_name(ele))
+            .fetch_optional(&self.database)
             .await?;
+        Ok(match res {
+            Some(row) => Some(ElementHandle::from(row.try_get::<String, _>("name")?)),
+            None => None,
+        })
+    }
 
-        let count = res.try_get::<i64, _>("count_")?;
-        if count == 0 {
-            return Ok(false);
-        } else if count == 1 {
-            return Ok(true);
-        } else {
-            return Err(Errors::ExpectOneResult { table_name: "elements".to_string() });
-        }
+    /// the mod `ele` belongs to, None if it has no mod or doesn't exist.
+    pub async fn element_mod(&self, ele: &ElementHandle) -> Result<Option<String>, Errors> {
+        let res = sqlx::query(&format!(
+            "SELECT belongs_to_mod FROM elements WHERE name={}", stored_name("$1")
+        ))
+            .bind(lookup_name(ele))
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(match res {
+            Some(row) => row.try_get::<Option<String>, _>("belongs_to_mod")?,
+            None => None,
+        })
+    }
+
+    /// the element spelled like `ele` as stored, see `find_element`, `ElementNotFound` if none.
+    async fn stored_element(&self, ele: &ElementHandle) -> Result<ElementHandle, Errors> {
+        self.find_element(ele).await?
+            .ok_or_else(|| Errors::ElementNotFound(ele.get_name()))
+    }
+
+    /// the at most `limit` elements closest to the misspelled `partial`, ignoring case,
+    /// within `MAX_SUGGESTION_DISTANCE` edits. The closest first, then by name.
+    pub async fn suggest_elements(&self, partial: &str, limit: usize) -> Result<Vec<ElementHandle>, Errors> {
+        self.suggest_elements_within(partial, limit, MAX_SUGGESTION_DISTANCE).await
+    }
+
+    /// Same as `suggest_elements`, within `max_distance` edits.
+    pub async fn suggest_elements_within(&self, partial: &str, limit: usize, max_distance: usize)
+        -> Result<Vec<ElementHandle>, Errors> {
+            let partial = partial.trim().to_lowercase();
+            let mut ret = self.list_elements().await?
+                .into_iter()
+                .map(|e| (crate::math::levenshtein(&partial, &e.name.to_lowercase()), e.name))
+                .filter(|(d, _)| *d <= max_distance)
+                .collect::<Vec<_>>();
+            ret.sort();
+            Ok(ret.into_iter()
+                .take(limit)
+                .map(|(_, name)| ElementHandle::from(name))
+                .collect())
+    }
+
+    /// each of `names` as stored if it's an element, like `find_element`, in a single query.
+    pub async fn which_elements_exist(&self, names: &[&str])
+        -> Result<std::collections::HashMap<String, Option<ElementHandle>>, Errors> {
+            let mut ret = names.iter()
+                .map(|a| (a.to_string(), None))
+                .collect::<std::collections::HashMap<_, _>>();
+            if ret.is_empty() {
+                return Ok(ret);
+            }
+            let placeholders = (1..=names.len())
+                .map(|i| format!("lower(${i})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!("SELECT name FROM elements WHERE lower(name) IN ({placeholders}) ORDER BY name");
+            let mut query = sqlx::query(&sql);
+            for name in names {
+                query = query.bind(name.trim().to_string());
+            }
+            let stored = query.fetch_all(&self.database).await?
+                .into_iter()
+                .map(|x| x.try_get::<String, _>("name"))
+                .collect::<Result<Vec<_>, _>>()?;
+            for (name, found) in ret.iter_mut() {
+                let name = name.trim();
+                *found = stored.iter()
+                    .find(|a| a.as_str() == name)
+                    .or_else(|| stored.iter().find(|a| a.to_lowercase() == name.to_lowercase()))
+                    .map(|a| ElementHandle::from(a.clone()));
+            }
+            Ok(ret)
     }
 
     pub async fn get_element_base_value(&self, ele: &ElementHandle) -> Result<f64, Errors> {
         let res = 
-            sqlx::query(
-                "SELECT base_value FROM elements WHERE name=$1"
-            )
-            .bind(ele.get_name())
+            sqlx::query(&format!(
+                "SELECT base_value FROM elements WHERE name={}", stored_name("$1")
+            ))
+            .bind(lookup_name(ele))
             .fetch_all(&self.database)
             .await?;
 
         if res.len() == 1 {
-            let bv = res.get(0).unwrap().try_get::<f64, _>("base_value")
+            let bv = res.first().unwrap().try_get::<f64, _>("base_value")
                 .unwrap();
             Ok(bv)
         } else {
@@ -179,42 +343,129 @@ impl DAO {
         }
     }
 
+    /// an element without a holding row, a newly added one for example, holds 0.
     pub async fn get_element_num_holding(&self, handle: &ElementHandle) -> Result<f64, Errors> {
-        let res = sqlx::query(
-            "SELECT num FROM elements_holding WHERE name=$1"
-        )
-            .bind(handle.get_name())
+        let res = sqlx::query(&format!(
+            "SELECT num FROM elements_holding WHERE name={}", stored_name("$1")
+        ))
+            .bind(lookup_name(handle))
             .fetch_all(&self.database)
             .await?;
         if res.len() == 1 {
-            let r = res.get(0).unwrap();
+            let r = res.first().unwrap();
             let res = r.try_get::<f64, _>("num")
                 .unwrap();
-            let res = res.try_into()
-                .expect("The convertion from signed number from database to unsigned local type failed.");
-            return Ok(res);
+            Ok(res)
+        } else if res.is_empty() {
+            Ok(0.)
         } else {
-            return Err(Errors::ExpectOneResult { table_name: "elements_holding".to_string() });
+            Err(Errors::ExpectOneResult { table_name: "elements_holding".to_string() })
         }
     }
 
+    /// the unix seconds `handle`'s holding was last changed at, `None` if it never was.
+    pub async fn get_holding_last_updated(&self, handle: &ElementHandle) -> Result<Option<i64>, Errors> {
+        let res = sqlx::query(&format!(
+            "SELECT last_updated FROM elements_holding WHERE name={}", stored_name("$1")
+        ))
+            .bind(lookup_name(handle))
+            .fetch_optional(&self.database)
+            .await?;
+        Ok(match res {
+            Some(row) => row.try_get::<Option<i64>, _>("last_updated")?,
+            None => None,
+        })
+    }
+
     pub async fn change_element_holding(&self, handle: &ElementHandle, num: usize)
         -> Result<(), Errors> {
+            let Some(stored) = self.find_element(handle).await? else {
+                return Err(Errors::ElementNotFound(handle.get_name()));
+            };
             let num: i64 = num.try_into()
                 .expect("The convertion from local unsigned type to database's signed type failed.");
-            let res = sqlx::query(
-                "UPDATE elements_holding SET num=$1 WHERE name=$2"
+            sqlx::query(
+                "INSERT INTO elements_holding(name, num, last_updated) \
+                VALUES($1, $2, CAST(strftime('%s', 'now') AS INTEGER)) \
+                ON CONFLICT(name) DO UPDATE SET num=excluded.num, last_updated=excluded.last_updated"
             )
+                .bind(stored.get_name())
                 .bind(num)
-                .bind(handle.get_name())
                 .execute(&self.database)
                 .await?;
-            if res.rows_affected() == 1 {
-                return Ok(());
-            } else {
-                return Err(Errors::ExpectOneResult { table_name: "elements_holding".to_string() });
+            Ok(())
+        }
+
+    /// set all the `holdings` in one transaction, inserting the missing holding rows.
+    /// Returns how many holdings are set, the unknown elements are skipped.
+    pub async fn set_holdings_bulk(&self, holdings: &[(ElementHandle, usize)]) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        let mut n = 0;
+        for (handle, num) in holdings {
+            let num: i64 = (*num).try_into()
+                .expect("The convertion from local unsigned type to database's signed type failed.");
+            let mut res = sqlx::query(
+                "UPDATE elements_holding SET num=$1, last_updated=CAST(strftime('%s', 'now') AS INTEGER) WHERE name=$2"
+            )
+                .bind(num)
+                .bind(handle.get_name())
+                .execute(&mut *tx)
+                .await?;
+            if res.rows_affected() == 0 {
+                res = sqlx::query(
+                    "INSERT INTO elements_holding(name, num, last_updated) SELECT name, $1, CAST(strftime('%s', 'now') AS INTEGER) FROM elements WHERE name=$2"
+                )
+                    .bind(num)
+                    .bind(handle.get_name())
+                    .execute(&mut *tx)
+                    .await?;
             }
+            n += res.rows_affected();
         }
+        tx.commit().await?;
+        Ok(n)
+    }
+
+    /// set all the `holdings` in one transaction, all or none of them: an unknown element
+    /// rolls them back. Returns how many holdings are set.
+    pub async fn import_holdings(&self, holdings: &[(ElementHandle, f64)]) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        let mut n = 0;
+        for (handle, num) in holdings {
+            let res = sqlx::query(&format!(
+                "INSERT INTO elements_holding(name, num, last_updated) \
+                SELECT name, $2, CAST(strftime('%s', 'now') AS INTEGER) FROM elements WHERE name={} \
+                ON CONFLICT(name) DO UPDATE SET num=excluded.num, last_updated=excluded.last_updated",
+                stored_name("$1")
+            ))
+                .bind(lookup_name(handle))
+                .bind(*num)
+                .execute(&mut *tx)
+                .await?;
+            if res.rows_affected() == 0 {
+                return Err(Errors::ElementNotFound(handle.get_name()));
+            }
+            n += res.rows_affected();
+        }
+        tx.commit().await?;
+        Ok(n)
+    }
+
+    /// set every element's holding to 0 in one transaction, inserting the missing holding rows.
+    /// Returns how many holdings are reset.
+    pub async fn reset_all_holdings(&self) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        // `WHERE true` tells sqlite's parser the `ON CONFLICT` isn't a join's.
+        let res = sqlx::query(
+            "INSERT INTO elements_holding(name, num, last_updated) \
+            SELECT name, 0.0, CAST(strftime('%s', 'now') AS INTEGER) FROM elements WHERE true \
+            ON CONFLICT(name) DO UPDATE SET num=excluded.num, last_updated=excluded.last_updated"
+        )
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(res.rows_affected())
+    }
 
     pub async fn get_primary_elements(&self, ) -> Result<Vec<ElementHandle>, Errors> {
         let res = sqlx::query(
@@ -232,56 +483,64 @@ impl DAO {
         Ok(v)
     }
 
+    /// the elements without a recipe that aren't in `PRIMAL_ASPECTS`, their recipes were most
+    /// likely lost in the import. `get_primary_elements` can't tell them from the true primals.
+    pub async fn find_undecomposable_non_primals(&self) -> Result<Vec<ElementHandle>, Errors> {
+        let mut v = self.get_primary_elements().await?
+            .into_iter()
+            .filter(|e| !crate::recipes::PRIMAL_ASPECTS.contains(&e.get_name().as_str()))
+            .collect::<Vec<_>>();
+        v.sort();
+        Ok(v)
+    }
+
     pub async fn is_primary_element(&self, handle: &ElementHandle) -> Result<bool, Errors> {
-        let res = sqlx::query(
-            "SELECT count(*) as num FROM recipes WHERE name=$1"
-        )
-            .bind(handle.get_name())
+        let res = sqlx::query(&format!(
+            "SELECT count(*) as num FROM recipes WHERE name={}", stored_name("$1")
+        ))
+            .bind(lookup_name(handle))
             .fetch_one(&self.database)
             .await?;
         let num = res.try_get::<i64, _>("num")
             .expect("Read count function's column `num` failed.");
-        return Ok(num == 0);
+        Ok(num == 0)
     }
 
-    pub async fn get_element_components(&self, handle: &ElementHandle)
-        -> Result<(ElementHandle, ElementHandle), Errors> {
-        //       let (component_a, component_b);
+    /// every recipe of `handle`, sorted. None for a primary element.
+    pub async fn get_all_element_components(&self, handle: &ElementHandle)
+        -> Result<Vec<(ElementHandle, ElementHandle)>, Errors> {
         let a: Vec<AnyRow> =
-            sqlx::query("SELECT component_a,component_b FROM recipes WHERE name=?",)
-            .bind(handle.get_name())
+            sqlx::query(&format!("SELECT component_a,component_b FROM recipes WHERE name={} \
+                ORDER BY component_a, component_b", stored_name("$1")))
+            .bind(lookup_name(handle))
             .fetch_all(&self.database)
             .await?;
-
-        if a.len() == 1 {
-            let r = a.get(0).unwrap();
-            let component_a: String = r
-                .try_get("component_a")
-                .unwrap();
-            let component_b: String = r
-                .try_get("component_b")
-                .unwrap();
-            return Ok((
-                    ElementHandle::from(component_a),
-                    ElementHandle::from(component_b)));
-        } else if a.len() == 0 {
-            return Err(
-                Errors::FetchedZeroRow(handle.get_name())
-            )
-        } else {
-            return Err(
-                Errors::ExpectOneResult { table_name: "recipes".to_string() }
-            )
+        let mut ret = vec![];
+        for r in a {
+            let component_a: String = r.try_get("component_a")?;
+            let component_b: String = r.try_get("component_b")?;
+            ret.push((ElementHandle::from(component_a), ElementHandle::from(component_b)));
         }
+        Ok(ret)
+    }
+
+    /// the first of `get_all_element_components`, most elements have just the one recipe.
+    #[allow(dead_code)]
+    pub async fn get_element_components(&self, handle: &ElementHandle)
+        -> Result<(ElementHandle, ElementHandle), Errors> {
+        self.get_all_element_components(handle).await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Errors::FetchedZeroRow(handle.get_name()))
     }
 
     pub async fn get_what_component_can_build(&self, component: &ElementHandle)
         -> Result<Vec<ElementHandle>, Errors> {
         let mut res = Vec::new();
-        let res1 = sqlx::query(
-            "SELECT name FROM recipes WHERE component_a=$1"
-        )
-            .bind(component.get_name())
+        let res1 = sqlx::query(&format!(
+            "SELECT name FROM recipes WHERE component_a={}", stored_name("$1")
+        ))
+            .bind(lookup_name(component))
             .fetch_all(&self.database)
             .await?;
 
@@ -289,10 +548,10 @@ impl DAO {
             res1.iter().map(|a| a.try_get::<String, _>("name").unwrap())
         );
 
-        let res1 = sqlx::query(
-            "SELECT name FROM recipes WHERE component_b=$1"
-        )
-            .bind(component.get_name())
+        let res1 = sqlx::query(&format!(
+            "SELECT name FROM recipes WHERE component_b={}", stored_name("$1")
+        ))
+            .bind(lookup_name(component))
             .fetch_all(&self.database)
             .await?;
 
@@ -301,4 +560,1098 @@ impl DAO {
         );
         Ok(res.iter().map(|a| ElementHandle::from(a.clone())).collect())
     }
+
+    /// the elements never used as a component of any recipe.
+    pub async fn find_terminal_products(&self) -> Result<Vec<ElementHandle>, Errors> {
+        let res = sqlx::query(
+            "SELECT name FROM elements WHERE \
+            name NOT IN (SELECT component_a FROM recipes WHERE component_a IS NOT NULL) \
+            AND name NOT IN (SELECT component_b FROM recipes WHERE component_b IS NOT NULL) \
+            ORDER BY name"
+        )
+            .fetch_all(&self.database)
+            .await?;
+
+        let mut v = vec![];
+        for x in res {
+            v.push(ElementHandle::from(x.try_get::<String, _>("name")?));
+        }
+        Ok(v)
+    }
+
+    /// what `a` and `b` combine into, in either order. The first by name if several recipes do.
+    pub async fn product_of(&self, a_handle: &ElementHandle, b_handle: &ElementHandle)
+        -> Result<Option<ElementHandle>, Errors> {
+            let (a, b) = (stored_name("$1"), stored_name("$2"));
+            let res = sqlx::query(&format!(
+                "SELECT name FROM recipes WHERE (component_a={a} AND component_b={b}) \
+                OR (component_a={b} AND component_b={a}) ORDER BY name LIMIT 1"
+            ))
+                .bind(lookup_name(a_handle))
+                .bind(lookup_name(b_handle))
+                .fetch_optional(&self.database)
+                .await?;
+            Ok(match res {
+                Some(row) => Some(ElementHandle::from(row.try_get::<String, _>("name")?)),
+                None => None,
+            })
+    }
+
+    /// whether `target` is `ele` or appears anywhere in `ele`'s decomposition.
+    pub async fn decomposition_contains(&self, ele: &ElementHandle, target: &ElementHandle)
+        -> Result<bool, Errors> {
+            // UNION rather than UNION ALL, so it ends even on a cyclic recipe.
+            let res = sqlx::query(
+                "WITH RECURSIVE decomposition(name) AS ( \
+                    SELECT $1 \
+                    UNION SELECT recipes.component_a FROM recipes JOIN decomposition ON recipes.name=decomposition.name \
+                    UNION SELECT recipes.component_b FROM recipes JOIN decomposition ON recipes.name=decomposition.name \
+                ) SELECT count(*) AS num FROM decomposition WHERE name=$2"
+            )
+                .bind(ele.get_name())
+                .bind(target.get_name())
+                .fetch_one(&self.database)
+                .await?;
+            Ok(res.try_get::<i64, _>("num")? != 0)
+    }
+
+    /// insert the recipe `name = component_a + component_b`.
+    /// All three elements must exist, `name` mustn't have the same recipe already,
+    /// and neither component may be made of `name`.
+    pub async fn add_recipe(&self, name: &ElementHandle,
+        component_a: &ElementHandle, component_b: &ElementHandle) -> Result<(), Errors> {
+            let name = &self.stored_element(name).await?;
+            let component_a = &self.stored_element(component_a).await?;
+            let component_b = &self.stored_element(component_b).await?;
+            let same = |(a, b): &(ElementHandle, ElementHandle)|
+                (a == component_a && b == component_b) || (a == component_b && b == component_a);
+            if self.get_all_element_components(name).await?.iter().any(same) {
+                return Err(Errors::DuplicateRecipe(name.get_name()));
+            }
+            for component in [component_a, component_b] {
+                if self.decomposition_contains(component, name).await? {
+                    return Err(Errors::CyclicRecipe {
+                        name: name.get_name(),
+                        component: component.get_name(),
+                    });
+                }
+            }
+
+            sqlx::query(
+                "INSERT INTO recipes(name, component_a, component_b) VALUES($1, $2, $3)"
+            )
+                .bind(name.get_name())
+                .bind(component_a.get_name())
+                .bind(component_b.get_name())
+                .execute(&self.database)
+                .await?;
+            self.relatives_cache.clear().await;
+            // the weights depend on the decompositions.
+            self.clear_weight_cache().await?;
+            // so do the relatives.
+            self.clear_distance_cache().await?;
+            Ok(())
+    }
+
+    /// delete every recipe of `name`, it's primal afterwards.
+    pub async fn remove_recipe(&self, name: &ElementHandle) -> Result<(), Errors> {
+        let res = sqlx::query(&format!("DELETE FROM recipes WHERE name={}", stored_name("$1")))
+            .bind(lookup_name(name))
+            .execute(&self.database)
+            .await?;
+        if res.rows_affected() == 0 {
+            return Err(Errors::FetchedZeroRow(name.get_name()));
+        }
+        self.relatives_cache.clear().await;
+        self.clear_weight_cache().await?;
+        self.clear_distance_cache().await?;
+        Ok(())
+    }
+
+    /// the groups of elements whose names are equal ignoring case, each and the groups sorted.
+    pub async fn find_case_duplicate_elements(&self) -> Result<Vec<Vec<ElementHandle>>, Errors> {
+        let res = sqlx::query(
+            "SELECT name FROM elements WHERE lower(name) IN \
+            (SELECT lower(name) FROM elements GROUP BY lower(name) HAVING count(*) > 1) \
+            ORDER BY lower(name), name"
+        )
+            .fetch_all(&self.database)
+            .await?;
+        let mut groups: Vec<Vec<ElementHandle>> = vec![];
+        for x in res {
+            let name = x.try_get::<String, _>("name")?;
+            match groups.last_mut() {
+                Some(g) if g[0].get_name().to_lowercase() == name.to_lowercase() => g.push(ElementHandle::from(name)),
+                _ => groups.push(vec![ElementHandle::from(name)]),
+            }
+        }
+        Ok(groups)
+    }
+
+    /// merge the elements spelled like `canonical` ignoring case into it, all or none of them:
+    /// their recipes, tags and presets are repointed to it, their holdings added to its,
+    /// then they're deleted. Returns how many elements are merged.
+    pub async fn merge_case_duplicates(&self, canonical: &ElementHandle) -> Result<u64, Errors> {
+        let canonical = self.stored_element(canonical).await?.get_name();
+        let mut tx = self.database.begin().await?;
+        let res = sqlx::query(
+            "SELECT name FROM elements WHERE lower(name)=lower($1) AND name<>$1"
+        )
+            .bind(&canonical)
+            .fetch_all(&mut *tx)
+            .await?;
+        let mut duplicates = vec![];
+        for x in res {
+            duplicates.push(x.try_get::<String, _>("name")?);
+        }
+        if duplicates.is_empty() {
+            return Ok(0);
+        }
+
+        for dup in &duplicates {
+            for sql in [
+                "UPDATE recipes SET name=$1 WHERE name=$2",
+                "UPDATE recipes SET component_a=$1 WHERE component_a=$2",
+                "UPDATE recipes SET component_b=$1 WHERE component_b=$2",
+                "UPDATE OR IGNORE element_tags SET name=$1 WHERE name=$2",
+                "UPDATE OR IGNORE holding_presets SET element=$1 WHERE element=$2",
+            ] {
+                sqlx::query(sql)
+                    .bind(&canonical)
+                    .bind(dup)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query(
+                "INSERT INTO elements_holding(name, num, last_updated) \
+                SELECT $1, num, last_updated FROM elements_holding WHERE name=$2 \
+                ON CONFLICT(name) DO UPDATE SET num = elements_holding.num + excluded.num"
+            )
+                .bind(&canonical)
+                .bind(dup)
+                .execute(&mut *tx)
+                .await?;
+            // the leftover tags, presets and holdings go along.
+            sqlx::query("DELETE FROM elements WHERE name=$1")
+                .bind(dup)
+                .execute(&mut *tx)
+                .await?;
+        }
+        // the recipes now identical.
+        sqlx::query(
+            "DELETE FROM recipes WHERE rowid NOT IN \
+            (SELECT min(rowid) FROM recipes GROUP BY name, component_a, component_b)"
+        )
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM weight_cache")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM distance_cache")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.relatives_cache.clear().await;
+        Ok(duplicates.len() as u64)
+    }
+
+    /// the recipes ordered so that every component made by a recipe is made before it's used.
+    pub async fn recipes_topologically_sorted(&self)
+        -> Result<Vec<(ElementHandle, ElementHandle, ElementHandle)>, Errors> {
+            use std::collections::{HashMap, HashSet};
+
+            let mut remaining = self.list_recipes().await?;
+            remaining.sort();
+            let mut unmade = HashMap::<ElementHandle, usize>::new();
+            for (name, _, _) in &remaining {
+                *unmade.entry(name.clone()).or_default() += 1;
+            }
+            // an element is available once it's a primary one, or its first recipe is placed.
+            let mut made = HashSet::new();
+            let mut sorted = Vec::with_capacity(remaining.len());
+            while !remaining.is_empty() {
+                let available = |e: &ElementHandle| made.contains(e) || !unmade.contains_key(e);
+                let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter()
+                    .partition(|(_, ca, cb)| available(ca) && available(cb));
+                if ready.is_empty() {
+                    let (name, ca, cb) = &blocked[0];
+                    let component = if available(ca) { cb } else { ca };
+                    return Err(Errors::CyclicRecipe {
+                        name: name.get_name(),
+                        component: component.get_name(),
+                    });
+                }
+                for recipe in ready {
+                    made.insert(recipe.0.clone());
+                    sorted.push(recipe);
+                }
+                remaining = blocked;
+            }
+            Ok(sorted)
+    }
+
+    /// the rows referencing a nonexistent parent, which the database let in while its
+    /// foreign keys weren't enforced. On sqlite it's `PRAGMA foreign_key_check`, elsewhere
+    /// every reference to `elements(name)` of `stage1.sql` is joined by hand.
+    pub async fn check_foreign_keys(&self) -> Result<Vec<ForeignKeyViolation>, Errors> {
+        let mut conn = self.database.acquire().await?;
+        let mut violations = vec![];
+        if conn.backend_name() == "SQLite" {
+            for row in sqlx::query("PRAGMA foreign_key_check").fetch_all(&mut *conn).await? {
+                let rowid = row.try_get::<Option<i64>, _>(1)?;
+                violations.push(ForeignKeyViolation {
+                    table: row.try_get::<String, _>(0)?,
+                    parent: row.try_get::<String, _>(2)?,
+                    row: rowid.map(|id| format!("rowid {id}")).unwrap_or("without rowid".to_string()),
+                });
+            }
+        } else {
+            const REFERENCES: [(&str, &str); 9] = [
+                ("recipes", "name"), ("recipes", "component_a"), ("recipes", "component_b"),
+                ("elements_holding", "name"), ("element_tags", "name"),
+                ("holding_presets", "element"), ("weight_cache", "element"),
+                ("distance_cache", "from_element"), ("distance_cache", "to_element"),
+            ];
+            for (table, column) in REFERENCES {
+                let query = format!("SELECT t.{column} AS value FROM {table} t
+                    LEFT JOIN elements e ON e.name = t.{column}
+                    WHERE t.{column} IS NOT NULL AND e.name IS NULL
+                    ORDER BY t.{column}");
+                for row in sqlx::query(&query).fetch_all(&mut *conn).await? {
+                    violations.push(ForeignKeyViolation {
+                        table: table.to_string(),
+                        parent: "elements".to_string(),
+                        row: format!("{column} = {}", row.try_get::<String, _>("value")?),
+                    });
+                }
+            }
+        }
+        Ok(violations)
+    }
+
+    /// the elements and recipes as sql `INSERT`s in the format of `sql/aspects_4.2.3.5.sql`,
+    /// ordered so that they can be read into an empty database with the foreign keys checked.
+    pub async fn export_sql(&self) -> Result<String, Errors> {
+        use std::collections::HashMap;
+        use std::fmt::Write;
+
+        let quote = |a: &str| format!("'{}'", a.replace('\'', "''"));
+        let insert_element = |out: &mut String, e: &Element| {
+            let _ = writeln!(out, "INSERT INTO elements(name, belongs_to_mod, base_value) VALUES({}, {}, {:?});",
+                quote(&e.name),
+                e.belongs_to_mod.as_deref().map(quote).unwrap_or("NULL".to_string()),
+                e.base_value);
+        };
+
+        let recipes = self.recipes_topologically_sorted().await?;
+        let mut elements = self.list_elements().await?
+            .into_iter()
+            .map(|e| (ElementHandle::from(e.name.clone()), e))
+            .collect::<HashMap<_, _>>();
+        let mut out = String::new();
+        let mut primaries = elements.keys()
+            .filter(|e| !recipes.iter().any(|(name, _, _)| name == *e))
+            .cloned()
+            .collect::<Vec<_>>();
+        primaries.sort();
+        for e in primaries {
+            insert_element(&mut out, &elements.remove(&e).unwrap());
+        }
+        for (name, ca, cb) in recipes {
+            if let Some(e) = elements.remove(&name) {
+                insert_element(&mut out, &e);
+            }
+            let _ = writeln!(out, "INSERT INTO recipes(name, component_a, component_b) VALUES({}, {}, {});",
+                quote(&name.get_name()), quote(&ca.get_name()), quote(&cb.get_name()));
+        }
+        Ok(out)
+    }
+
+    /// the weight cached for `ele` with `alpha`, if it was calculated for the holdings hashing to `holdings_hash`.
+    pub async fn get_cached_weight(&self, ele: &ElementHandle, alpha: f64, holdings_hash: u64)
+        -> Result<Option<f64>, Errors> {
+            let res = sqlx::query(
+                "SELECT weight FROM weight_cache WHERE element=$1 AND alpha=$2 AND holdings_hash=$3"
+            )
+                .bind(ele.get_name())
+                .bind(alpha)
+                .bind(holdings_hash as i64)
+                .fetch_optional(&self.database)
+                .await?;
+            Ok(match res {
+                Some(row) => Some(row.try_get::<f64, _>("weight")?),
+                None => None,
+            })
+    }
+
+    /// cache `ele`'s weight with `alpha`, replacing the one of other holdings.
+    pub async fn cache_weight(&self, ele: &ElementHandle, alpha: f64, holdings_hash: u64, weight: f64)
+        -> Result<(), Errors> {
+            sqlx::query(
+                "INSERT OR REPLACE INTO weight_cache(element, alpha, holdings_hash, weight) VALUES($1, $2, $3, $4)"
+            )
+                .bind(ele.get_name())
+                .bind(alpha)
+                .bind(holdings_hash as i64)
+                .bind(weight)
+                .execute(&self.database)
+                .await?;
+            Ok(())
+    }
+
+    /// Returns how many cached weights are removed.
+    pub async fn clear_weight_cache(&self) -> Result<u64, Errors> {
+        let res = sqlx::query("DELETE FROM weight_cache")
+            .execute(&self.database)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// replace the cached distances with `distances`, each `(from, to, steps)`.
+    /// Returns how many distances are stored.
+    pub async fn store_distances(&self, distances: &[(ElementHandle, ElementHandle, usize)]) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        sqlx::query("DELETE FROM distance_cache")
+            .execute(&mut *tx)
+            .await?;
+        let mut n = 0;
+        for (from, to, steps) in distances {
+            let steps: i64 = (*steps).try_into()
+                .expect("The convertion from local unsigned type to database's signed type failed.");
+            n += sqlx::query(
+                "INSERT INTO distance_cache(from_element, to_element, steps) VALUES($1, $2, $3)"
+            )
+                .bind(from.get_name())
+                .bind(to.get_name())
+                .bind(steps)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(n)
+    }
+
+    /// the fewest intermediates from `from` to `to`, if they're cached.
+    pub async fn get_cached_distance(&self, from: &ElementHandle, to: &ElementHandle)
+        -> Result<Option<usize>, Errors> {
+            let res = sqlx::query(
+                "SELECT steps FROM distance_cache WHERE from_element=$1 AND to_element=$2"
+            )
+                .bind(from.get_name())
+                .bind(to.get_name())
+                .fetch_optional(&self.database)
+                .await?;
+            Ok(match res {
+                Some(row) => Some(row.try_get::<i64, _>("steps")? as usize),
+                None => None,
+            })
+    }
+
+    /// Returns how many cached distances are removed.
+    pub async fn clear_distance_cache(&self) -> Result<u64, Errors> {
+        let res = sqlx::query("DELETE FROM distance_cache")
+            .execute(&self.database)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// snapshot the current holdings as the preset `preset_name`, replacing the one of the same name.
+    /// Returns how many holdings are saved.
+    pub async fn save_preset(&self, preset_name: &str) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        sqlx::query("DELETE FROM holding_presets WHERE preset_name=$1")
+            .bind(preset_name)
+            .execute(&mut *tx)
+            .await?;
+        let res = sqlx::query(
+            "INSERT INTO holding_presets(preset_name, element, num) SELECT $1, name, num FROM elements_holding"
+        )
+            .bind(preset_name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(res.rows_affected())
+    }
+
+    /// set the holdings to the ones saved in the preset `preset_name`, all or none of them.
+    /// The holdings not in the preset are set to 0. Returns how many holdings are restored.
+    pub async fn load_preset(&self, preset_name: &str) -> Result<u64, Errors> {
+        let mut tx = self.database.begin().await?;
+        let saved = sqlx::query("SELECT count(*) AS num FROM holding_presets WHERE preset_name=$1")
+            .bind(preset_name)
+            .fetch_one(&mut *tx)
+            .await?
+            .try_get::<i64, _>("num")?;
+        if saved == 0 {
+            return Err(Errors::PresetNotFound(preset_name.to_string()));
+        }
+        sqlx::query(
+            "UPDATE elements_holding SET num=0.0, last_updated=CAST(strftime('%s', 'now') AS INTEGER) \
+            WHERE name NOT IN (SELECT element FROM holding_presets WHERE preset_name=$1)"
+        )
+            .bind(preset_name)
+            .execute(&mut *tx)
+            .await?;
+        let res = sqlx::query(
+            "INSERT INTO elements_holding(name, num, last_updated) \
+            SELECT element, num, CAST(strftime('%s', 'now') AS INTEGER) FROM holding_presets WHERE preset_name=$1 \
+            ON CONFLICT(name) DO UPDATE SET num=excluded.num, last_updated=excluded.last_updated"
+        )
+            .bind(preset_name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(res.rows_affected())
+    }
+
+    /// the presets' names and how many holdings each has.
+    pub async fn list_presets(&self) -> Result<Vec<(String, i64)>, Errors> {
+        let res = sqlx::query(
+            "SELECT preset_name, count(*) AS num FROM holding_presets GROUP BY preset_name ORDER BY preset_name"
+        )
+            .fetch_all(&self.database)
+            .await?;
+        let mut v = vec![];
+        for x in res {
+            v.push((x.try_get::<String, _>("preset_name")?, x.try_get::<i64, _>("num")?));
+        }
+        Ok(v)
+    }
+
+    /// write a copy of the whole database to the file `to`, which mustn't exist.
+    pub async fn backup(&self, to: &str) -> Result<(), Errors> {
+        sqlx::query("VACUUM INTO $1")
+            .bind(to)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    /// replace every table's content with the one in the backup file `from`.
+    /// The backup must pass sqlite's integrity check and have the tables of `stage1.sql`.
+    pub async fn restore(&self, from: &str) -> Result<(), Errors> {
+        use sqlx::Connection;
+        // ATTACH is per connection, so do everything on one.
+        let mut conn = self.database.acquire().await?;
+        sqlx::query("ATTACH DATABASE $1 AS restored")
+            .bind(from)
+            .execute(&mut *conn)
+            .await?;
+
+        let res = async {
+            let check = sqlx::query("PRAGMA restored.integrity_check")
+                .fetch_one(&mut *conn)
+                .await?
+                .try_get::<String, _>(0)?;
+            if check != "ok" {
+                return Err(Errors::InvalidBackup(check));
+            }
+            let tables_of = |schema: &str| format!(
+                "SELECT name FROM {schema}.sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'");
+            let mut tables = vec![];
+            for row in sqlx::query(&tables_of("main")).fetch_all(&mut *conn).await? {
+                tables.push(row.try_get::<String, _>("name")?);
+            }
+            let mut restored_tables = vec![];
+            for row in sqlx::query(&tables_of("restored")).fetch_all(&mut *conn).await? {
+                restored_tables.push(row.try_get::<String, _>("name")?);
+            }
+            for required in ["elements", "recipes", "elements_holding"] {
+                if !restored_tables.iter().any(|a| a == required) {
+                    return Err(Errors::InvalidBackup(format!("table {required} is missing")));
+                }
+            }
+
+            // an older backup can miss the columns added since, those take their defaults.
+            let mut copies = vec![];
+            for table in tables.iter().filter(|a| restored_tables.contains(a)) {
+                let mut columns_of = vec![];
+                for schema in ["main", "restored"] {
+                    let mut columns = vec![];
+                    for row in sqlx::query(&format!("PRAGMA {schema}.table_info(\"{table}\")"))
+                        .fetch_all(&mut *conn).await? {
+                        columns.push(row.try_get::<String, _>("name")?);
+                    }
+                    columns_of.push(columns);
+                }
+                let common = columns_of[0].iter()
+                    .filter(|a| columns_of[1].contains(a))
+                    .map(|a| format!("\"{a}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                copies.push(format!("INSERT INTO main.\"{table}\"({common}) SELECT {common} FROM restored.\"{table}\""));
+            }
+
+            let mut tx = conn.begin().await?;
+            sqlx::raw_sql("PRAGMA defer_foreign_keys = ON").execute(&mut *tx).await?;
+            for table in &tables {
+                sqlx::raw_sql(&format!("DELETE FROM main.\"{table}\"")).execute(&mut *tx).await?;
+            }
+            for copy in &copies {
+                sqlx::raw_sql(copy).execute(&mut *tx).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        }.await;
+
+        sqlx::raw_sql("DETACH DATABASE restored").execute(&mut *conn).await?;
+        self.relatives_cache.clear().await;
+        res
+    }
+
+    pub async fn add_tag(&self, handle: &ElementHandle, tag: &str) -> Result<(), Errors> {
+        let handle = self.stored_element(handle).await?;
+        sqlx::query(
+            "INSERT INTO element_tags(name, tag) VALUES($1, $2) ON CONFLICT DO NOTHING"
+        )
+            .bind(handle.get_name())
+            .bind(tag)
+            .execute(&self.database)
+            .await?;
+        Ok(())
+    }
+
+    /// returns false if the element wasn't tagged with `tag`.
+    pub async fn remove_tag(&self, handle: &ElementHandle, tag: &str) -> Result<bool, Errors> {
+        let res = sqlx::query(&format!(
+            "DELETE FROM element_tags WHERE name={} AND tag=$2", stored_name("$1")
+        ))
+            .bind(lookup_name(handle))
+            .bind(tag)
+            .execute(&self.database)
+            .await?;
+        Ok(res.rows_affected() != 0)
+    }
+
+    pub async fn list_by_tag(&self, tag: &str) -> Result<Vec<ElementHandle>, Errors> {
+        let res = sqlx::query(
+            "SELECT name FROM element_tags WHERE tag=$1 ORDER BY name"
+        )
+            .bind(tag)
+            .fetch_all(&self.database)
+            .await?;
+
+        let mut v = vec![];
+        for x in res {
+            v.push(ElementHandle::from(x.try_get::<String, _>("name")?));
+        }
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::DAO;
+    use crate::recipes::ElementHandle;
+
+    use std::sync::LazyLock;
+
+    static INIT_SQLX_DRIVERS: LazyLock<()> = LazyLock::new(|| {
+        sqlx::any::install_default_drivers();
+    });
+
+    pub(crate) const STAGE1: &str = include_str!("../sql/stage1.sql");
+    pub(crate) const ASPECTS_4_2_3_5: &str = include_str!("../sql/aspects_4.2.3.5.sql");
+
+    /// Three aspects of the mod `Isolated` linked only among themselves, Duo = Solus + Unus.
+    pub(crate) const ISOLATED: &str = "
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Solus', 'Isolated', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Unus', 'Isolated', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Duo', 'Isolated', 1.0);
+        INSERT INTO recipes(name, component_a, component_b) VALUES('Duo', 'Solus', 'Unus');
+        ";
+
+    /// Nodus hubs three compounds each made of it and a primal, the only aspects.
+    pub(crate) const NODUS_HUB: &str = "
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Nodus', 'Test', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ventus', 'Test', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+        INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Humus', 'Test', 1.0);
+        INSERT INTO recipes(name, component_a, component_b) VALUES('Ventus', 'Nodus', 'Aer');
+        INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Nodus', 'Ignis');
+        INSERT INTO recipes(name, component_a, component_b) VALUES('Humus', 'Nodus', 'Terra');
+        ";
+
+    /// A private in-memory database with the tables of `stage1.sql`, then `seed` executed.
+    pub(crate) async fn memory_dao(seed: &str) -> DAO {
+        seeded_dao("sqlite::memory:", seed).await
+    }
+
+    /// Same as `memory_dao`, but backed by a new file at `path`.
+    pub(crate) async fn file_dao(path: &str, seed: &str) -> DAO {
+        let _ = std::fs::remove_file(path);
+        seeded_dao(&format!("sqlite://{path}?mode=rwc"), seed).await
+    }
+
+    async fn seeded_dao(url: &str, seed: &str) -> DAO {
+        let _ = &*INIT_SQLX_DRIVERS;
+        // every connection of an in-memory sqlite is a new database, so keep exactly one.
+        let database = sqlx::any::AnyPoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect(url)
+            .await
+            .expect("seeded database");
+        sqlx::raw_sql("PRAGMA foreign_keys = ON")
+            .execute(&database)
+            .await
+            .expect("PRAGMA");
+        sqlx::raw_sql(STAGE1).execute(&database).await.expect("stage1.sql");
+        sqlx::raw_sql(seed).execute(&database).await.expect("seed");
+        DAO { database, relatives_cache: Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_load_full_adjacency() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let adjacency = dao.load_full_adjacency().await.expect("1");
+        let elements = dao.list_elements().await.expect("2");
+        assert_eq!(adjacency.len(), elements.len());
+        for e in elements {
+            let ele = ElementHandle::from(e.name);
+            assert_eq!(adjacency[&ele], crate::pathes::get_relatives(&dao, &ele).await.expect("3"), "{ele:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_undecomposable_non_primals() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        assert_eq!(dao.find_undecomposable_non_primals().await.expect("1"), vec![]);
+
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            DELETE FROM recipes WHERE name = 'Lux';
+            ")).await;
+        assert_eq!(dao.find_undecomposable_non_primals().await.expect("2"),
+            vec![ElementHandle::from("Lux")]);
+    }
+
+    #[tokio::test]
+    async fn test_check_foreign_keys() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        assert_eq!(dao.check_foreign_keys().await.expect("1"), vec![]);
+
+        // inserted behind the enforcement's back
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            PRAGMA foreign_keys = OFF;
+            INSERT INTO elements_holding(name, num) VALUES('Nonexistentia', 3);
+            PRAGMA foreign_keys = ON;
+            ")).await;
+        let violations = dao.check_foreign_keys().await.expect("2");
+        assert_eq!(violations.len(), 1, "{violations:?}");
+        assert_eq!(violations[0].table, "elements_holding");
+        assert_eq!(violations[0].parent, "elements");
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let lux = ElementHandle::from("Lux");
+        let aer = ElementHandle::from("Aer");
+        dao.add_tag(&lux, "ignore").await.expect("1");
+        dao.add_tag(&aer, "ignore").await.expect("1");
+        // tagging twice is harmless
+        dao.add_tag(&aer, "ignore").await.expect("1");
+        dao.add_tag(&aer, "need").await.expect("1");
+        assert_eq!(dao.list_by_tag("ignore").await.expect("1"), vec![aer.clone(), lux.clone()]);
+        assert_eq!(dao.list_by_tag("need").await.expect("1"), vec![aer.clone()]);
+
+        assert!(dao.remove_tag(&aer, "ignore").await.expect("1"));
+        assert!(!dao.remove_tag(&aer, "ignore").await.expect("1"));
+        assert_eq!(dao.list_by_tag("ignore").await.expect("1"), vec![lux.clone()]);
+
+        assert!(matches!(dao.add_tag(&ElementHandle::from("Nope"), "need").await,
+            Err(super::Errors::ElementNotFound(..))));
+
+        // spelled in another case, the stored name is tagged.
+        dao.add_tag(&ElementHandle::from(" aer "), "typed").await.expect("2");
+        assert_eq!(dao.list_by_tag("typed").await.expect("2"), vec![aer.clone()]);
+        assert!(dao.remove_tag(&ElementHandle::from("AER"), "typed").await.expect("3"));
+        assert_eq!(dao.list_by_tag("typed").await.expect("3"), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_find_terminal_products() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let terminals = dao.find_terminal_products().await.expect("1");
+        // Superbia = Volatus + Vacuos, and nothing is made of Superbia
+        assert!(terminals.contains(&ElementHandle::from("Superbia")));
+        assert!(!terminals.contains(&ElementHandle::from("Volatus")));
+        assert!(!terminals.contains(&ElementHandle::from("Aer")));
+    }
+
+    /// a path in the temp directory unique to this test run.
+    pub(crate) fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("t4ach_test_{}_{name}", std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_backup_restore() {
+        // `VACUUM INTO` of an in-memory database writes an in-memory copy, so back a file.
+        let db_file = temp_path("backup_restore.sqlite3");
+        let dao = file_dao(&db_file, ASPECTS_4_2_3_5).await;
+        let file = temp_path("backup.sqlite3");
+        let file = file.as_str();
+        let _ = std::fs::remove_file(file);
+
+        dao.backup(file).await.expect("backup");
+        let lux = ElementHandle::from("Lux");
+        dao.change_element_holding(&lux, 3).await.expect("1");
+        sqlx::raw_sql("DELETE FROM recipes WHERE name='Lux'").execute(&dao.database).await.expect("1");
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), 3.);
+
+        dao.restore(file).await.expect("restore");
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), f64::INFINITY);
+        assert_eq!(dao.get_element_components(&lux).await.expect("1"),
+            (ElementHandle::from("Aer"), ElementHandle::from("Ignis")));
+        std::fs::remove_file(file).expect("remove the backup");
+        // not a backup of this tool
+        let other_file = temp_path("backup_restore_other.sqlite3");
+        let other = file_dao(&other_file, "DROP TABLE recipes;").await;
+        other.backup(file).await.expect("backup");
+        assert!(matches!(dao.restore(file).await, Err(super::Errors::InvalidBackup(..))));
+        assert_eq!(dao.get_element_components(&lux).await.expect("1"),
+            (ElementHandle::from("Aer"), ElementHandle::from("Ignis")));
+        for x in [file, &db_file, &other_file] {
+            std::fs::remove_file(x).expect("remove the test database");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_mods() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Nemo', NULL, 1.0);
+            ")).await;
+        let mods = dao.list_mods().await.expect("1");
+        assert_eq!(mods[0], crate::pathes::UNKNOWN_MOD);
+        assert!(mods.iter().any(|a| a == "Thaumcraft"));
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        // a database created before elements_holding had last_updated, stage1.sql read again.
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            ALTER TABLE elements_holding DROP COLUMN last_updated;
+            ")).await;
+        let lux = ElementHandle::from("Lux");
+        assert!(dao.change_element_holding(&lux, 5).await.is_err());
+        dao.migrate().await.expect("1");
+        dao.change_element_holding(&lux, 5).await.expect("2");
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("3"), 5.);
+        assert!(dao.get_holding_last_updated(&lux).await.expect("4").is_some());
+        // nothing left to add.
+        dao.migrate().await.expect("5");
+    }
+
+    #[tokio::test]
+    async fn test_restore_missing_column() {
+        // a backup made before elements_holding had last_updated.
+        let old_file = temp_path("restore_missing_column_old.sqlite3");
+        let old = file_dao(&old_file, &format!("{ASPECTS_4_2_3_5}
+            UPDATE elements_holding SET num=7 WHERE name='Lux';
+            DROP INDEX elements_holding_name;
+            ALTER TABLE elements_holding DROP COLUMN last_updated;
+            ")).await;
+        let file = temp_path("restore_missing_column.sqlite3");
+        let file = file.as_str();
+        let _ = std::fs::remove_file(file);
+        old.backup(file).await.expect("backup");
+
+        let db_file = temp_path("restore_missing_column_db.sqlite3");
+        let dao = file_dao(&db_file, ASPECTS_4_2_3_5).await;
+        let lux = ElementHandle::from("Lux");
+        dao.change_element_holding(&lux, 3).await.expect("1");
+        dao.restore(file).await.expect("restore");
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), 7.);
+        use sqlx::Row;
+        let last_updated = sqlx::query("SELECT last_updated FROM elements_holding WHERE name='Lux'")
+            .fetch_one(&dao.database).await.expect("1")
+            .try_get::<Option<i64>, _>(0).expect("1");
+        assert_eq!(last_updated, None);
+        for x in [file, &db_file, &old_file] {
+            std::fs::remove_file(x).expect("remove the test database");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_recipe_rejects_cycle() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ")).await;
+        let h = |a: &str| ElementHandle::from(a);
+        // Lux = Aer + Ignis
+        assert!(matches!(dao.add_recipe(&h("Ignis"), &h("Lux"), &h("Aer")).await,
+            Err(super::Errors::CyclicRecipe { .. })));
+        // Tenebrae = Vacuos + Lux, deeper
+        assert!(matches!(dao.add_recipe(&h("Aer"), &h("Tenebrae"), &h("Ordo")).await,
+            Err(super::Errors::CyclicRecipe { .. })));
+        assert!(matches!(dao.add_recipe(&h("Fulgur"), &h("Fulgur"), &h("Ordo")).await,
+            Err(super::Errors::CyclicRecipe { .. })));
+        assert!(dao.is_primary_element(&h("Ignis")).await.expect("1"));
+
+        assert!(matches!(dao.add_recipe(&h("Fulgur"), &h("Nope"), &h("Ordo")).await,
+            Err(super::Errors::ElementNotFound(..))));
+        dao.add_recipe(&h("Fulgur"), &h("Lux"), &h("Potentia")).await.expect("1");
+        assert_eq!(dao.get_element_components(&h("Fulgur")).await.expect("1"), (h("Lux"), h("Potentia")));
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_recipe() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ")).await;
+        let h = |a: &str| ElementHandle::from(a);
+        dao.add_recipe(&h("Fulgur"), &h("Lux"), &h("Potentia")).await.expect("1");
+        assert!(matches!(dao.add_recipe(&h("Fulgur"), &h("Potentia"), &h("Lux")).await,
+            Err(super::Errors::DuplicateRecipe(..))));
+        // another recipe of it.
+        dao.add_recipe(&h("Fulgur"), &h("Ignis"), &h("Aer")).await.expect("2");
+        assert_eq!(dao.get_all_element_components(&h("Fulgur")).await.expect("2"),
+            vec![(h("Ignis"), h("Aer")), (h("Lux"), h("Potentia"))]);
+        assert_eq!(dao.get_element_components(&h("Fulgur")).await.expect("2"), (h("Ignis"), h("Aer")));
+
+        dao.remove_recipe(&h("Fulgur")).await.expect("3");
+        assert!(dao.is_primary_element(&h("Fulgur")).await.expect("4"));
+        assert!(matches!(dao.remove_recipe(&h("Fulgur")).await, Err(super::Errors::FetchedZeroRow(..))));
+        // a misptyped one can be added anew.
+        dao.add_recipe(&h("Fulgur"), &h("Ignis"), &h("Aer")).await.expect("5");
+
+        // spelled in other cases, the stored names are written and compared.
+        assert!(matches!(dao.add_recipe(&h(" fulgur "), &h("AER"), &h("ignis")).await,
+            Err(super::Errors::DuplicateRecipe(..))));
+        dao.add_recipe(&h(" fulgur "), &h("lux"), &h("POTENTIA")).await.expect("6");
+        assert_eq!(dao.get_all_element_components(&h("Fulgur")).await.expect("6"),
+            vec![(h("Ignis"), h("Aer")), (h("Lux"), h("Potentia"))]);
+        dao.remove_recipe(&h(" fulgur ")).await.expect("7");
+        assert!(dao.is_primary_element(&h("Fulgur")).await.expect("7"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_holding_row() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ")).await;
+        let fulgur = ElementHandle::from("Fulgur");
+        assert_eq!(dao.get_element_num_holding(&fulgur).await.expect("1"), 0.);
+        dao.change_element_holding(&fulgur, 40).await.expect("1");
+        assert_eq!(dao.get_element_num_holding(&fulgur).await.expect("1"), 40.);
+        // the row inserted above is updated in place.
+        dao.change_element_holding(&fulgur, 7).await.expect("2");
+        assert_eq!(dao.get_element_num_holding(&fulgur).await.expect("2"), 7.);
+        assert_eq!(dao.list_elements_holding().await.expect("3").iter().filter(|a| a.0 == fulgur).count(), 1);
+        assert!(matches!(dao.change_element_holding(&ElementHandle::from("Nope"), 40).await,
+            Err(super::Errors::ElementNotFound(..))));
+    }
+
+    #[tokio::test]
+    async fn test_import_holdings() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ")).await;
+        let (aer, fulgur) = (ElementHandle::from("Aer"), ElementHandle::from("Fulgur"));
+        assert_eq!(dao.import_holdings(&[(aer.clone(), 12.), (ElementHandle::from("fulgur"), 3.)]).await.expect("1"), 2);
+        assert_eq!(dao.get_element_num_holding(&aer).await.expect("2"), 12.);
+        assert_eq!(dao.get_element_num_holding(&fulgur).await.expect("2"), 3.);
+        // an unknown element, the holdings before it are rolled back.
+        assert!(matches!(dao.import_holdings(&[(aer.clone(), 99.), (ElementHandle::from("Nope"), 1.)]).await,
+            Err(super::Errors::ElementNotFound(..))));
+        assert_eq!(dao.get_element_num_holding(&aer).await.expect("3"), 12.);
+    }
+
+    #[tokio::test]
+    async fn test_reset_all_holdings() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ")).await;
+        let elements = dao.list_elements().await.expect("1");
+        assert_eq!(dao.reset_all_holdings().await.expect("2"), elements.len() as u64);
+        let holdings = dao.list_elements_holding().await.expect("3");
+        assert_eq!(holdings.len(), elements.len());
+        assert!(holdings.iter().all(|a| a.1 == 0.), "{holdings:?}");
+        assert!(dao.check_foreign_keys().await.expect("4").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_lookup() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            UPDATE elements_holding SET num = 5 WHERE name = 'Ignis';
+            ")).await;
+        let ignis = ElementHandle::from("  ignis ");
+        assert!(dao.does_element_exists(&ignis).await.expect("1"));
+        assert_eq!(dao.find_element(&ignis).await.expect("2"), Some(ElementHandle::from("Ignis")));
+        assert_eq!(dao.find_element(&ElementHandle::from("IGNIS")).await.expect("2"), Some(ElementHandle::from("Ignis")));
+        assert_eq!(dao.find_element(&ElementHandle::from("Ign")).await.expect("2"), None);
+        assert_eq!(dao.get_element_num_holding(&ignis).await.expect("3"), 5.);
+        assert!(dao.is_primary_element(&ignis).await.expect("4"));
+        // the stored names are returned.
+        let lux = ElementHandle::from("lux ");
+        assert_eq!(dao.get_element_components(&lux).await.expect("5"),
+            (ElementHandle::from("Aer"), ElementHandle::from("Ignis")));
+        assert!(dao.get_what_component_can_build(&ignis).await.expect("6").contains(&ElementHandle::from("Lux")));
+        dao.change_element_holding(&ElementHandle::from("AER"), 7).await.expect("7");
+        assert_eq!(dao.get_element_num_holding(&ElementHandle::from("Aer")).await.expect("8"), 7.);
+    }
+
+    #[tokio::test]
+    async fn test_suggest_elements() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let h = ElementHandle::from;
+        let suggested = dao.suggest_elements("Ingis", 5).await.expect("1");
+        assert_eq!(suggested.first(), Some(&h("Ignis")));
+        assert_eq!(dao.suggest_elements("ingis", 1).await.expect("2"), vec![h("Ignis")]);
+        // Iter takes 2 edits, Aqua 3.
+        assert_eq!(dao.suggest_elements("Aer", 2).await.expect("3"), vec![h("Aer"), h("Iter")]);
+        assert!(dao.suggest_elements("Xxxxxxxxxxxx", 5).await.expect("4").is_empty());
+        assert_eq!(dao.suggest_elements_within("Ignix", 5, 1).await.expect("5"), vec![h("Ignis")]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_case_duplicates() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('aer', 'Imported', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Imported', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'aer', 'Potentia');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'aer', 'Ignis');
+            UPDATE elements_holding SET num = 3 WHERE name = 'Aer';
+            INSERT INTO elements_holding(name, num) VALUES('aer', 4);
+            INSERT INTO element_tags(name, tag) VALUES('aer', 'need');
+            ")).await;
+        let aer = ElementHandle::from("Aer");
+        assert_eq!(dao.find_case_duplicate_elements().await.expect("1"),
+            vec![vec![aer.clone(), ElementHandle::from("aer")]]);
+
+        assert_eq!(dao.merge_case_duplicates(&aer).await.expect("2"), 1);
+        assert!(dao.find_case_duplicate_elements().await.expect("3").is_empty());
+        // only the canonical spelling is left for the lookups to find.
+        assert_eq!(dao.find_element(&ElementHandle::from("aer")).await.expect("3"), Some(aer.clone()));
+        assert_eq!(dao.get_element_components(&ElementHandle::from("Fulgur")).await.expect("4"),
+            (aer.clone(), ElementHandle::from("Potentia")));
+        // the duplicated Lux recipe is kept once
+        let lux = dao.list_recipes().await.expect("5").into_iter()
+            .filter(|(name, _, _)| name.get_name() == "Lux")
+            .count();
+        assert_eq!(lux, 1);
+        assert_eq!(dao.get_element_num_holding(&aer).await.expect("6"), 7.);
+        assert_eq!(dao.list_by_tag("need").await.expect("7"), vec![aer.clone()]);
+        assert_eq!(dao.merge_case_duplicates(&aer).await.expect("8"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_merge_three_spellings() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Foo', 'Imported', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('foo', 'Imported', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('FOO', 'Imported', 1.0);
+            INSERT INTO elements_holding(name, num) VALUES('foo', 4);
+            INSERT INTO elements_holding(name, num) VALUES('FOO', 5);
+            ")).await;
+        // the canonical spelling has no holding of its own, and is typed with spaces.
+        assert_eq!(dao.merge_case_duplicates(&ElementHandle::from(" Foo ")).await.expect("1"), 2);
+        let foo = ElementHandle::from("Foo");
+        assert_eq!(dao.find_element(&ElementHandle::from("FOO")).await.expect("2"), Some(foo.clone()));
+        assert_eq!(dao.get_element_num_holding(&foo).await.expect("3"), 9.);
+        let holdings = dao.list_elements_holding().await.expect("4");
+        assert_eq!(holdings.iter().filter(|a| a.0.get_name().eq_ignore_ascii_case("foo")).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_which_elements_exist() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let exist = dao.which_elements_exist(&["Aer", "Nope", "Lux", "aer", "Aer"]).await.expect("1");
+        assert_eq!(exist.len(), 4);
+        assert_eq!(exist["Aer"], Some(ElementHandle::from("Aer")));
+        assert_eq!(exist["Lux"], Some(ElementHandle::from("Lux")));
+        assert_eq!(exist["Nope"], None);
+        assert_eq!(exist["aer"], Some(ElementHandle::from("Aer")));
+        assert!(dao.which_elements_exist(&[]).await.expect("2").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_product_of() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let h = ElementHandle::from;
+        assert_eq!(dao.product_of(&h("Aer"), &h("Ignis")).await.expect("1"), Some(h("Lux")));
+        assert_eq!(dao.product_of(&h("Ignis"), &h("Aer")).await.expect("2"), Some(h("Lux")));
+        assert_eq!(dao.product_of(&h("Aer"), &h("Lux")).await.expect("3"), None);
+        assert_eq!(dao.product_of(&h(" aer "), &h("IGNIS")).await.expect("4"), Some(h("Lux")));
+    }
+
+    #[tokio::test]
+    async fn test_holding_presets() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            UPDATE elements_holding SET num = 10;
+            ")).await;
+        let lux = ElementHandle::from("Lux");
+        let total = dao.list_elements_holding().await.expect("1").len() as u64;
+        assert_eq!(dao.save_preset("starter").await.expect("1"), total);
+
+        dao.change_element_holding(&lux, 500).await.expect("1");
+        assert_eq!(dao.save_preset("endgame").await.expect("1"), total);
+        dao.change_element_holding(&lux, 7).await.expect("1");
+        assert_eq!(dao.list_presets().await.expect("1"),
+            vec![("endgame".to_string(), total as i64), ("starter".to_string(), total as i64)]);
+
+        assert_eq!(dao.load_preset("starter").await.expect("1"), total);
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), 10.);
+        dao.load_preset("endgame").await.expect("1");
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), 500.);
+        assert_eq!(dao.get_element_num_holding(&ElementHandle::from("Aer")).await.expect("1"), 10.);
+
+        assert!(matches!(dao.load_preset("nope").await, Err(super::Errors::PresetNotFound(..))));
+        assert_eq!(dao.get_element_num_holding(&lux).await.expect("1"), 500.);
+    }
+
+    #[tokio::test]
+    async fn test_load_preset_restores() {
+        let dao = memory_dao(&format!("{ASPECTS_4_2_3_5}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            DELETE FROM elements_holding;
+            INSERT INTO elements_holding(name, num) VALUES('Aer', 5);
+            ")).await;
+        let (aer, ignis, fulgur) = (ElementHandle::from("Aer"), ElementHandle::from("Ignis"), ElementHandle::from("Fulgur"));
+        dao.change_element_holding(&fulgur, 3).await.expect("1");
+        assert_eq!(dao.save_preset("p").await.expect("1"), 2);
+
+        // a holding added after the save, and a saved holding whose row is gone.
+        dao.change_element_holding(&ignis, 9).await.expect("2");
+        sqlx::query("DELETE FROM elements_holding WHERE name='Fulgur'").execute(&dao.database).await.expect("2");
+        assert_eq!(dao.load_preset("p").await.expect("3"), 2);
+        assert_eq!(dao.get_element_num_holding(&aer).await.expect("4"), 5.);
+        assert_eq!(dao.get_element_num_holding(&ignis).await.expect("4"), 0.);
+        assert_eq!(dao.get_element_num_holding(&fulgur).await.expect("4"), 3.);
+        assert!(dao.get_holding_last_updated(&aer).await.expect("5").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_export_sql_imports_into_empty_schema() {
+        let dao = memory_dao(ASPECTS_4_2_3_5).await;
+        let sorted = dao.recipes_topologically_sorted().await.expect("1");
+        assert_eq!(sorted.len(), dao.list_recipes().await.expect("1").len());
+        for (i, (_, ca, cb)) in sorted.iter().enumerate() {
+            for component in [ca, cb] {
+                assert!(sorted[i..].iter().all(|(name, _, _)| name != component), "{:?}", sorted[i]);
+            }
+        }
+
+        let exported = dao.export_sql().await.expect("1");
+        // stage1 is applied with the foreign keys on, every statement is checked right away.
+        let imported = memory_dao("").await;
+        for statement in exported.lines() {
+            sqlx::raw_sql(statement).execute(&imported.database).await.expect(statement);
+        }
+        assert_eq!(imported.list_elements().await.expect("1").len(), dao.list_elements().await.expect("1").len());
+        let mut a = imported.list_recipes().await.expect("1");
+        let mut b = dao.list_recipes().await.expect("1");
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+    }
 }
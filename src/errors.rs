@@ -18,6 +18,22 @@ pub(crate) enum T4ACHError {
         line_number: usize,
     },
 
+    #[snafu(display("parsing pairs failed at line {line_number}, expect two aspects."), visibility(pub))]
+    ParsingPairs {
+        backtrace: snafu::Backtrace,
+        #[snafu(implicit)]
+        err_loc: snafu::Location,
+        line_number: usize,
+    },
+
+    #[snafu(display("parsing holdings failed at line {line_number}, expect an aspect and its quantity."), visibility(pub))]
+    ParsingHoldings {
+        backtrace: snafu::Backtrace,
+        #[snafu(implicit)]
+        err_loc: snafu::Location,
+        line_number: usize,
+    },
+
     #[snafu(display("Function Domain error"), visibility(pub))]
     Math {
         source: crate::math::MathError,
@@ -34,7 +50,7 @@ pub(crate) enum T4ACHError {
         source: crate::dao::Errors,
     },
 
-    #[snafu(visibility(pub))]
+    #[snafu(display("the element {element_name} in {context} doesn't exists"), visibility(pub))]
     ElementNotFound {
         #[snafu(implicit)]
         err_loc: snafu::Location,
@@ -42,6 +58,23 @@ pub(crate) enum T4ACHError {
         element_name: String,
         context: String,
     },
+
+    #[snafu(display("cyclic recipe: {}", cycle.join(" -> ")), visibility(pub))]
+    CyclicRecipe {
+        backtrace: snafu::Backtrace,
+        #[snafu(implicit)]
+        err_loc: snafu::Location,
+        /// the element made of itself, through the others.
+        cycle: Vec<String>,
+    },
+
+    #[snafu(display("parsing the aspects failed, {reason}."), visibility(pub))]
+    ParsingAspects {
+        backtrace: snafu::Backtrace,
+        #[snafu(implicit)]
+        err_loc: snafu::Location,
+        reason: String,
+    },
 }
 
 pub(crate) type Result<T> = std::result::Result<T, T4ACHError>;
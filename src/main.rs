@@ -3,7 +3,11 @@ mod errors;
 mod dao;
 mod math;
 mod pathes;
+mod query_cache;
+mod report;
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::{Arc, LazyLock};
 
 static INIT_SQLX_DRIVERS: LazyLock<()> = LazyLock::new(|| {
@@ -11,7 +15,7 @@ static INIT_SQLX_DRIVERS: LazyLock<()> = LazyLock::new(|| {
 });
 
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use recipes::ElementHandle;
 
 #[derive(Parser)]
@@ -19,6 +23,37 @@ use recipes::ElementHandle;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Print the relatives cache's hits, misses and size after the command completes.
+    #[arg(long, global = true)]
+    cache_stats: bool,
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Reuse the results of expensive queries (`tiers`) stored next to the database,
+    /// for as long as the database file isn't modified.
+    #[arg(long, global = true)]
+    query_cache: bool,
+    /// Check the data first, and refuse to run the command if anything in it is broken.
+    #[arg(long, global = true)]
+    preflight: bool,
+    /// The alpha within (0, 1) the holdings are mapped to weights with, 0.7 if not given.
+    /// The bigger, the less the holdings count.
+    #[arg(long, global = true)]
+    alpha: Option<f64>,
+    /// How much within [0, 1] of an aspect's weight is its own, the rest comes from its
+    /// decomposition. 0.7 if not given.
+    #[arg(long, global = true)]
+    blend_rate: Option<f64>,
+    /// How many paths to weight at a time, as many as the CPU cores if not given.
+    #[arg(long, global = true, value_name = "N")]
+    weight_concurrency: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// Plain text, Markdown for `report`.
+    #[value(alias = "md")]
+    Text,
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -30,63 +65,1306 @@ enum Commands {
     Crack {
         #[arg(value_name="ASPECTS [QUANTITIES]")]
         aspects: Vec<String>,
+        /// Stop cracking this many recipes down, the aspects there are counted as they are.
+        /// Cracking all the way to the primary aspects if not given.
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Also sum the counts by the mod of the aspects.
+        #[arg(long)]
+        by_mod: bool,
+    },
+    /// List every distinct set of primary elements an aspect can crack into, when some aspects
+    /// on the way have several recipes.
+    AllCracks {
+        aspect: String,
+    },
+    /// Find the compound aspects whose decomposition is the closest to the given primal counts,
+    /// written the same way as `crack`, for example:
+    ///  Aer 2 Ignis
+    MatchProfile {
+        #[arg(value_name="PRIMALS [QUANTITIES]", num_args=1..)]
+        primals: Vec<String>,
+        /// only count the missing primals, ignore the surplus.
+        #[arg(long)]
+        cover: bool,
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
     },
     /// Connect two elements with `steps_n` steps
     TryConnect {
         from: String,
         to: String,
         steps_n: usize,
+        /// Don't step on the aspects tagged with it, can be repeated.
+        #[arg(long, value_name="TAG")]
+        exclude_tag: Vec<String>,
+        /// Don't step on the aspects of this mod, can be repeated.
+        #[arg(long, value_name="MOD")]
+        avoid_mod: Vec<String>,
+        /// Only step on the aspects holding some.
+        #[arg(long)]
+        owned_only: bool,
+        /// A file listing the aspects already placed on the research board,
+        /// separated by whitespaces. Paths reusing them are favored.
+        #[arg(long, value_name="FILE")]
+        on_board: Option<PathBuf>,
+        /// Only the paths stepping on this aspect, can be repeated to pin several in order.
+        #[arg(long, value_name="ASPECT")]
+        through: Vec<String>,
+        /// Show the best N paths only, all of them by default.
+        #[arg(long, value_name="N", visible_alias = "top")]
+        limit: Option<usize>,
+        /// Pre-rank the paths by their intermediates' base values, and only weight the best of them.
+        /// Faster, but may miss a path through rare yet well stocked aspects.
+        #[arg(long, requires = "limit")]
+        lazy: bool,
+        /// Output `rank,weight,length,steps` rows as CSV.
+        #[arg(long)]
+        csv: bool,
+        /// Output every link across the paths once, as `a -> b` lines.
+        #[arg(long, conflicts_with = "csv")]
+        edges: bool,
+        /// With `--format json`, output each step's base value, holding and weight too.
+        #[arg(long, conflicts_with = "csv")]
+        enriched: bool,
+        /// After the paths, print the distinct intermediates across all of them.
+        /// Goes to stderr with `--csv`, `--edges` or `--format json`.
+        #[arg(long)]
+        summary: bool,
+        /// Print each path's weight as a score out of 100 too, the best path scoring 100.
+        #[arg(long, conflicts_with = "csv")]
+        score: bool,
+        /// Show each aspect's holding after it, like `Lux(x48)`.
+        #[arg(long, conflicts_with = "csv")]
+        annotate_holdings: bool,
+        /// Count the holdings as halved every HOURS since they were last changed.
+        #[arg(long, value_name = "HOURS")]
+        decay: Option<f64>,
+        /// Print how stocked up each path's intermediates are too, from 0 to 1,
+        /// the least mapped holding among them.
+        #[arg(long, conflicts_with = "csv")]
+        show_readiness: bool,
+        /// Order the paths by a weighted sum of metrics instead, out of length, holdings and rarity,
+        /// like `length:0.5,holdings:0.3,rarity:0.2`. Each metric is normalized over the paths found.
+        #[arg(long, value_name = "CRITERIA", conflicts_with = "lazy")]
+        criteria: Option<pathes::Criteria>,
+        /// Print each path's weight as an interval, every holding taken as off by up to
+        /// this fraction of it, like 0.2 for 20%.
+        #[arg(long, value_name = "FRACTION", conflicts_with_all = ["csv", "annotate_holdings"])]
+        uncertain: Option<f64>,
+        /// Search from `to` to `from` too, and tell whether it finds the same paths reversed.
+        #[arg(long)]
+        both_directions: bool,
+        /// Write how the search expanded, which aspects it stepped on and pruned, to FILE as JSON.
+        #[arg(long, value_name = "FILE")]
+        dump_search: Option<PathBuf>,
+        /// Print the aspects each path's intermediates spend, one per placement.
+        #[arg(long, conflicts_with = "csv")]
+        show_cost: bool,
+        /// Count the cost in primary elements instead.
+        #[arg(long, requires = "show_cost")]
+        cost_in_primals: bool,
+    },
+    /// Suggest the next aspect to place after a partial chain, keeping `--to` reachable
+    /// within `--remaining` more intermediates, the best weighted first.
+    NextStep {
+        #[arg(value_name = "PARTIAL", num_args = 1.., required = true)]
+        partial: Vec<String>,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        remaining: usize,
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// Connect every two primary elements, the best weighted path of each of the `--steps` lengths.
+    AllPrimalPaths {
+        #[arg(long, num_args = 1.., required = true)]
+        steps: Vec<usize>,
+    },
+    /// Find the fewest steps connecting each pair of aspects in `--from-file`,
+    /// a file of lines like `Aer Ignis`, `#` starts a comment.
+    MinSteps {
+        #[arg(long)]
+        from_file: PathBuf,
+        #[arg(long, default_value_t = 6)]
+        max_steps: usize,
+    },
+    /// Find the fewest intermediates connecting two aspects, up to `--max-steps` of them,
+    /// and the paths of that many.
+    Connect {
+        from: String,
+        to: String,
+        #[arg(long, default_value_t = 6)]
+        max_steps: usize,
+    },
+    /// Find the cheapest connection of any length up to `--max-steps` intermediates,
+    /// where stepping on an aspect costs the inverse of its weight.
+    BestConnect {
+        from: String,
+        to: String,
+        #[arg(long, default_value_t = 4)]
+        max_steps: usize,
+    },
+    /// Check whether a hand-planned path is the best weighted one of its length.
+    /// The path is given from the start to the end, for example:
+    ///  Bestia Corpus Mortuus Spiritus
+    IsOptimal {
+        #[arg(value_name="ASPECTS", num_args=2.., required = true)]
+        aspects: Vec<String>,
+    },
+    /// Count the intermediates of the best path of each pair, the most frequent, pivot ones first.
+    Pivots {
+        /// the pairs, a file of lines like `Aer Ignis`, `#` starts a comment.
+        /// Every pair of primal aspects by default.
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+        #[arg(long, default_value_t = 2)]
+        steps: usize,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Plan a research board linking all the given aspects with the fewest connectors,
+    /// by a tree between them or a hub they all link to.
+    PlanBoard {
+        #[arg(value_name="ASPECTS", num_args=2.., required = true)]
+        aspects: Vec<String>,
+        /// The most steps allowed for one connection.
+        #[arg(long, default_value_t = 3)]
+        max_steps: usize,
+    },
+    /// Find a small set of connector aspects linking all the given aspects together.
+    LinkAll {
+        #[arg(value_name="ASPECTS", num_args=2.., required = true)]
+        aspects: Vec<String>,
+        /// The most steps allowed between two linked aspects.
+        #[arg(long, default_value_t = 3)]
+        max_steps: usize,
+    },
+    /// Rank the paths with two alphas of the holdings mapping, and show them side by side.
+    CompareWeights {
+        from: String,
+        to: String,
+        steps_n: usize,
+        #[arg(long)]
+        alpha_a: f64,
+        #[arg(long)]
+        alpha_b: f64,
+    },
+    /// Suggest the alpha of the holdings mapping that sets the best path the furthest apart from the second.
+    SuggestAlpha {
+        from: String,
+        to: String,
+        steps_n: usize,
+    },
+    /// List the aspects weighing more than `--min-weight`, the heaviest first:
+    /// the well stocked and rare ones.
+    TopAspects {
+        #[arg(long, value_name = "X")]
+        min_weight: f64,
+    },
+    /// Export the recipe graph as Graphviz DOT, to be rendered by e.g. `dot -Tpng`.
+    ExportGraph {
+        /// Write to FILE instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Export every aspect's base value, holding and weight as CSV.
+    WeightTable {
+        /// Write to FILE instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
     },
     /// List the elements in `Database`
-    ListElements,
+    ListElements {
+        /// Show each base value's percentile rank too, the rarest at 100.
+        #[arg(long)]
+        percentile: bool,
+    },
     /// List the recipes in `Database`
     ListRecipes,
     /// List the mods in `Database`
     ListMods,
+    /// Print the elements and recipes as sql, in an order that reads into an empty database.
+    ExportSql,
+    /// Add the recipe `name = component_a + component_b`, the elements must exist already.
+    AddRecipe {
+        name: String,
+        component_a: String,
+        component_b: String,
+    },
+    /// Remove the recipe of `name`, e.g. a misptyped one.
+    RemoveRecipe {
+        name: String,
+    },
     /// The `Aspects Connecting Algorithm` can calculate a `recommendation rate` by their
     /// quantities. This is the way let you input each one manually.
     ChangeElementHolding {
         element_name: String,
         change_to_num: usize,
+        /// show how the ranking of the pathes from FROM to TO changes first, then ask to commit.
+        #[arg(long, num_args = 3, value_names = ["FROM", "TO", "STEPS"])]
+        preview: Option<Vec<String>>,
+    },
+    /// Remove the weights cached by `precompute-weights`.
+    ClearWeightCache,
+    /// Calculate and cache every aspect's weight ahead of the queries.
+    PrecomputeWeights {
+        /// how many weights to calculate at a time.
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
     },
+    /// Cache the fewest steps between every two aspects, making `min-steps` lookups instant.
+    /// Adding a recipe drops the cache.
+    PrecomputeDistances,
+    /// Save the current holdings as a named preset, replacing the one of the same name.
+    SavePreset {
+        name: String,
+    },
+    /// Set the holdings to the ones saved in a preset.
+    LoadPreset {
+        name: String,
+    },
+    /// List the saved holding presets.
+    ListPresets,
     /// List the elements currently holding.
-    ListElementsHolding,
+    ListElementsHolding {
+        /// Draw the holdings as bars, scaled to the largest holding.
+        #[arg(long)]
+        chart: bool,
+    },
+    /// Print what two aspects combine into.
+    Combine {
+        a: String,
+        b: String,
+    },
+    /// List the aspects craftable from the holdings within `max_crafts` combinations, by depth.
+    CraftableWithin {
+        max_crafts: usize,
+    },
+    /// Rank the compound aspects by how cheaply cracking them yields `primal`,
+    /// and how many of each to crack to get `needed` of it.
+    SourceFor {
+        primal: String,
+        #[arg(default_value_t = 1)]
+        needed: usize,
+    },
+    /// Compare the base value cost of farming `needed` of `primal` with cracking compounds for it.
+    CraftVsFarm {
+        primal: String,
+        needed: usize,
+    },
+    /// Find the missing links between two aspects which would connect the most pairs of aspects
+    /// within `--max-steps`.
+    BestNewLink {
+        #[arg(long, default_value_t = 3)]
+        max_steps: usize,
+        /// only evaluate this many candidate pairs.
+        #[arg(long)]
+        sample: Option<usize>,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Propose new compounds bridging two aspects of separate components, the shortest connection first.
+    SuggestBridge {
+        a: String,
+        b: String,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Rank the aspects by how many pairs of the others their removal would disconnect.
+    CriticalAspect {
+        /// only evaluate this many aspects.
+        #[arg(long)]
+        sample: Option<usize>,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Compare the holdings against a target inventory, a file like `Aer 64, Ignis 64`,
+    /// the largest deficit first.
+    CompareInventory {
+        #[arg(long, value_name = "FILE")]
+        target: PathBuf,
+    },
+    /// List every aspect in the decomposition tree of an aspect, the ones to have scanned to research it.
+    ResearchAspects {
+        aspect: String,
+    },
+    /// List the aspects made of exactly `k` primary elements in total, counting repeats.
+    ByComplexity {
+        k: usize,
+    },
+    /// List the aspects with no recipe that aren't primal, gaps left by an incomplete import.
+    FindGaps,
+    /// List the rows referencing a missing aspect, left by writes made with the foreign keys off.
+    CheckIntegrity,
+    /// Find the compound aspect whose primal profile is the closest to the holdings' one.
+    Centroid,
+    /// Compare two aspects by the primary elements their decompositions share.
+    Similarity {
+        a: String,
+        b: String,
+    },
+    /// Print an aspect's decomposition as a Mermaid diagram.
+    Mermaid {
+        aspect: String,
+    },
+    /// Check that every relative of an aspect has the aspect as a relative too.
+    VerifySymmetry,
+    /// Merge the elements spelled as `--canonical` ignoring case into it,
+    /// repointing their recipes and holdings. Lists the case duplicates if there are none for it.
+    MergeCaseDuplicates {
+        #[arg(long)]
+        canonical: String,
+    },
+    /// List the groups of elements cracking to the same primary elements.
+    EquivalentAspects,
+    /// List each primary element followed by the compounds cracking into it the most.
+    GroupByPrimal,
+    /// List the holdings so far up the value curve that collecting more is a waste.
+    Wasted {
+        /// the fraction of the curve's maximum value, in (0, 1].
+        #[arg(long, default_value_t = 0.95)]
+        threshold: f64,
+    },
+    /// List the compound elements whose base value is far off the sum of its primal base values.
+    VerifyBaseValues {
+        /// the allowed relative deviation from the primal cost.
+        #[arg(long, default_value_t = 0.5)]
+        tolerance: f64,
+    },
+    /// Group the elements by tier, the depth of decomposing them to primary elements.
+    Tiers,
+    /// Write a copy of the database to a new file.
+    Backup {
+        #[arg(long, value_name="FILE")]
+        to: PathBuf,
+    },
+    /// Replace the database's content with a backup.
+    Restore {
+        #[arg(long, value_name="FILE")]
+        from: PathBuf,
+        /// Confirm the current content can be overwritten.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Overwrite every holding with a random number in [0, `--max`], to experiment with
+    /// the recommendations. The same `--seed` gives the same holdings.
+    RandomHoldings {
+        #[arg(long)]
+        max: usize,
+        #[arg(long)]
+        seed: u64,
+        /// Confirm the current holdings can be overwritten.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Set every holding to 0, for a fresh playthrough.
+    ResetHoldings {
+        /// Confirm the current holdings can be overwritten.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Set the holdings listed in a file of lines like `Aer 64`, `#` starts a comment.
+    /// Nothing is set if any line names an unknown aspect.
+    ImportHoldings {
+        path: PathBuf,
+    },
+    /// Export the holdings in the format of `import-holdings`.
+    ExportHoldings {
+        /// Write to FILE instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// For each mod, how many of its aspects connect to Thaumcraft's base aspects within `steps_n` steps.
+    ModIntegration {
+        steps_n: usize,
+    },
+    /// Summarize the database: stats, primals, tiers, diameter, terminal aspects and
+    /// the integration of each mod. Markdown, or a JSON document with `--format json`.
+    Report {
+        /// the steps within which a mod's aspect counts as connected to the base aspects.
+        #[arg(long, default_value_t = 2)]
+        integration_steps: usize,
+    },
+    /// List the elements never used as a component, they can't be connectors.
+    TerminalAspects,
+    /// Tag an element with a custom category, e.g. "need" or "ignore".
+    Tag {
+        element_name: String,
+        tag: String,
+    },
+    /// Remove a tag from an element.
+    Untag {
+        element_name: String,
+        tag: String,
+    },
+    /// List the elements tagged with `tag`.
+    ListByTag {
+        tag: String,
+    },
+}
+
+const CHART_WIDTH: usize = 40;
+
+/// the length of `holding`'s bar when `max` fills `width`.
+/// Infinite holdings always fill the bar, finite ones are empty beside an infinite `max`.
+fn holding_bar_len(holding: f64, max: f64, width: usize) -> usize {
+    if holding.is_infinite() {
+        width
+    } else if max <= 0. || max.is_infinite() {
+        0
+    } else {
+        (holding / max * width as f64).round() as usize
+    }
+}
+
+fn csv_quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// A random holding in [0, `max`] for each of `elements`, drawn in the order of their names
+/// so that a `seed` always gives the same holdings.
+fn random_holdings(mut elements: Vec<ElementHandle>, max: usize, seed: u64) -> Vec<(ElementHandle, usize)> {
+    use rand::{Rng, SeedableRng};
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    elements.sort();
+    elements.into_iter()
+        .map(|e| (e, rng.gen_range(0..=max)))
+        .collect()
+}
+
+/// `Aer(x12)->Lux(x48)->Ignis(xinf)`, every aspect of `path` with its holding in `holdings`,
+/// 0 if it's missing, then the weight if cached.
+fn annotate_holdings(path: &pathes::Path, holdings: &std::collections::HashMap<ElementHandle, f64>) -> String {
+    let mut ret = std::iter::once(path.start())
+        .chain(path.steps())
+        .chain(std::iter::once(path.end()))
+        .map(|a| format!("{}(x{})", a.get_name(), holdings.get(a).copied().unwrap_or(0.)))
+        .collect::<Vec<_>>()
+        .join("->");
+    if let Some(weight) = path.weight() {
+        ret += &format!(": weight {}", weight);
+    }
+    ret
+}
+
+/// `rank,weight,length,steps` rows with a header, `steps` lists the whole path from the start to the end.
+fn pathes_to_csv(pathes: &[pathes::Path]) -> String {
+    let mut ret = "rank,weight,length,steps\n".to_string();
+    for (i, p) in pathes.iter().enumerate() {
+        let steps = std::iter::once(p.start())
+            .chain(p.steps())
+            .chain(std::iter::once(p.end()))
+            .map(|a| a.get_name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        ret += &format!("{},{},{},{}\n", i + 1, p.weight().unwrap_or(0.), p.steps().len(), csv_quote(&steps));
+    }
+    ret
+}
+
+/// `name,base_value,holding,weight` rows of every element sorted by name, with a header,
+/// after a `# alpha` comment line. The weights are `calc_weight`'s under `config`.
+async fn weight_table_csv(dao: Arc<dao::DAO>, config: &pathes::WeightConfig) -> errors::Result<String> {
+    use snafu::ResultExt;
+    let mut elements = dao.list_elements().await.context(errors::DatabaseSnafu)?;
+    elements.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut ret = format!("# alpha {}\nname,base_value,holding,weight\n", config.alpha());
+    for e in elements {
+        let ele = ElementHandle::from(e.name.clone());
+        let holding = dao.get_element_num_holding(&ele).await.context(errors::DatabaseSnafu)?;
+        let weight = pathes::calc_weight(dao.clone(), &ele, config).await?;
+        ret += &format!("{},{},{},{}\n", csv_quote(&e.name), e.base_value, holding, weight);
+    }
+    Ok(ret)
+}
+
+/// The recipe graph as a Graphviz DOT document: every element a node, the primal ones
+/// double circled, and an edge from each component to what it builds.
+async fn recipes_dot(dao: &dao::DAO) -> errors::Result<String> {
+    use snafu::ResultExt;
+    let primals = dao.get_primary_elements().await.context(errors::DatabaseSnafu)?
+        .into_iter()
+        .collect::<std::collections::HashSet<_>>();
+    let mut elements = dao.list_elements().await.context(errors::DatabaseSnafu)?;
+    elements.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut recipes = dao.list_recipes().await.context(errors::DatabaseSnafu)?;
+    recipes.sort();
+    let quote = |a: &str| format!("\"{}\"", a.replace('"', "\\\""));
+
+    let mut ret = "digraph aspects {\n".to_string();
+    for e in elements {
+        let shape = if primals.contains(&ElementHandle::from(e.name.clone())) {
+            "doublecircle"
+        } else {
+            "ellipse"
+        };
+        ret += &format!("    {} [shape={}];\n", quote(&e.name), shape);
+    }
+    for (name, a, b) in recipes {
+        for component in [a, b] {
+            ret += &format!("    {} -> {};\n", quote(&component.get_name()), quote(&name.get_name()));
+        }
+    }
+    ret += "}\n";
+    Ok(ret)
+}
+
+/// Every link of `pathes`, the endpoints' included, as `a -> b` lines, each once in the order first met.
+fn pathes_to_edges(pathes: &[pathes::Path]) -> String {
+    let mut seen = std::collections::HashSet::new();
+    let mut ret = String::new();
+    for p in pathes {
+        let chain = std::iter::once(p.start())
+            .chain(p.steps())
+            .chain(std::iter::once(p.end()))
+            .collect::<Vec<_>>();
+        for pair in chain.windows(2) {
+            if seen.insert((pair[0].clone(), pair[1].clone())) {
+                ret += &format!("{} -> {}\n", pair[0].get_name(), pair[1].get_name());
+            }
+        }
+    }
+    ret
+}
+
+/// Parse `Aer 2 Ignis`, the aspects each followed by an optional quantity, into `[(Aer, 2), (Ignis, 1)]`.
+/// `None` if it's empty or starts with a quantity.
+fn parse_aspect_quantities(args: &[String]) -> Option<Vec<(ElementHandle, usize)>> {
+    let mut ret: Vec<(ElementHandle, Option<usize>)> = vec![];
+    for arg in args {
+        match (arg.parse::<usize>(), ret.last_mut()) {
+            (Ok(num), Some((_, quantity @ None))) => *quantity = Some(num),
+            (Ok(_), _) => return None,
+            (Err(_), _) => ret.push((ElementHandle::from(arg.clone()), None)),
+        }
+    }
+    (!ret.is_empty()).then(|| ret.into_iter().map(|(e, n)| (e, n.unwrap_or(1))).collect())
+}
+
+/// The most names suggested for a misspelled element.
+const SUGGESTIONS_LIMIT: usize = 3;
+
+/// that the element `name` doesn't exist, with the closest names if any are close enough.
+async fn element_not_found(dao: &dao::DAO, name: &str) -> String {
+    let suggested = dao.suggest_elements(name, SUGGESTIONS_LIMIT).await.expect("Suggest elements failed.");
+    if suggested.is_empty() {
+        format!("The element {} doesn't exists", name)
+    } else {
+        format!("The element {} doesn't exists; did you mean {}?", name,
+            suggested.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "))
+    }
+}
+
+/// the element spelled like `name` as stored, see `DAO::find_element`,
+/// or `None` after telling it doesn't exist.
+async fn stored_element(dao: &dao::DAO, name: &str) -> Option<ElementHandle> {
+    let [ele] = stored_elements(dao, [ElementHandle::from(name.to_string())]).await?;
+    Some(ele)
+}
+
+/// `eles` each spelled as stored, or `None` after telling the first one which doesn't exist.
+async fn stored_elements<T: AsMut<[ElementHandle]>>(dao: &dao::DAO, mut eles: T) -> Option<T> {
+    for ele in eles.as_mut() {
+        match dao.find_element(ele).await.expect("`does elements exists` failed") {
+            Some(stored) => *ele = stored,
+            None => {
+                eprintln!("{}", element_not_found(dao, &ele.get_name()).await);
+                return None;
+            }
+        }
+    }
+    Some(eles)
+}
+
+/// read the aspect names separated by whitespaces in `path`, `#` starts a comment till the line end.
+async fn read_aspects_file(path: &std::path::Path) -> errors::Result<Vec<ElementHandle>> {
+    use snafu::ResultExt;
+    let content = tokio::fs::read_to_string(path).await.context(errors::IoSnafu)?;
+    Ok(content.lines()
+        .map(|line| line.split('#').next().unwrap())
+        .flat_map(|line| line.split_whitespace())
+        .map(ElementHandle::from)
+        .collect())
+}
+
+/// Read `Aer 64, Ignis 64` with `parse_aspect_quantities`, the commas optional,
+/// `#` starts a comment till the line end. `None` if it doesn't parse.
+async fn read_quantities_file(path: &std::path::Path) -> errors::Result<Option<Vec<(ElementHandle, usize)>>> {
+    use snafu::ResultExt;
+    let content = tokio::fs::read_to_string(path).await.context(errors::IoSnafu)?;
+    let args = content.lines()
+        .map(|line| line.split('#').next().unwrap())
+        .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|a| !a.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+    Ok(parse_aspect_quantities(&args))
+}
+
+/// For each aspect of `target`, the holding, the target and the holding minus the target,
+/// the largest deficit first. An aspect listed twice adds up, one not held holds 0.
+fn compare_inventory(holdings: &std::collections::HashMap<ElementHandle, f64>, target: &[(ElementHandle, usize)])
+    -> Vec<(ElementHandle, f64, usize, f64)> {
+        let mut wanted = BTreeMap::new();
+        for (ele, n) in target {
+            *wanted.entry(ele.clone()).or_insert(0usize) += n;
+        }
+        let mut ret = wanted.into_iter()
+            .map(|(ele, n)| {
+                let held = holdings.get(&ele).copied().unwrap_or(0.);
+                (ele, held, n, held - n as f64)
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| a.3.total_cmp(&b.3).then_with(|| a.0.cmp(&b.0)));
+        ret
+}
+
+/// `{"Aer": 12, "Ignis": 4}`, sorted by name.
+fn primal_counts_json(counts: &std::collections::HashMap<ElementHandle, usize>) -> String {
+    let sorted = counts.iter()
+        .map(|(e, n)| (e.get_name(), *n))
+        .collect::<BTreeMap<_, _>>();
+    serde_json::to_string(&sorted).expect("Serialize primal counts failed.")
+}
+
+/// Read the lines of two aspects, `#` starts a comment.
+async fn read_pairs_file(path: &std::path::Path) -> errors::Result<Vec<(ElementHandle, ElementHandle)>> {
+    use snafu::{ResultExt, OptionExt};
+    let content = tokio::fs::read_to_string(path).await.context(errors::IoSnafu)?;
+    let mut ret = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        let words = line.split('#').next().unwrap().split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            [] => {},
+            [a, b] => ret.push((ElementHandle::from(a), ElementHandle::from(b))),
+            _ => return None.context(errors::ParsingPairsSnafu { line_number: idx + 1 }),
+        }
+    }
+    Ok(ret)
+}
+
+/// Read the lines of an aspect and its holding, `#` starts a comment. The holdings with
+/// their line numbers.
+async fn read_holdings_file(path: &std::path::Path) -> errors::Result<Vec<(usize, ElementHandle, f64)>> {
+    use snafu::{ResultExt, OptionExt};
+    let content = tokio::fs::read_to_string(path).await.context(errors::IoSnafu)?;
+    let mut ret = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        let words = line.split('#').next().unwrap().split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            [] => {},
+            [name, num] if num.parse::<f64>().is_ok_and(|n| n >= 0.) =>
+                ret.push((idx + 1, ElementHandle::from(name), num.parse().unwrap())),
+            _ => return None.context(errors::ParsingHoldingsSnafu { line_number: idx + 1 }),
+        }
+    }
+    Ok(ret)
 }
 
+/// A `Aer 64` line for each holding, sorted by name, as `read_holdings_file` reads them.
+fn holdings_text(holdings: &[(ElementHandle, f64)]) -> String {
+    let mut sorted = holdings.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    sorted.iter()
+        .map(|(ele, num)| format!("{} {}\n", ele.get_name(), num))
+        .collect()
+}
+
+/// A `Aer->Ignis: 1` line for each pair.
+async fn min_steps_report(dao: Arc<dao::DAO>, pairs: &[(ElementHandle, ElementHandle)], max_steps: usize)
+    -> Vec<String> {
+        let mut ret = vec![];
+        for (from, to) in pairs {
+            let mut line = format!("{}->{}: ", from.get_name(), to.get_name());
+            let stored_from = dao.find_element(from).await.expect("`does elements exists` failed");
+            let stored_to = dao.find_element(to).await.expect("`does elements exists` failed");
+            match (stored_from, stored_to) {
+                (Some(from), Some(to)) => {
+                    match pathes::calc_shortest_path(dao.clone(), &from, &to, max_steps).await
+                        .expect("Calc shortest path failed.") {
+                            Some(path) => line += &path.steps().len().to_string(),
+                            None => line += &format!("unconnectable within {}", max_steps),
+                    }
+                },
+                (None, _) => line += &format!("the element {} doesn't exists", from.get_name()),
+                (_, None) => line += &format!("the element {} doesn't exists", to.get_name()),
+            }
+            ret.push(line);
+        }
+        ret
+}
+
+const DATABASE_FILE: &str = "aspects.sqlite3";
+const DATABASE_URL: &str = "sqlite://aspects.sqlite3";
+
 #[tokio::main]
 async fn main() {
     let _ = &*INIT_SQLX_DRIVERS;
-    let dao = Arc::new(dao::DAO::new_str("sqlite://aspects.sqlite3").await);
+    let dao = Arc::new(dao::DAO::new_str(DATABASE_URL).await);
     let cli = Cli::parse();
+    if cli.preflight && !passes_preflight(dao.as_ref()).await {
+        std::process::exit(1);
+    }
+    let mut weights = match cli.alpha.map(pathes::WeightConfig::with_alpha) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("Invalid alpha {}: {}", cli.alpha.unwrap(), e);
+            std::process::exit(1);
+        },
+        None => pathes::WeightConfig::default(),
+    };
+    if let Some(rate) = cli.blend_rate
+        && let Err(e) = weights.set_blend_rate(rate) {
+            eprintln!("Invalid blend rate {}: {}", rate, e);
+            std::process::exit(1);
+    }
+    if let Some(n) = cli.weight_concurrency {
+        weights.set_concurrency(n);
+    }
+
+    let mut query_cache = if cli.query_cache {
+        let db_path = std::path::Path::new(DATABASE_FILE);
+        Some(query_cache::QueryCache::load(query_cache::QueryCache::sidecar_of(db_path), db_path)
+            .await
+            .expect("Load query cache failed."))
+    } else {
+        None
+    };
+    run(dao.clone(), &cli.command, cli.format, &weights, query_cache.as_mut()).await;
+    if let Some(cache) = &query_cache
+        && let Err(e) = cache.save().await {
+        eprintln!("Save query cache failed: {e}");
+    }
 
-    match &cli.command {
-        Commands::ListElementsHolding => {
-            let res = dao.list_elements_holding().await.expect("list_elements_holding failed.");
-            res.iter()
-                .for_each(|(e, f)| {
-                    println!("Element: {} | Number: {:.0}", e.get_name(), f);
-                })
+    if cli.cache_stats {
+        let cache = dao.relatives_cache();
+        eprintln!("relatives cache: {} hits, {} misses, {} elements",
+            cache.hits(), cache.misses(), cache.size().await);
+    }
+}
+
+/// Run `pathes::preflight_problems`, printing them if there are.
+async fn passes_preflight(dao: &dao::DAO) -> bool {
+    let problems = pathes::preflight_problems(dao).await;
+    if !problems.is_empty() {
+        eprintln!("Preflight found {} problems, nothing is run:", problems.len());
+        for problem in &problems {
+            eprintln!("  {problem}");
+        }
+    }
+    problems.is_empty()
+}
+
+async fn run(dao: Arc<dao::DAO>, command: &Commands, format: OutputFormat, weights: &pathes::WeightConfig,
+    query_cache: Option<&mut query_cache::QueryCache>) {
+    match command {
+        Commands::ModIntegration { steps_n } => {
+            let report = pathes::mod_integration_report(dao.clone(), *steps_n).await
+                .expect("Calc mod integration failed.");
+            for m in report.iter().filter(|a| a.is_isolated()) {
+                println!("ISOLATED: {} has no aspect connected to {}", m.mod_name, pathes::BASE_MOD);
+            }
+            for m in &report {
+                println!("{}: {}/{} connected ({:.0}%)",
+                    m.mod_name, m.connected, m.total, m.fraction() * 100.);
+            }
+        },
+        Commands::Report { integration_steps } => {
+            let report = report::build_report(dao.clone(), *integration_steps).await
+                .expect("Build report failed.");
+            if format == OutputFormat::Json {
+                println!("{}", serde_json::to_string(&report).expect("Serialize report failed."));
+            } else {
+                print!("{}", report.to_markdown());
+            }
+        },
+        Commands::Backup { to } => {
+            dao.backup(&to.to_string_lossy()).await.expect("Backup failed.");
+        },
+        Commands::Restore { from, yes } => {
+            if !yes {
+                eprintln!("Restore overwrites the current database, pass --yes to confirm.");
+                return;
+            }
+            dao.restore(&from.to_string_lossy()).await.expect("Restore failed.");
+        },
+        Commands::RandomHoldings { max, seed, yes } => {
+            if !yes {
+                eprintln!("RandomHoldings overwrites the current holdings, pass --yes to confirm.");
+                return;
+            }
+            let elements = dao.list_elements().await.expect("list elements failed.")
+                .into_iter()
+                .map(|e| ElementHandle::from(e.name))
+                .collect::<Vec<_>>();
+            let holdings = random_holdings(elements, *max, *seed);
+            let n = dao.set_holdings_bulk(&holdings).await.expect("Set holdings failed.");
+            println!("{n} holdings randomized.");
+        },
+        Commands::ResetHoldings { yes } => {
+            if !yes {
+                eprintln!("ResetHoldings overwrites the current holdings, pass --yes to confirm.");
+                return;
+            }
+            let n = dao.reset_all_holdings().await.expect("Reset holdings failed.");
+            println!("{n} holdings reset.");
+        },
+        Commands::ImportHoldings { path } => {
+            let lines = match read_holdings_file(path).await {
+                Ok(lines) => lines,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+            };
+            let mut holdings = vec![];
+            let mut skipped = vec![];
+            for (line_number, ele, num) in lines {
+                if dao.does_element_exists(&ele).await.expect("`does elements exists` failed") {
+                    holdings.push((ele, num));
+                } else {
+                    skipped.push(format!("line {}: {}", line_number, element_not_found(dao.as_ref(), &ele.get_name()).await));
+                }
+            }
+            if !skipped.is_empty() {
+                for line in skipped {
+                    eprintln!("{}", line);
+                }
+                eprintln!("No holdings imported.");
+                std::process::exit(1);
+            }
+            let n = dao.import_holdings(&holdings).await.expect("Import holdings failed.");
+            println!("{n} holdings imported.");
+        },
+        Commands::ExportHoldings { output } => {
+            let holdings = dao.list_elements_holding().await.expect("list elements holding failed.");
+            let text = holdings_text(&holdings);
+            match output {
+                Some(output) => tokio::fs::write(output, text).await.expect("Write holdings failed."),
+                None => print!("{}", text),
+            }
+        },
+        Commands::Combine { a, b } => {
+            let (a, b) = (ElementHandle::from(a.clone()), ElementHandle::from(b.clone()));
+            match dao.product_of(&a, &b).await.expect("Find the product failed.") {
+                Some(product) => println!("{}", product.get_name()),
+                None => println!("no recipe"),
+            }
+        },
+        Commands::CraftableWithin { max_crafts } => {
+            let depths = pathes::craftable_within_budget(dao.clone(), *max_crafts).await
+                .expect("Calc craftable aspects failed.");
+            if depths.is_empty() {
+                println!("Nothing new can be crafted from the holdings.");
+            }
+            for (i, made) in depths.iter().enumerate() {
+                println!("Depth {}: {}", i + 1, made.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "));
+            }
+        },
+        Commands::SourceFor { primal, needed } => {
+            let Some(primal) = stored_element(dao.as_ref(), primal).await else {
+                return;
+            };
+            let sources = pathes::best_source_for_primal(dao.clone(), &primal, *needed).await
+                .expect("Calc sources failed.");
+            for x in sources {
+                println!("{}: yields {} per craft, crack {} | efficiency {:.3}",
+                    x.element.get_name(), x.yield_per_craft, x.crafts_needed, x.efficiency);
+            }
+        },
+        Commands::CraftVsFarm { primal, needed } => {
+            let Some(primal) = stored_element(dao.as_ref(), primal).await else {
+                return;
+            };
+            if !dao.is_primary_element(&primal).await.expect("`is primary element` failed") {
+                eprintln!("The element {} isn't a primary element", primal.get_name());
+                return;
+            }
+            let res = pathes::craft_vs_farm(dao.clone(), &primal, *needed).await.expect("Calc craft vs farm failed.");
+            println!("farm {} {}: cost {}", needed, primal.get_name(), res.farm_cost);
+            match &res.craft {
+                Some((source, cost)) => println!("crack {} {}: cost {}",
+                    source.crafts_needed, source.element.get_name(), cost),
+                None => println!("no compound cracks to {}", primal.get_name()),
+            }
+            println!("recommend: {:?}", res.recommendation);
+        },
+        Commands::BestNewLink { max_steps, sample, limit } => {
+            let adjacency = dao.load_full_adjacency().await.expect("Load adjacency failed.");
+            let links = pathes::best_new_link(&adjacency, *max_steps, *sample);
+            for link in links.into_iter().take(*limit) {
+                println!("{} - {}: +{} pairs", link.a.get_name(), link.b.get_name(), link.gain);
+            }
+        },
+        Commands::SuggestBridge { a, b, limit } => {
+            let (a, b) = (ElementHandle::from(a.clone()), ElementHandle::from(b.clone()));
+            let Some([a, b]) = stored_elements(dao.as_ref(), [a, b]).await else {
+                return;
+            };
+            let bridges = pathes::suggest_bridging_recipes(dao.clone(), &a, &b).await
+                .expect("Suggest bridging recipes failed.");
+            if bridges.is_empty() {
+                println!("{} and {} are already connected.", a.get_name(), b.get_name());
+            }
+            for bridge in bridges.into_iter().take(*limit) {
+                println!("new compound = {} + {}: connects in {} steps",
+                    bridge.component_a.get_name(), bridge.component_b.get_name(), bridge.steps);
+            }
+        },
+        Commands::CriticalAspect { sample, limit } => {
+            let adjacency = dao.load_full_adjacency().await.expect("Load adjacency failed.");
+            let ranked = pathes::most_critical_aspect(&adjacency, *sample);
+            for (ele, pairs) in ranked.into_iter().take(*limit) {
+                println!("{}: +{} disconnected pairs", ele.get_name(), pairs);
+            }
+        },
+        Commands::CompareInventory { target } => {
+            let Some(target) = read_quantities_file(target).await.expect("read the target file failed.") else {
+                eprintln!("The target must be aspects each followed by an optional quantity, like `Aer 64, Ignis 64`");
+                return;
+            };
+            let (eles, quantities): (Vec<_>, Vec<_>) = target.into_iter().unzip();
+            let Some(eles) = stored_elements(dao.as_ref(), eles).await else {
+                return;
+            };
+            let target = eles.into_iter().zip(quantities).collect::<Vec<_>>();
+            let holdings = dao.list_elements_holding().await.expect("list elements holding failed.")
+                .into_iter()
+                .collect();
+            for (ele, held, wanted, diff) in compare_inventory(&holdings, &target) {
+                println!("{}: {} / {}, {:+}", ele.get_name(), held, wanted, diff);
+            }
+        },
+        Commands::ResearchAspects { aspect } => {
+            let Some(ele) = stored_element(dao.as_ref(), aspect).await else {
+                return;
+            };
+            let eles = pathes::all_aspects_in_tree(dao.clone(), &ele).await
+                .expect("List the aspects in the tree failed.");
+            for ele in eles {
+                println!("{}", ele.get_name());
+            }
+        },
+        Commands::ByComplexity { k } => {
+            let eles = pathes::aspects_by_primal_count(dao.clone(), *k).await
+                .expect("Find aspects by primal count failed.");
+            for ele in eles {
+                println!("{}", ele.get_name());
+            }
+        },
+        Commands::FindGaps => {
+            let gaps = dao.find_undecomposable_non_primals().await.expect("Find gaps failed.");
+            if gaps.is_empty() {
+                eprintln!("Every aspect but the primal ones has a recipe.");
+            }
+            for ele in gaps {
+                println!("{}", ele.get_name());
+            }
+        },
+        Commands::CheckIntegrity => {
+            let violations = dao.check_foreign_keys().await.expect("Check foreign keys failed.");
+            if violations.is_empty() {
+                println!("ok");
+            }
+            for v in violations {
+                println!("{}: {} references a missing row of {}", v.table, v.row, v.parent);
+            }
+        },
+        Commands::Centroid => {
+            match pathes::holdings_centroid(dao.clone()).await.expect("Calc holdings centroid failed.") {
+                Some((ele, similarity)) => println!("{}: similarity {:.2}", ele.get_name(), similarity),
+                None => eprintln!("Nothing countable is held."),
+            }
+        },
+        Commands::Similarity { a, b } => {
+            let (a, b) = (ElementHandle::from(a.clone()), ElementHandle::from(b.clone()));
+            let Some([a, b]) = stored_elements(dao.as_ref(), [a, b]).await else {
+                return;
+            };
+            let overlap = pathes::decomposition_overlap(dao.clone(), &a, &b).await
+                .expect("Calc decomposition overlap failed.");
+            println!("shared: {}", overlap.shared.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "));
+            println!("jaccard: {:.2}", overlap.jaccard);
+            println!("count overlap: {}", overlap.count_overlap);
+        },
+        Commands::Mermaid { aspect } => {
+            let Some(ele) = stored_element(dao.as_ref(), aspect).await else {
+                return;
+            };
+            print!("{}", pathes::decomposition_mermaid(dao.clone(), &ele).await.expect("Render mermaid failed."));
+        },
+        Commands::VerifySymmetry => {
+            let asymmetries = pathes::verify_symmetry(dao.as_ref()).await.expect("Verify symmetry failed.");
+            if asymmetries.is_empty() {
+                println!("All the relatives are symmetric.");
+            }
+            for (a, b) in asymmetries {
+                println!("{} relates to {}, but not the other way around", a.get_name(), b.get_name());
+            }
+        },
+        Commands::MergeCaseDuplicates { canonical } => {
+            let Some(canonical) = stored_element(dao.as_ref(), canonical).await else {
+                return;
+            };
+            let merged = dao.merge_case_duplicates(&canonical).await.expect("Merge case duplicates failed.");
+            if merged != 0 {
+                println!("merged {} elements into {}", merged, canonical.get_name());
+                return;
+            }
+            println!("{} has no case duplicate.", canonical.get_name());
+            for group in dao.find_case_duplicate_elements().await.expect("Find case duplicates failed.") {
+                println!("{}", group.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "));
+            }
+        },
+        Commands::EquivalentAspects => {
+            let groups = pathes::find_equivalent_aspects(dao.clone()).await
+                .expect("Find equivalent aspects failed.");
+            for group in groups {
+                println!("{}", group.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "));
+            }
+        },
+        Commands::GroupByPrimal => {
+            let groups = pathes::group_by_dominant_primal(dao.clone()).await
+                .expect("Group by dominant primal failed.");
+            for (primal, group) in groups {
+                println!("{}: {}", primal.get_name(), group.iter().map(|a| a.get_name()).collect::<Vec<_>>().join(", "));
+            }
         },
-        Commands::ChangeElementHolding { element_name, change_to_num } => {
+        Commands::Wasted { threshold } => {
+            if !(*threshold > 0. && *threshold <= 1.) {
+                eprintln!("The threshold must be in (0, 1], but input is {}", threshold);
+                return;
+            }
+            let wasted = pathes::wasted_holdings(dao.clone(), *threshold, weights).await
+                .expect("Find wasted holdings failed.");
+            for (ele, fraction) in wasted {
+                println!("{}: {:.1}%", ele.get_name(), fraction * 100.);
+            }
+        },
+        Commands::VerifyBaseValues { tolerance } => {
+            if *tolerance < 0. {
+                eprintln!("The tolerance must be non-negative, but input is {}", tolerance);
+                return;
+            }
+            let flagged = pathes::verify_base_values(dao.clone(), *tolerance).await
+                .expect("Verify base values failed.");
+            for d in &flagged {
+                println!("{}: base value {}, primal cost {}, {:+.1}%",
+                    d.element.get_name(), d.base_value, d.primal_cost, d.deviation * 100.);
+            }
+            if flagged.is_empty() {
+                println!("All the base values are within the tolerance.");
+            }
+        },
+        Commands::Tiers => {
+            let cached = query_cache.as_ref().and_then(|c| c.get::<Vec<(String, usize)>>("tiers"));
+            let tiers = match cached {
+                Some(tiers) => tiers,
+                None => {
+                    let tiers: Vec<(String, usize)> = pathes::aspect_tiers(dao.clone()).await
+                        .expect("Calc tiers failed.")
+                        .into_iter()
+                        .map(|(ele, tier)| (ele.get_name(), tier))
+                        .collect();
+                    if let Some(cache) = query_cache {
+                        cache.put("tiers", &tiers);
+                    }
+                    tiers
+                },
+            };
+            let mut grouped: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+            for (name, tier) in tiers {
+                grouped.entry(tier).or_default().push(name);
+            }
+            for (tier, mut names) in grouped {
+                names.sort();
+                println!("Tier {}: {}", tier, names.join(", "));
+            }
+        },
+        Commands::TerminalAspects => {
+            let res = dao.find_terminal_products().await.expect("find terminal products failed.");
+            res.iter().for_each(|e| {
+                println!("{}", e.get_name());
+            })
+        },
+        Commands::Tag { element_name, tag } => {
+            let ele = ElementHandle::from(element_name.clone());
+            dao.add_tag(&ele, tag).await.expect("Tag element failed.");
+        },
+        Commands::Untag { element_name, tag } => {
             let ele = ElementHandle::from(element_name.clone());
+            if !dao.remove_tag(&ele, tag).await.expect("Untag element failed.") {
+                eprintln!("The element {} isn't tagged with {}", element_name, tag);
+            }
+        },
+        Commands::ListByTag { tag } => {
+            let res = dao.list_by_tag(tag).await.expect("list by tag failed.");
+            res.iter().for_each(|e| {
+                println!("{}", e.get_name());
+            })
+        },
+        Commands::ListElementsHolding { chart } => {
+            let mut res = dao.list_elements_holding().await.expect("list_elements_holding failed.");
+            if *chart {
+                res.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                let max = res.first().map_or(0., |a| a.1);
+                let name_width = res.iter().map(|a| a.0.get_name().len()).max().unwrap_or(0);
+                res.iter()
+                    .for_each(|(e, f)| {
+                        println!("{:<name_width$} {:<CHART_WIDTH$} {:.0}", e.get_name(),
+                            "\u{2588}".repeat(holding_bar_len(*f, max, CHART_WIDTH)), f);
+                    })
+            } else {
+                res.iter()
+                    .for_each(|(e, f)| {
+                        println!("Element: {} | Number: {:.0}", e.get_name(), f);
+                    })
+            }
+        },
+        Commands::ChangeElementHolding { element_name, change_to_num, preview } => {
+            let Some(ele) = stored_element(dao.as_ref(), element_name).await else {
+                return;
+            };
+            if let Some(preview) = preview {
+                let from = ElementHandle::from(preview[0].clone());
+                let to = ElementHandle::from(preview[1].clone());
+                let Ok(steps_n) = preview[2].parse::<usize>() else {
+                    eprintln!("STEPS must be a number, but input is {}", preview[2]);
+                    return;
+                };
+                let Some([from, to]) = stored_elements(dao.as_ref(), [from, to]).await else {
+                    return;
+                };
+                let before = pathes::calc_path_order_by_weight_filtered(dao.clone(), &from, &to, steps_n,
+                    &pathes::SearchFilter::default(), weights).await
+                    .expect("Calc pathes failed.");
+                let mut config = weights.clone();
+                config.override_holding(ele.clone(), *change_to_num as f64);
+                let after = pathes::calc_path_order_by_weight_filtered(dao.clone(), &from, &to, steps_n,
+                    &pathes::SearchFilter::default(), &config).await
+                    .expect("Calc pathes failed.");
+                for (i, path) in after.iter().enumerate() {
+                    println!("{:>4}  {:?}", i + 1, path);
+                }
+                let changes = pathes::rank_changes(&before, &after);
+                if changes.iter().all(|a| a.delta() == 0) {
+                    println!("The ranking doesn't change.");
+                }
+                for change in changes.iter().filter(|a| a.delta() != 0) {
+                    println!("{:+} {:?}: {} -> {}", change.delta(), change.path, change.before, change.after);
+                }
+
+                eprint!("Commit the change? [y/N] ");
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer).expect("Read stdin failed.");
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    return;
+                }
+            }
             dao.change_element_holding(&ele, *change_to_num).await
                 .expect("Change Element Holding failed.");
         },
+        Commands::ClearWeightCache => {
+            let removed = dao.clear_weight_cache().await.expect("Clear weight cache failed.");
+            println!("removed {} cached weights", removed);
+        },
+        Commands::PrecomputeWeights { concurrency } => {
+            let cached = pathes::precompute_weights(dao.clone(), weights, *concurrency,
+                |done, total| eprint!("\r{done}/{total} weights"))
+                .await
+                .expect("Precompute weights failed.");
+            eprintln!();
+            println!("cached {} weights", cached);
+        },
+        Commands::PrecomputeDistances => {
+            let adjacency = dao.load_full_adjacency().await.expect("Load adjacency failed.");
+            let stored = pathes::precompute_distances(dao.clone(), &adjacency).await
+                .expect("Precompute distances failed.");
+            println!("stored {} distances", stored);
+        },
+        Commands::SavePreset { name } => {
+            let saved = dao.save_preset(name).await.expect("Save preset failed.");
+            println!("saved {} holdings as {}", saved, name);
+        },
+        Commands::LoadPreset { name } => {
+            match dao.load_preset(name).await {
+                Ok(loaded) => println!("loaded {} holdings from {}", loaded, name),
+                Err(dao::Errors::PresetNotFound(_)) => eprintln!("The preset {} doesn't exists", name),
+                Err(e) => {
+                    eprintln!("Load preset failed: {}", e);
+                    std::process::exit(1);
+                },
+            }
+        },
+        Commands::ListPresets => {
+            for (name, num) in dao.list_presets().await.expect("List presets failed.") {
+                println!("{}: {} holdings", name, num);
+            }
+        },
+        Commands::AddRecipe { name, component_a, component_b } => {
+            dao.add_recipe(&ElementHandle::from(name.clone()),
+                &ElementHandle::from(component_a.clone()),
+                &ElementHandle::from(component_b.clone())).await
+                .expect("Add recipe failed.");
+        },
+        Commands::RemoveRecipe { name } => {
+            dao.remove_recipe(&ElementHandle::from(name.clone())).await
+                .expect("Remove recipe failed.");
+        },
         Commands::ListMods => {
             let res = dao.list_mods().await.expect("list mods failed.");
             res.iter().for_each(|a| {
                 println!("{}", a);
             })
         }
+        Commands::ExportSql => {
+            print!("{}", dao.export_sql().await.expect("export sql failed."));
+        },
         Commands::ListRecipes => {
             let res
                 = dao.list_recipes().await.expect("list recipes failed.");
             for (name, ca, cb) in res {
                 println!("{} = {} + {}", name.get_name(), ca.get_name(), cb.get_name());
             }
-            std::process::exit(0);
         },
-        Commands::Crack { aspects } => {
+        Commands::MatchProfile { primals, cover, limit } => {
+            let Some(quantities) = parse_aspect_quantities(primals) else {
+                eprintln!("Must input the primal aspects, each followed by an optional quantity.");
+                return;
+            };
+            let mut wanted = std::collections::HashMap::new();
+            for (ele, num) in quantities {
+                if !dao.is_primary_element(&ele).await.expect("`is primary element` failed") {
+                    eprintln!("The element {} isn't a primary element", ele.get_name());
+                    return;
+                }
+                *wanted.entry(ele).or_insert(0) += num;
+            }
+            let ranked = pathes::find_closest_compound(dao.clone(), &wanted, *cover).await
+                .expect("Find closest compound failed.");
+            for (ele, distance) in ranked.into_iter().take(*limit) {
+                println!("{}: distance {}", ele.get_name(), distance);
+            }
+        },
+        Commands::AllCracks { aspect } => {
+            let Some(ele) = stored_element(dao.as_ref(), aspect).await else {
+                return;
+            };
+            let profiles = pathes::all_decompositions(dao.clone(), &ele).await.expect("Crack failed.");
+            if format == OutputFormat::Json {
+                let v = profiles.iter().map(primal_counts_json).collect::<Vec<_>>();
+                println!("[{}]", v.join(","));
+                return;
+            }
+            for profile in profiles {
+                let mut counts = profile.into_iter().collect::<Vec<_>>();
+                counts.sort();
+                println!("{}", counts.iter().map(|(e, n)| format!("{}: {}", e.get_name(), n)).collect::<Vec<_>>().join(", "));
+            }
+        },
+        Commands::Crack { aspects, depth, by_mod } => {
             let insert_or_add =
                 |mp: &mut HashMap<ElementHandle, usize>, eleh: ElementHandle, sz: usize| {
                     if let Some(ct) = mp.get_mut(&eleh) {
@@ -97,51 +1375,40 @@ async fn main() {
             };
 
             use std::collections::HashMap;
-            let mut mp: HashMap<ElementHandle, usize> = HashMap::new();
-
-            if aspects.len() == 0 {
-                panic!("Must input at least one element.");
-            }
-            if aspects.get(0).unwrap().parse::<usize>().is_ok() {
-                panic!("The first element in array must be an aspect.")
-            }
-            let mut idx = 0usize;
-            while idx < aspects.len() {
-                // idx is passed the break test
-                let gt_str = aspects.get(idx).unwrap();
-                let gt = ElementHandle::from(gt_str.clone());
-
-                if idx + 1 < aspects.len() {
-                    if dao.does_element_exists(&gt).await.expect("call does_element_exists failed") {
-                        if let Ok(e) = aspects.get(idx+1).unwrap().parse::<usize>() {
-                            insert_or_add(&mut mp, gt, e);
-                            idx += 2;
-                        } else {
-                            insert_or_add(&mut mp, gt, 1usize);
-                            idx += 1;
-                        }
-                    } else {
-                        panic!("element {} doesn't exists.", gt_str);
-                    }
-                } else { // this is the last string.
-                    if dao.does_element_exists(&gt).await.expect("call does_element_exists failed.") {
-                        insert_or_add(&mut mp, gt, 1usize);
-                        idx += 1;
-                    } else {
-                        panic!("element {} doesn't exists.", gt_str);
-                    }
-                }
-            }
+            let mp = match pathes::parse_crack_args(dao.as_ref(), aspects).await {
+                Ok(mp) => mp,
+                Err(errors::T4ACHError::ElementNotFound { element_name, .. }) => {
+                    eprintln!("{}", element_not_found(dao.as_ref(), &element_name).await);
+                    std::process::exit(1);
+                },
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                },
+            };
             let mut ret = HashMap::new();
 
             for aspect in &mp {
-                for elee in
-                    pathes::crack_element_until_primary(dao.clone(), aspect.0)
-                        .await.expect("crack element until primary") {
-                            insert_or_add(&mut ret, elee.0, elee.1 * aspect.1);
-                        }
+                let cracked = match depth {
+                    Some(depth) => pathes::crack_element_to_depth(dao.clone(), aspect.0, *depth).await,
+                    None => pathes::crack_element_until_primary(dao.clone(), aspect.0).await,
+                };
+                let cracked = match cracked {
+                    Ok(cracked) => cracked,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    },
+                };
+                for elee in cracked {
+                    insert_or_add(&mut ret, elee.0, elee.1 * aspect.1);
+                }
             }
 
+            if format == OutputFormat::Json {
+                println!("{}", primal_counts_json(&ret));
+                return;
+            }
             let mut vret = ret.iter().collect::<Vec<_>>();
             vret.sort_by(|a, b| {
                 a.0.cmp(b.0)
@@ -149,37 +1416,725 @@ async fn main() {
             for x in vret {
                 println!("{}: {}", x.0.get_name(), x.1);
             }
+            println!("Total: {}", ret.values().sum::<usize>());
+            if *by_mod {
+                let by_mod = pathes::counts_by_mod(dao.as_ref(), &ret).await.expect("Sum by mod failed.");
+                for (mod_name, n) in by_mod {
+                    println!("{} total: {}", mod_name, n);
+                }
+            }
         },
-        Commands::TryConnect { from, to, steps_n } => {
-            let from = recipes::ElementHandle::from(from.clone());
-            let to = recipes::ElementHandle::from(to.clone());
-
-            if !dao.does_element_exists(&from).await.expect("`does elements exists` failed") {
-                eprintln!("The element {} doesn't exists", from.get_name());
+        Commands::TryConnect { from, to, steps_n, exclude_tag, avoid_mod, owned_only, on_board, through, limit, lazy, csv,
+            edges, enriched, summary, score, show_readiness, decay, annotate_holdings, show_cost, cost_in_primals, criteria, uncertain, dump_search, both_directions } => {
+            if *edges && (*enriched || *score || *show_readiness || *annotate_holdings || *show_cost
+                || uncertain.is_some() || format != OutputFormat::Text) {
+                eprintln!("--edges is a plain text output of its own");
                 return;
             }
-            if !dao.does_element_exists(&to).await.expect("`does elements exists` failed") {
-                eprintln!("The element {} doesn't exists", to.get_name());
+            if *enriched && format != OutputFormat::Json {
+                eprintln!("--enriched is only for --format json");
                 return;
             }
-            let pathes =
-                pathes::calc_path_order_by_weight(dao.clone(), &from, &to, steps_n.clone()).await
-                .expect("Calc pathes failed."); 
+            if (*score || *show_readiness || *annotate_holdings || *show_cost || uncertain.is_some())
+                && format != OutputFormat::Text {
+                eprintln!("--score, --show-readiness, --annotate-holdings, --show-cost and --uncertain are only for --format text");
+                return;
+            }
+            if let Some(fraction) = uncertain && !(0. ..=1.).contains(fraction) {
+                eprintln!("The uncertainty must be within [0, 1], but input is {}", fraction);
+                return;
+            }
+            let Some(from) = stored_element(dao.as_ref(), from).await else {
+                return;
+            };
+            let Some(to) = stored_element(dao.as_ref(), to).await else {
+                return;
+            };
+            let mut filter = pathes::SearchFilter::default();
+            for tag in exclude_tag {
+                filter.exclude(dao.list_by_tag(tag).await.expect("list by tag failed."));
+            }
+            if !avoid_mod.is_empty() {
+                let mods = dao.list_mods().await.expect("list mods failed.");
+                for mod_name in avoid_mod {
+                    if !mods.contains(mod_name) {
+                        eprintln!("The mod {} doesn't exists", mod_name);
+                        return;
+                    }
+                    filter.exclude(pathes::elements_of_mod(dao.as_ref(), mod_name).await
+                        .expect("list elements of mod failed."));
+                }
+            }
+            if *owned_only {
+                filter.exclude(pathes::unheld_elements(dao.as_ref()).await.expect("list unheld elements failed."));
+            }
+            let through = through.iter()
+                .map(|a| ElementHandle::from(a.clone()))
+                .collect::<Vec<_>>();
+            let Some(through) = stored_elements(dao.as_ref(), through).await else {
+                return;
+            };
+            filter.pass_through(through);
+            let trace = pathes::SearchTrace::default();
+            if dump_search.is_some() {
+                filter.record_into(trace.clone());
+            }
+            let mut config = weights.clone();
+            if let Some(on_board) = on_board {
+                let eles = read_aspects_file(on_board).await.expect("read the on board file failed.");
+                let Some(eles) = stored_elements(dao.as_ref(), eles).await else {
+                    return;
+                };
+                config.place_on_board(eles);
+            }
+            if let Some(hours) = decay {
+                if *hours <= 0. {
+                    eprintln!("The half-life must be positive, but input is {}", hours);
+                    return;
+                }
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                    .expect("The clock is before 1970.")
+                    .as_secs() as i64;
+                config.decay_holdings(hours * 3600., now);
+            }
+            let pathes = if let (true, Some(limit)) = (lazy, limit) {
+                pathes::calc_path_order_by_weight_lazy(dao.clone(), &from, &to, *steps_n, &filter, &config, *limit)
+                    .await
+            } else {
+                pathes::calc_path_order_by_weight_filtered(dao.clone(), &from, &to, *steps_n, &filter, &config)
+                    .await
+            }.expect("Calc pathes failed.");
+            if *both_directions {
+                let forth = pathes::calc_path_filtered(dao.clone(), &from, &to, *steps_n, &filter).await
+                    .expect("Calc pathes failed.");
+                let back = pathes::calc_path_filtered(dao.clone(), &to, &from, *steps_n, &filter).await
+                    .expect("Calc pathes failed.");
+                let forth_n = forth.len();
+                let forth = forth.into_iter().collect::<std::collections::HashSet<_>>();
+                let back = back.iter().map(pathes::Path::reversed).collect::<std::collections::HashSet<_>>();
+                if forth == back && forth.len() == forth_n {
+                    eprintln!("The same {} paths both ways.", forth_n);
+                } else {
+                    eprintln!("The searches disagree: {} paths from {}, {} from {}, {} in common.",
+                        forth.len(), from.get_name(), back.len(), to.get_name(), forth.intersection(&back).count());
+                }
+            }
+            if let Some(dump_search) = dump_search {
+                let content = serde_json::to_string_pretty(&trace.events()).expect("Serialize search trace failed.");
+                tokio::fs::write(dump_search, content).await.expect("Write search dump failed.");
+            }
+            let (mut pathes, mut composite) = match criteria {
+                Some(criteria) => pathes::rank_by_criteria(dao.clone(), pathes, criteria, &config).await
+                    .expect("Rank pathes by criteria failed.")
+                    .into_iter()
+                    .map(|(path, score)| (path, Some(score)))
+                    .unzip(),
+                None => {
+                    let n = pathes.len();
+                    (pathes, vec![None; n])
+                },
+            };
+            pathes.truncate(limit.unwrap_or(usize::MAX));
+            composite.truncate(pathes.len());
 
-            if pathes.is_empty() {
+            if *enriched {
+                let mut v = vec![];
+                for path in &pathes {
+                    v.push(pathes::enrich_path(dao.clone(), path, &config).await.expect("Enrich path failed."));
+                }
+                println!("{}", serde_json::to_string(&v).expect("Serialize pathes failed."));
+            } else if format == OutputFormat::Json {
+                let v = pathes.iter().map(pathes::PathDto::from).collect::<Vec<_>>();
+                println!("{}", serde_json::to_string(&v).expect("Serialize pathes failed."));
+            } else if *csv {
+                print!("{}", pathes_to_csv(&pathes));
+            } else if *edges {
+                print!("{}", pathes_to_edges(&pathes));
+            } else if pathes.is_empty() {
                 eprintln!("can't be connected");
             } else {
-                for path in pathes {
-                    println!("{:?}", path);
+                let holdings = if *annotate_holdings {
+                    dao.list_elements_holding().await.expect("list elements holding failed.")
+                        .into_iter()
+                        .collect()
+                } else {
+                    std::collections::HashMap::new()
+                };
+                for ((path, path_score), composite) in pathes.iter().zip(pathes::score_pathes(&pathes)).zip(&composite) {
+                    let mut line = if *annotate_holdings {
+                        self::annotate_holdings(path, &holdings)
+                    } else if let Some(fraction) = uncertain {
+                        let (low, high) = pathes::calc_weight_path_interval(dao.clone(), path, &config, *fraction).await
+                            .expect("Calc weight interval failed.");
+                        let chain = std::iter::once(path.start())
+                            .chain(path.steps())
+                            .chain(std::iter::once(path.end()))
+                            .map(|a| a.get_name())
+                            .collect::<Vec<_>>()
+                            .join("->");
+                        format!("{}: weight {}..{}", chain, low, high)
+                    } else {
+                        format!("{:?}", path)
+                    };
+                    if let (true, Some(path_score)) = (score, path_score) {
+                        line += &format!(", score {:.0}", path_score);
+                    }
+                    if *show_readiness {
+                        let readiness = pathes::path_readiness(dao.clone(), path, &config).await
+                            .expect("Calc readiness failed.");
+                        line += &format!(", readiness {:.2}", readiness);
+                    }
+                    if let Some(composite) = composite {
+                        line += &format!(", criteria {:.2}", composite);
+                    }
+                    if *show_cost {
+                        let mut cost = pathes::path_aspect_cost(dao.clone(), path, *cost_in_primals).await
+                            .expect("Calc path cost failed.")
+                            .into_iter()
+                            .collect::<Vec<_>>();
+                        cost.sort();
+                        let cost = cost.iter()
+                            .map(|(ele, n)| format!("{} {}", ele.get_name(), n))
+                            .collect::<Vec<_>>();
+                        line += &format!(", cost {}", cost.join(" "));
+                    }
+                    println!("{line}");
+                }
+            }
+            if *summary {
+                let names = pathes::distinct_intermediates(&pathes).iter()
+                    .map(|e| e.get_name())
+                    .collect::<Vec<_>>();
+                let line = format!("{} distinct intermediates: {}", names.len(), names.join(", "));
+                if *csv || *edges || format == OutputFormat::Json {
+                    eprintln!("{line}");
+                } else {
+                    println!("{line}");
                 }
             }
         }, 
-        Commands::ListElements => {
+        Commands::NextStep { partial, to, remaining, limit } => {
+            let partial = partial.iter().map(|a| ElementHandle::from(a.clone())).collect::<Vec<_>>();
+            let Some(partial) = stored_elements(dao.as_ref(), partial).await else {
+                return;
+            };
+            let Some(to) = stored_element(dao.as_ref(), to).await else {
+                return;
+            };
+            let suggestions = pathes::suggest_next_step(dao.clone(), &partial, &to, *remaining, weights).await
+                .expect("Suggest the next step failed.");
+            if suggestions.is_empty() {
+                eprintln!("{} can't be reached within {} steps", to.get_name(), remaining);
+            }
+            for (ele, weight) in suggestions.into_iter().take(*limit) {
+                println!("{}: weight {}", ele.get_name(), weight);
+            }
+        },
+        Commands::Pivots { from_file, steps, limit } => {
+            let pairs = match from_file {
+                Some(from_file) => Some(read_pairs_file(from_file).await.expect("read the pairs file failed.")),
+                None => None,
+            };
+            let pivots = pathes::pivot_aspects(dao.clone(), pairs.as_deref(), *steps, weights).await
+                .expect("Calc pivot aspects failed.");
+            for (ele, n) in pivots.into_iter().take(*limit) {
+                println!("{}: on {} best paths", ele.get_name(), n);
+            }
+        },
+        Commands::AllPrimalPaths { steps } => {
+            let grouped = pathes::all_primal_paths(dao.clone(), steps, weights).await.expect("Calc primal pathes failed.");
+            for (from, pathes) in grouped {
+                println!("{}:", from.get_name());
+                for path in pathes {
+                    println!("  {:?}", path);
+                }
+            }
+        },
+        Commands::MinSteps { from_file, max_steps } => {
+            let pairs = read_pairs_file(from_file).await.expect("read the pairs file failed.");
+            for line in min_steps_report(dao.clone(), &pairs, *max_steps).await {
+                println!("{}", line);
+            }
+        },
+        Commands::Connect { from, to, max_steps } => {
+            let from = ElementHandle::from(from.clone());
+            let to = ElementHandle::from(to.clone());
+            let Some([from, to]) = stored_elements(dao.as_ref(), [from, to]).await else {
+                return;
+            };
+            match pathes::calc_path_min_steps(dao.clone(), &from, &to, *max_steps).await
+                .expect("Calc pathes failed.") {
+                    Some((steps_n, pathes)) => {
+                        println!("{} intermediates at least, {} paths:", steps_n, pathes.len());
+                        for path in pathes {
+                            println!("{:?}", path);
+                        }
+                    },
+                    None => println!("not connectable within {} steps", max_steps),
+            }
+        },
+        Commands::BestConnect { from, to, max_steps } => {
+            let from = ElementHandle::from(from.clone());
+            let to = ElementHandle::from(to.clone());
+            let Some([from, to]) = stored_elements(dao.as_ref(), [from, to]).await else {
+                return;
+            };
+            match pathes::best_weighted_connection(dao.clone(), &from, &to, *max_steps, weights).await
+                .expect("Calc best connection failed.") {
+                    Some(path) => {
+                        let cost = pathes::calc_cost_path(dao.clone(), &path, weights).await
+                            .expect("Calc path cost failed.");
+                        println!("{:?}, cost {}", path, cost);
+                    },
+                    None => println!("{} and {} can't be connected in {} steps",
+                        from.get_name(), to.get_name(), max_steps),
+            }
+        },
+        Commands::IsOptimal { aspects } => {
+            let eles = aspects.iter()
+                .map(|a| ElementHandle::from(a.clone()))
+                .collect::<Vec<_>>();
+            let Some(eles) = stored_elements(dao.as_ref(), eles).await else {
+                return;
+            };
+            let mut path = pathes::Path::new(
+                eles.first().unwrap().clone(),
+                eles.last().unwrap().clone());
+            for ele in &eles[1..eles.len() - 1] {
+                path.push(ele.clone());
+            }
+
+            if !pathes::is_path_viable(dao.as_ref(), &path).await.expect("`is path viable` failed") {
+                eprintln!("{:?} isn't a viable path", path);
+                return;
+            }
+            let better = pathes::find_better_path(dao.clone(), &mut path, weights).await
+                .expect("Find better path failed.");
+            match better {
+                None => println!("{:?} is optimal", path),
+                Some(best) => {
+                    println!("{:?} isn't optimal", path);
+                    println!("the best is {:?}", best);
+                }
+            }
+        },
+        Commands::PlanBoard { aspects, max_steps } => {
+            let eles = aspects.iter()
+                .map(|a| ElementHandle::from(a.clone()))
+                .collect::<Vec<_>>();
+            let Some(eles) = stored_elements(dao.as_ref(), eles).await else {
+                return;
+            };
+            let plan = pathes::plan_board(dao.clone(), &eles, *max_steps, weights).await
+                .expect("Plan the board failed.");
+            println!("connectors: {}", plan.connectors.iter()
+                .map(|a| a.get_name())
+                .collect::<Vec<_>>()
+                .join(", "));
+            for p in &plan.connections {
+                println!("{:?}", p);
+            }
+            for ele in &plan.unreached {
+                eprintln!("{} can't be linked within {} steps", ele.get_name(), max_steps);
+            }
+        },
+        Commands::LinkAll { aspects, max_steps } => {
+            let eles = aspects.iter()
+                .map(|a| ElementHandle::from(a.clone()))
+                .collect::<Vec<_>>();
+            let Some(eles) = stored_elements(dao.as_ref(), eles).await else {
+                return;
+            };
+            let tree = pathes::minimal_connector_tree(dao.clone(), &eles, *max_steps, weights).await
+                .expect("Calc connector tree failed.");
+            println!("connectors: {}", tree.connectors.iter()
+                .map(|a| a.get_name())
+                .collect::<Vec<_>>()
+                .join(", "));
+            for (a, b) in &tree.edges {
+                println!("{} - {}", a.get_name(), b.get_name());
+            }
+            for ele in &tree.unreached {
+                eprintln!("{} can't be linked within {} steps", ele.get_name(), max_steps);
+            }
+        },
+        Commands::CompareWeights { from, to, steps_n, alpha_a, alpha_b } => {
+            let from = ElementHandle::from(from.clone());
+            let to = ElementHandle::from(to.clone());
+            let Some([from, to]) = stored_elements(dao.as_ref(), [from, to]).await else {
+                return;
+            };
+            let mut rankings = vec![];
+            for alpha in [alpha_a, alpha_b] {
+                let config = match pathes::WeightConfig::with_alpha(*alpha) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        eprintln!("Invalid alpha {}: {}", alpha, e);
+                        return;
+                    }
+                };
+                rankings.push(pathes::calc_path_order_by_weight_filtered(dao.clone(), &from, &to, *steps_n,
+                    &pathes::SearchFilter::default(), &config).await
+                    .expect("Calc pathes failed."));
+            }
+            let (a, b) = (&rankings[0], &rankings[1]);
+            let a_strs = a.iter().map(|p| format!("{:?}", p)).collect::<Vec<_>>();
+            let width = a_strs.iter().map(|p| p.len()).max().unwrap_or(0);
+            println!("{:>4}  {:<width$}  alpha {}", "rank", format!("alpha {}", alpha_a), alpha_b);
+            for (i, b_path) in b.iter().enumerate() {
+                println!("{:>4}  {:<width$}  {:?}", i + 1, a_strs[i], b_path);
+            }
+            for change in pathes::rank_changes(a, b) {
+                match change.delta() {
+                    0 => {},
+                    d => println!("{:+} {:?}: {} -> {}", d, change.path, change.before, change.after),
+                }
+            }
+        },
+        Commands::SuggestAlpha { from, to, steps_n } => {
+            let from = ElementHandle::from(from.clone());
+            let to = ElementHandle::from(to.clone());
+            let Some([from, to]) = stored_elements(dao.as_ref(), [from, to]).await else {
+                return;
+            };
+            match pathes::suggest_alpha(dao.clone(), &from, &to, *steps_n).await.expect("Suggest alpha failed.") {
+                Some((alpha, gap)) => println!("alpha {:.2}: the best path leads by {}", alpha, gap),
+                None => eprintln!("There aren't two paths to tell apart."),
+            }
+        },
+        Commands::TopAspects { min_weight } => {
+            let top = pathes::aspects_above_weight(dao.clone(), *min_weight, weights).await
+                .expect("Calc aspect weights failed.");
+            for (ele, weight) in top {
+                println!("{}: {}", ele.get_name(), weight);
+            }
+        },
+        Commands::ExportGraph { output } => {
+            let dot = recipes_dot(dao.as_ref()).await.expect("Build recipe graph failed.");
+            match output {
+                Some(output) => tokio::fs::write(output, dot).await.expect("Write recipe graph failed."),
+                None => print!("{}", dot),
+            }
+        },
+        Commands::WeightTable { out } => {
+            let csv = weight_table_csv(dao.clone(), weights).await
+                .expect("Build weight table failed.");
+            match out {
+                Some(out) => tokio::fs::write(out, csv).await.expect("Write weight table failed."),
+                None => print!("{}", csv),
+            }
+        },
+        Commands::ListElements { percentile } => {
             let v = dao.list_elements().await
                 .expect("list elements error");
+            let percentiles = if *percentile {
+                pathes::base_value_percentiles(dao.clone()).await.expect("Calc percentiles failed.")
+            } else {
+                Default::default()
+            };
             for e in v {
-                println!("{}", e.pretty_print());
+                match percentiles.get(&ElementHandle::from(e.name.clone())) {
+                    Some(p) => println!("{}, percentile: {:.0}", e.pretty_print(), p),
+                    None => println!("{}", e.pretty_print()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::holding_bar_len;
+    use crate::recipes::ElementHandle;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_aspects_required() {
+        use clap::Parser;
+        for command in ["is-optimal", "plan-board", "link-all"] {
+            assert!(super::Cli::try_parse_from(["t4ach", command]).is_err(), "{command}");
+            assert!(super::Cli::try_parse_from(["t4ach", command, "Aer"]).is_err(), "{command}");
+            assert!(super::Cli::try_parse_from(["t4ach", command, "Aer", "Lux"]).is_ok(), "{command}");
+        }
+    }
+
+    use super::annotate_holdings;
+    #[tokio::test]
+    async fn test_annotate_holdings() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 48 WHERE name = 'Lux';
+            UPDATE elements_holding SET num = 3 WHERE name = 'Vacuos';
+            DELETE FROM elements_holding WHERE name = 'Aer';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let mut path = crate::pathes::Path::new(ElementHandle::from("Aer"), ElementHandle::from("Tenebrae"));
+        path.push(ElementHandle::from("Lux"));
+        let holdings = dao.list_elements_holding().await.expect("1").into_iter().collect();
+        let annotated = annotate_holdings(&path, &holdings);
+        assert_eq!(annotated, "Aer(x0)->Lux(x48)->Tenebrae(xinf)");
+        for ele in [path.start(), &path.steps()[0], path.end()] {
+            let held = dao.get_element_num_holding(ele).await.expect("2");
+            assert!(annotated.contains(&format!("{}(x{})", ele.get_name(), held)));
+        }
+    }
+
+    use super::random_holdings;
+    #[tokio::test]
+    async fn test_random_holdings_reproducible() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        let elements = dao.list_elements().await.expect("1")
+            .into_iter()
+            .map(|e| ElementHandle::from(e.name))
+            .collect::<Vec<_>>();
+        let mut reversed = elements.clone();
+        reversed.reverse();
+        let holdings = random_holdings(elements.clone(), 100, 42);
+        assert_eq!(holdings, random_holdings(reversed, 100, 42));
+        assert_ne!(holdings, random_holdings(elements.clone(), 100, 43));
+        assert!(holdings.iter().all(|(_, n)| *n <= 100));
+
+        assert_eq!(dao.set_holdings_bulk(&holdings).await.expect("2"), elements.len() as u64);
+        for (ele, n) in &holdings {
+            assert_eq!(dao.get_element_num_holding(ele).await.expect("3"), *n as f64);
+        }
+    }
+
+    #[test]
+    fn test_holding_bar_len() {
+        assert_eq!(holding_bar_len(300., 300., 40), 40);
+        assert_eq!(holding_bar_len(150., 300., 40), 20);
+        assert_eq!(holding_bar_len(0., 300., 40), 0);
+        assert_eq!(holding_bar_len(0., 0., 40), 0);
+        assert_eq!(holding_bar_len(f64::INFINITY, f64::INFINITY, 40), 40);
+        assert_eq!(holding_bar_len(300., f64::INFINITY, 40), 0);
+    }
+
+    /// split a CSV line, handling the quoted fields.
+    fn parse_csv_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                },
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(String::new()),
+                _ => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    #[tokio::test]
+    async fn test_pathes_to_csv() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let pathes = crate::pathes::calc_path_order_by_weight(dao.clone(),
+            &ElementHandle::from("Bestia"), &ElementHandle::from("Spiritus"), 2, &crate::pathes::WeightConfig::default()).await.expect("1");
+        let csv = super::pathes_to_csv(&pathes);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("rank,weight,length,steps"));
+        let rows = lines.map(parse_csv_line).collect::<Vec<_>>();
+        assert_eq!(rows.len(), pathes.len());
+        for (i, (row, p)) in rows.iter().zip(&pathes).enumerate() {
+            assert_eq!(row.len(), 4, "{row:?}");
+            assert_eq!(row[0], (i + 1).to_string());
+            assert_eq!(row[1].parse::<f64>().unwrap(), p.weight().unwrap());
+            assert_eq!(row[2], "2");
+        }
+        assert_eq!(rows[0][3], "Bestia, Victus, Mortuus, Spiritus");
+        assert_eq!(super::csv_quote("a \"b\", c"), "\"a \"\"b\"\", c\"");
+    }
+
+    #[tokio::test]
+    async fn test_weight_table_csv() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO elements_holding(name, num) VALUES('Aer', 1e999);
+            INSERT INTO elements_holding(name, num) VALUES('Ignis', 0);
+            INSERT INTO elements_holding(name, num) VALUES('Lux', 1e999);
+            ").await);
+        let csv = super::weight_table_csv(dao.clone(), &crate::pathes::WeightConfig::default()).await.expect("1");
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("# alpha 0.7"));
+        assert_eq!(lines.next(), Some("name,base_value,holding,weight"));
+        let rows = lines.map(parse_csv_line).collect::<Vec<_>>();
+        assert_eq!(rows.iter().map(|r| r[0].as_str()).collect::<Vec<_>>(), ["Aer", "Ignis", "Lux"]);
+        // Lux: 0.7 + 0.3 / (1 + 1 + 0)
+        let lux = &rows[2];
+        assert_eq!(lux[1].parse::<f64>().unwrap(), 1.0);
+        assert_eq!(lux[2].parse::<f64>().unwrap(), f64::INFINITY);
+        assert!((lux[3].parse::<f64>().unwrap() - 0.85).abs() < 1e-9, "{lux:?}");
+        assert_eq!(rows[1][2], "0");
+    }
+
+    #[tokio::test]
+    async fn test_recipes_dot() {
+        let dao = crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            ").await;
+        let dot = super::recipes_dot(&dao).await.expect("1");
+        assert_eq!(dot, "digraph aspects {
+    \"Aer\" [shape=doublecircle];
+    \"Ignis\" [shape=doublecircle];
+    \"Lux\" [shape=ellipse];
+    \"Aer\" -> \"Lux\";
+    \"Ignis\" -> \"Lux\";
+}
+");
+    }
+
+    #[tokio::test]
+    async fn test_pathes_to_edges() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let pathes = crate::pathes::calc_path_order_by_weight(dao.clone(),
+            &ElementHandle::from("Bestia"), &ElementHandle::from("Spiritus"), 2, &crate::pathes::WeightConfig::default()).await.expect("1");
+        assert!(pathes.len() > 1);
+        let edges = super::pathes_to_edges(&pathes);
+        let lines = edges.lines().collect::<Vec<_>>();
+        let distinct = lines.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(distinct.len(), lines.len(), "{edges}");
+        for p in &pathes {
+            let chain = std::iter::once(p.start())
+                .chain(p.steps())
+                .chain(std::iter::once(p.end()))
+                .collect::<Vec<_>>();
+            for pair in chain.windows(2) {
+                let edge = format!("{} -> {}", pair[0].get_name(), pair[1].get_name());
+                assert!(lines.contains(&edge.as_str()), "{edge} missing in {edges}");
+            }
+        }
+    }
+
+    use super::passes_preflight;
+    #[tokio::test]
+    async fn test_preflight_rejects_broken_database() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        assert!(passes_preflight(&dao).await);
+
+        // a recipe made of itself, and a holding of an aspect that doesn't exist.
+        let dao = crate::dao::tests::memory_dao(&format!("{}
+            PRAGMA foreign_keys = OFF;
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Ignis', 'Ignis', 'Aer');
+            INSERT INTO elements_holding(name, num) VALUES('Nonexistentia', 3);
+            PRAGMA foreign_keys = ON;
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await;
+        assert!(!passes_preflight(&dao).await);
+        let problems = crate::pathes::preflight_problems(&dao).await;
+        assert!(problems.iter().any(|a| a.starts_with("elements_holding:")), "{problems:?}");
+        assert!(problems.iter().any(|a| a.starts_with("Ignis is made of itself")), "{problems:?}");
+        assert!(problems.iter().any(|a| a.starts_with("Cyclic recipe")), "{problems:?}");
+    }
+
+    use super::compare_inventory;
+    #[test]
+    fn test_compare_inventory() {
+        let h = ElementHandle::from;
+        let holdings = std::collections::HashMap::from([
+            (h("Aer"), 70.), (h("Ignis"), 10.), (h("Terra"), f64::INFINITY)]);
+        let target = [(h("Aer"), 64), (h("Ignis"), 32), (h("Ignis"), 32), (h("Aqua"), 20), (h("Terra"), 5)];
+        let compared = compare_inventory(&holdings, &target);
+        assert_eq!(compared, vec![
+            (h("Ignis"), 10., 64, -54.),
+            (h("Aqua"), 0., 20, -20.),
+            (h("Aer"), 70., 64, 6.),
+            (h("Terra"), f64::INFINITY, 5, f64::INFINITY),
+        ]);
+    }
+
+    use super::parse_aspect_quantities;
+    #[test]
+    fn test_parse_aspect_quantities() {
+        let args = |a: &str| a.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(parse_aspect_quantities(&args("Aer 2 Ignis Terra 0")), Some(vec![
+            (ElementHandle::from("Aer"), 2),
+            (ElementHandle::from("Ignis"), 1),
+            (ElementHandle::from("Terra"), 0),
+        ]));
+        assert_eq!(parse_aspect_quantities(&args("2 Aer")), None);
+        assert_eq!(parse_aspect_quantities(&args("Aer 2 3")), None);
+        assert_eq!(parse_aspect_quantities(&[]), None);
+    }
+
+    use super::{read_pairs_file, min_steps_report};
+    #[tokio::test]
+    async fn test_min_steps_from_file() {
+        let file = crate::dao::tests::temp_path("pairs.txt");
+        std::fs::write(&file, "# pairs\nAer Lux\n\nAer Tenebrae # one between\nAer Victus\nAer Nope\n").unwrap();
+        let pairs = read_pairs_file(std::path::Path::new(&file)).await.expect("1");
+        std::fs::write(&file, "Aer Lux\nAer\n").unwrap();
+        assert!(matches!(read_pairs_file(std::path::Path::new(&file)).await,
+            Err(crate::errors::T4ACHError::ParsingPairs { line_number: 2, .. })));
+        let _ = std::fs::remove_file(&file);
+
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        assert_eq!(min_steps_report(dao.clone(), &pairs, 1).await, vec![
+            "Aer->Lux: 0",
+            "Aer->Tenebrae: 1",
+            "Aer->Victus: unconnectable within 1",
+            "Aer->Nope: the element Nope doesn't exists",
+        ]);
+        assert_eq!(min_steps_report(dao.clone(), &pairs[2..3], 6).await, vec!["Aer->Victus: 2"]);
+    }
+
+    use super::stored_element;
+    #[tokio::test]
+    async fn test_stored_element() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        // `craft-vs-farm aer` works on the stored Aer.
+        assert_eq!(stored_element(&dao, " aer ").await, Some(ElementHandle::from("Aer")));
+        assert_eq!(stored_element(&dao, "Nope").await, None);
+    }
+
+    use super::{read_holdings_file, holdings_text};
+    #[tokio::test]
+    async fn test_holdings_file() {
+        let file = crate::dao::tests::temp_path("holdings.txt");
+        std::fs::write(&file, "# my inventory\nAer 64\n\nIgnis 2.5 # half\nLux inf\n").unwrap();
+        let lines = read_holdings_file(std::path::Path::new(&file)).await.expect("1");
+        assert_eq!(lines, vec![
+            (2, ElementHandle::from("Aer"), 64.),
+            (4, ElementHandle::from("Ignis"), 2.5),
+            (5, ElementHandle::from("Lux"), f64::INFINITY),
+        ]);
+        for bad in ["Aer 64\nIgnis\n", "Aer 64\nIgnis -1\n", "Aer 64\nIgnis 2 3\n"] {
+            std::fs::write(&file, bad).unwrap();
+            assert!(matches!(read_holdings_file(std::path::Path::new(&file)).await,
+                Err(crate::errors::T4ACHError::ParsingHoldings { line_number: 2, .. })), "{bad}");
+        }
+
+        // exported then read back
+        let holdings = lines.into_iter().map(|(_, e, n)| (e, n)).rev().collect::<Vec<_>>();
+        let text = holdings_text(&holdings);
+        assert_eq!(text, "Aer 64\nIgnis 2.5\nLux inf\n");
+        std::fs::write(&file, &text).unwrap();
+        let read = read_holdings_file(std::path::Path::new(&file)).await.expect("2")
+            .into_iter().map(|(_, e, n)| (e, n)).collect::<Vec<_>>();
+        assert_eq!(read, holdings.into_iter().rev().collect::<Vec<_>>());
+        let _ = std::fs::remove_file(&file);
+    }
+
+    use super::primal_counts_json;
+    #[tokio::test]
+    async fn test_primal_counts_json() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        // a node of Lux * 2 and Tenebrae, Tenebrae = Vacuos + Lux, Vacuos = Aer + Perditio
+        let mut counts = std::collections::HashMap::new();
+        for (ele, num) in [("Lux", 2), ("Tenebrae", 1)] {
+            for (primal, n) in crate::pathes::crack_element_until_primary(dao.clone(), &ElementHandle::from(ele))
+                .await.expect("1") {
+                    *counts.entry(primal).or_insert(0) += n * num;
             }
         }
+        assert_eq!(primal_counts_json(&counts),
+            r#"{"Aer":4,"Aqua":0,"Ignis":3,"Ordo":0,"Perditio":1,"Terra":0}"#);
     }
 }
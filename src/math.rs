@@ -4,6 +4,7 @@ pub trait Evaluable {
     fn eval(&self, x: f64) -> Result<f64>;
 }
 
+#[derive(Clone, Debug)]
 pub struct NumberMapToValue {
     alpha: f64,
     beta: f64,
@@ -47,7 +48,7 @@ impl Default for NumberMapToValue {
 }
 
 impl NumberMapToValue {
-    fn new(alpha: f64) -> Result<Self> {
+    pub fn new(alpha: f64) -> Result<Self> {
         if alpha <= 0. || alpha >= 1.0 {
             return Err(
                 MathError::Domain {
@@ -68,6 +69,15 @@ impl NumberMapToValue {
     }
 }
 
+impl NumberMapToValue {
+    /// the value `eval` approaches as the number grows.
+    pub const MAX_VALUE: f64 = 1.0;
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+}
+
 impl Evaluable for NumberMapToValue {
     fn eval(&self, x: f64) -> Result<f64> {
         if 0. > x {
@@ -76,7 +86,7 @@ impl Evaluable for NumberMapToValue {
                 inputted: x,
             })
         }
-        return Ok(if 0. <= x && x < 1000. {
+        Ok(if (0.0..1000.0).contains(&x) {
             self.alpha * x / 1000.
         } else {
             let pa = -self.beta * ( x - 1000.);
@@ -85,11 +95,39 @@ impl Evaluable for NumberMapToValue {
     }
 }
 
+/// the fewest single character insertions, deletions and substitutions turning `a` into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    // the distances from the prefix of `a` so far to each prefix of `b`.
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::NumberMapToValue;
     use super::Evaluable;
 
+    use super::levenshtein;
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("Ignis", "Ignis"), 0);
+        assert_eq!(levenshtein("Ingis", "Ignis"), 2);
+        assert_eq!(levenshtein("Aer", "Aqua"), 3);
+        assert_eq!(levenshtein("", "Lux"), 3);
+        assert_eq!(levenshtein("Lux", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
     #[test]
     fn test_map_to_value_function() {
         {
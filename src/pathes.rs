@@ -3,13 +3,17 @@ use crate::dao::DAO;
 use crate::math::{NumberMapToValue, Evaluable};
 use crate::errors::*;
 
-use std::cmp::Ordering;
 use std::collections::{HashSet, HashMap};
-use std::sync::{Arc, LazyLock};
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicUsize};
 use std::hash::Hash;
 
+use tokio::sync::Mutex;
+
 use snafu::prelude::*;
 use ego_tree::Tree;
+use serde::Serialize;
 
 #[derive(Clone)]
 pub struct Path {
@@ -55,31 +59,227 @@ impl Hash for Path {
 }
 
 
+/// A `Path` flattened for serializing.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PathDto {
+    pub start: String,
+    pub end: String,
+    pub intermediate: Vec<String>,
+    pub weight: Option<f64>,
+}
+
+impl From<&Path> for PathDto {
+    fn from(value: &Path) -> Self {
+        Self {
+            start: value.start.get_name(),
+            end: value.end.get_name(),
+            intermediate: value.path.iter().map(|a| a.get_name()).collect(),
+            weight: value.cached_weight,
+        }
+    }
+}
+
+/// One aspect of an `EnrichedPath`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct EnrichedStep {
+    pub name: String,
+    pub base_value: f64,
+    /// `None` for an unlimited holding, which json can't represent.
+    pub holding: Option<f64>,
+    /// the aspect's own `calc_weight`.
+    pub weight: f64,
+}
+
+/// A path with what rendering it needs, see `enrich_path`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct EnrichedPath {
+    /// every aspect from the start to the end.
+    pub steps: Vec<EnrichedStep>,
+    /// the path's `calc_weight_path`.
+    pub weight: f64,
+}
+
+/// Look up the base value, holding and weight of each aspect of `path`.
+pub async fn enrich_path(dao: Arc<DAO>, path: &Path, config: &WeightConfig) -> Result<EnrichedPath> {
+    let mut steps = vec![];
+    for ele in std::iter::once(&path.start).chain(&path.path).chain(std::iter::once(&path.end)) {
+        let holding = dao.get_element_num_holding(ele).await.context(DatabaseSnafu)?;
+        steps.push(EnrichedStep {
+            name: ele.get_name(),
+            base_value: dao.get_element_base_value(ele).await.context(DatabaseSnafu)?,
+            holding: holding.is_finite().then_some(holding),
+            weight: calc_weight(dao.clone(), ele, config).await?,
+        });
+    }
+    Ok(EnrichedPath {
+        steps,
+        weight: calc_weight_path(dao.clone(), path, config).await?,
+    })
+}
+
+/// Restrictions on the intermediate aspects a path search may step on.
+/// The endpoints of a search are never restricted.
+#[derive(Clone, Default)]
+pub struct SearchFilter {
+    excluded: HashSet<ElementHandle>,
+    through: Vec<ElementHandle>,
+    trace: Option<SearchTrace>,
+}
+
+/// One decision of a path search, see `SearchFilter::record_into`.
+/// `depth` is the number of links from the start.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SearchEvent {
+    /// `node`'s allowed relatives are stepped on next.
+    Expand { depth: usize, node: String, children: Vec<String> },
+    /// `node` isn't stepped on, or nothing goes on from it.
+    Prune { depth: usize, node: String, reason: &'static str },
+    /// every aspect from the start to the end.
+    Found { path: Vec<String> },
+}
+
+/// The events of the searches sharing it, in order.
+#[derive(Clone, Default)]
+pub struct SearchTrace(Arc<std::sync::Mutex<Vec<SearchEvent>>>);
+
+impl SearchTrace {
+    pub fn events(&self) -> Vec<SearchEvent> {
+        self.0.lock().expect("search trace poisoned").clone()
+    }
+}
+
+impl SearchFilter {
+    pub fn exclude(&mut self, eles: impl IntoIterator<Item = ElementHandle>) {
+        self.excluded.extend(eles);
+    }
+
+    /// only the paths stepping on all `eles` in order are wanted.
+    pub fn pass_through(&mut self, eles: impl IntoIterator<Item = ElementHandle>) {
+        self.through.extend(eles);
+    }
+
+    pub fn allows(&self, ele: &ElementHandle) -> bool {
+        !self.excluded.contains(ele)
+    }
+
+    /// record how the searches using this filter expand into `trace`.
+    pub fn record_into(&mut self, trace: SearchTrace) {
+        self.trace = Some(trace);
+    }
+
+    fn record(&self, event: impl FnOnce() -> SearchEvent) {
+        if let Some(trace) = &self.trace {
+            trace.0.lock().expect("search trace poisoned").push(event());
+        }
+    }
+
+    /// the allowed ones of `relatives` sorted, recording `node`'s expansion and the excluded ones.
+    fn expand(&self, depth: usize, node: &ElementHandle, relatives: &HashSet<ElementHandle>) -> Vec<ElementHandle> {
+        let mut children = relatives.iter().cloned().collect::<Vec<_>>();
+        children.sort();
+        let (children, excluded): (Vec<_>, Vec<_>) = children.into_iter().partition(|a| self.allows(a));
+        self.record(|| SearchEvent::Expand {
+            depth,
+            node: node.get_name(),
+            children: children.iter().map(|a| a.get_name()).collect(),
+        });
+        for x in excluded {
+            self.record(|| SearchEvent::Prune { depth: depth + 1, node: x.get_name(), reason: "excluded" });
+        }
+        children
+    }
+
+    fn record_found(&self, path: &Path) {
+        self.record(|| SearchEvent::Found {
+            path: std::iter::once(&path.start)
+                .chain(&path.path)
+                .chain(std::iter::once(&path.end))
+                .map(|a| a.get_name())
+                .collect(),
+        });
+    }
+}
+
+/// The elements holding none, excluding them leaves the paths ready to be placed right away.
+pub async fn unheld_elements(dao: &DAO) -> Result<Vec<ElementHandle>> {
+    let mut ret = vec![];
+    for e in dao.list_elements().await.context(DatabaseSnafu)? {
+        let ele = ElementHandle::from(e.name);
+        if dao.get_element_num_holding(&ele).await.context(DatabaseSnafu)? <= 0. {
+            ret.push(ele);
+        }
+    }
+    Ok(ret)
+}
+
+/// The elements belonging to `mod_name`, `UNKNOWN_MOD` for those without one.
+/// Excluding them keeps the paths off that mod.
+pub async fn elements_of_mod(dao: &DAO, mod_name: &str) -> Result<Vec<ElementHandle>> {
+    Ok(dao.list_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .filter(|e| e.belongs_to_mod.as_deref().unwrap_or(UNKNOWN_MOD) == mod_name)
+        .map(|e| ElementHandle::from(e.name))
+        .collect())
+}
+
+/// Every element's relatives at once, see `DAO::load_full_adjacency`.
+/// The whole graph analyses take it, so that it's built once for them.
+pub type Adjacency = HashMap<ElementHandle, HashSet<ElementHandle>>;
+
+/// Memoized `get_relatives` results, so each element's relatives are queried at most once.
+#[derive(Default)]
+pub struct RelativesCache {
+    relatives: Mutex<HashMap<ElementHandle, HashSet<ElementHandle>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl RelativesCache {
+    pub fn hits(&self) -> usize {
+        self.hits.load(atomic::Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(atomic::Ordering::Relaxed)
+    }
+
+    /// the number of elements cached.
+    pub async fn size(&self) -> usize {
+        self.relatives.lock().await.len()
+    }
+
+    /// forget every cached element, after the recipes changed.
+    pub async fn clear(&self) {
+        self.relatives.lock().await.clear();
+    }
+}
+
 /// get the elements it can build and the components built it.
 pub async fn get_relatives(dao: &DAO, ele: &ElementHandle) -> Result<HashSet<ElementHandle>> {
-    use crate::dao::Errors;
+    get_relatives_cached(dao, dao.relatives_cache(), ele).await
+}
+
+/// Same as `get_relatives`, memoized in `cache` rather than the DAO's own,
+/// to keep one search's lookups apart.
+pub async fn get_relatives_cached(dao: &DAO, cache: &RelativesCache, ele: &ElementHandle)
+    -> Result<HashSet<ElementHandle>> {
+    if let Some(v) = cache.relatives.lock().await.get(ele) {
+        cache.hits.fetch_add(1, atomic::Ordering::Relaxed);
+        return Ok(v.clone());
+    }
+    cache.misses.fetch_add(1, atomic::Ordering::Relaxed);
+    let relative_eles = query_relatives(dao, ele).await?;
+    cache.relatives.lock().await.insert(ele.clone(), relative_eles.clone());
+    Ok(relative_eles)
+}
+
+async fn query_relatives(dao: &DAO, ele: &ElementHandle) -> Result<HashSet<ElementHandle>> {
     let mut relative_eles = HashSet::new();
-    match dao.get_element_components(ele).await {
-        Ok((component_a, component_b)) => {
-            relative_eles.insert(component_a);
-            relative_eles.insert(component_b);
-        },
-        Err(e) => {
-            match e {
-                Errors::FetchedZeroRow(_s) => {
-                    // this situation means primary key
-                    // do nothing
-                }
-                _ => {
-                    return Err(
-                        crate::errors::T4ACHError::Database {
-                            err_loc: snafu::location!(),
-                            backtrace: snafu::Backtrace::capture(),
-                            source: e,
-                        })
-                }
-            }
-        }
+    // a primary element has none, one with several recipes is made of each of their components.
+    for (component_a, component_b) in dao.get_all_element_components(ele).await.context(DatabaseSnafu)? {
+        relative_eles.insert(component_a);
+        relative_eles.insert(component_b);
     }
     let v = dao
         .get_what_component_can_build(ele)
@@ -90,10 +290,117 @@ pub async fn get_relatives(dao: &DAO, ele: &ElementHandle) -> Result<HashSet<Ele
     Ok(relative_eles)
 }
 
+/// All the recipes in memory, so a search looks the relatives up without querying the database.
+pub struct RecipeGraph {
+    /// each element's components united with the elements it builds, as `get_relatives` gives them.
+    relatives: Adjacency,
+    empty: HashSet<ElementHandle>,
+}
+
+impl RecipeGraph {
+    /// Read the whole recipe table at once.
+    pub async fn load(dao: &DAO) -> Result<Self> {
+        let recipes = dao.list_recipes().await.context(DatabaseSnafu)?;
+        Ok(Self::from_recipes(recipes))
+    }
+
+    fn from_recipes(recipes: Vec<(ElementHandle, ElementHandle, ElementHandle)>) -> Self {
+        let mut components = Adjacency::new();
+        let mut builds = Adjacency::new();
+        for (name, component_a, component_b) in recipes {
+            for component in [component_a, component_b] {
+                components.entry(name.clone()).or_default().insert(component.clone());
+                builds.entry(component).or_default().insert(name.clone());
+            }
+        }
+        let mut relatives = components;
+        for (component, built) in builds {
+            relatives.entry(component).or_default().extend(built);
+        }
+        Self { relatives, empty: HashSet::new() }
+    }
+
+    /// the elements `ele` builds and the components built it, none for an unknown element.
+    pub fn relatives(&self, ele: &ElementHandle) -> &HashSet<ElementHandle> {
+        self.relatives.get(ele).unwrap_or(&self.empty)
+    }
+
+    /// Every aspect reachable from `ele` by stepping on relatives, `ele` included.
+    pub fn connected_component(&self, ele: &ElementHandle) -> HashSet<ElementHandle> {
+        let mut component = HashSet::from([ele.clone()]);
+        let mut frontier = vec![ele];
+        while let Some(x) = frontier.pop() {
+            for r in self.relatives(x) {
+                if component.insert(r.clone()) {
+                    frontier.push(r);
+                }
+            }
+        }
+        component
+    }
+
+    pub fn are_in_same_component(&self, a: &ElementHandle, b: &ElementHandle) -> bool {
+        self.connected_component(b).contains(a)
+    }
+}
+
+/// What's broken in the data, one line per problem, none if it's safe to search paths in:
+/// the rows referencing missing elements, the recipes made of themselves, the cyclic recipes
+/// and the asymmetric relatives. A check which can't run at all is a problem too.
+pub async fn preflight_problems(dao: &DAO) -> Vec<String> {
+    let mut problems = vec![];
+    match dao.check_foreign_keys().await {
+        Ok(violations) => problems.extend(violations.into_iter()
+            .map(|v| format!("{}: {} references a missing row of {}", v.table, v.row, v.parent))),
+        Err(e) => problems.push(format!("check foreign keys failed: {e}")),
+    }
+    match dao.list_recipes().await {
+        Ok(recipes) => problems.extend(recipes.into_iter()
+            .filter(|(name, ca, cb)| name == ca || name == cb)
+            .map(|(name, ca, cb)| format!("{} is made of itself: {} + {}", name.get_name(), ca.get_name(), cb.get_name()))),
+        Err(e) => problems.push(format!("list recipes failed: {e}")),
+    }
+    if let Err(e) = dao.recipes_topologically_sorted().await {
+        problems.push(e.to_string());
+    }
+    match verify_symmetry(dao).await {
+        Ok(asymmetries) => problems.extend(asymmetries.into_iter()
+            .map(|(a, b)| format!("{} relates to {}, but not the other way around", a.get_name(), b.get_name()))),
+        Err(e) => problems.push(format!("verify symmetry failed: {e}")),
+    }
+    problems
+}
+
+/// The edges `(a, b)` where `b` is one of `a`'s relatives but not the other way around,
+/// sorted. Relatives are built from both sides of the same recipes, so an asymmetry means
+/// a stale relatives cache or a broken recipe.
+/// Every element is checked, and every relative met, even one missing from `elements`.
+pub async fn verify_symmetry(dao: &DAO) -> Result<Vec<(ElementHandle, ElementHandle)>> {
+    let mut pending = dao.list_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|e| ElementHandle::from(e.name))
+        .collect::<Vec<_>>();
+    let mut checked = HashSet::new();
+    let mut ret = vec![];
+    while let Some(a) = pending.pop() {
+        if !checked.insert(a.clone()) {
+            continue;
+        }
+        for b in get_relatives(dao, &a).await? {
+            if !get_relatives(dao, &b).await?.contains(&a) {
+                ret.push((a.clone(), b.clone()));
+            }
+            pending.push(b);
+        }
+    }
+    ret.sort();
+    Ok(ret)
+}
+
 pub async fn is_two_eles_connected(dao: &DAO, a: &ElementHandle, b: &ElementHandle)
     -> Result<bool> {
         let relative_eles = get_relatives(dao, a).await?;
-        return Ok(relative_eles.contains(b));
+        Ok(relative_eles.contains(b))
 }
 
 impl Path {
@@ -111,9 +418,57 @@ impl Path {
     pub fn push(&mut self, ele: ElementHandle) {
         self.path.push(ele);
     }
+    #[allow(dead_code)]
     pub fn pop(&mut self, ) -> Option<ElementHandle> {
         self.path.pop()
     }
+
+    pub fn start(&self) -> &ElementHandle {
+        &self.start
+    }
+
+    pub fn end(&self) -> &ElementHandle {
+        &self.end
+    }
+
+    /// the intermediates between the start and the end.
+    pub fn steps(&self) -> &[ElementHandle] {
+        &self.path
+    }
+
+    /// the same path from the end to the start, not weighted.
+    pub fn reversed(&self) -> Path {
+        let mut ret = Path::new(self.end.clone(), self.start.clone());
+        self.path.iter().rev().for_each(|a| ret.push(a.clone()));
+        ret
+    }
+
+    /// the weight cached by `calc_path_order_by_weight`.
+    pub fn weight(&self) -> Option<f64> {
+        self.cached_weight
+    }
+}
+
+/// Each path's cached weight as a score out of 100, relative to the best weighted of `pathes`
+/// scoring 100. `None` for the paths not weighted, and for all when the best weighs nothing.
+pub fn score_pathes(pathes: &[Path]) -> Vec<Option<f64>> {
+    let best = pathes.iter()
+        .filter_map(Path::weight)
+        .fold(f64::NEG_INFINITY, f64::max);
+    pathes.iter()
+        .map(|p| p.weight().filter(|_| best > 0.).map(|w| w / best * 100.))
+        .collect()
+}
+
+/// The intermediates stepped on by any of `pathes`, each once, sorted by name.
+pub fn distinct_intermediates(pathes: &[Path]) -> Vec<ElementHandle> {
+    let mut ret = pathes.iter()
+        .flat_map(|p| p.steps().iter().cloned())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    ret.sort();
+    ret
 }
 
 pub async fn is_path_viable(dao: &DAO, path: &Path) -> Result<bool> {
@@ -139,285 +494,2194 @@ pub async fn is_path_viable(dao: &DAO, path: &Path) -> Result<bool> {
 }
 
 
-pub async fn calc_path_steps_1(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle)
-    ->  Result<Vec<Path>> {
-    let a_rel = get_relatives(dao.as_ref(), from).await?;
-    let b_rel = get_relatives(dao.as_ref(), to).await?;
-    let path_inners: Vec<ElementHandle> = a_rel.intersection(&b_rel)
-        .cloned()
-        .collect();
+pub fn calc_path_steps_1(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, filter: &SearchFilter)
+    -> Vec<Path> {
+    let a_rel = graph.relatives(from);
+    let b_rel = graph.relatives(to);
 
     let path = Path::new(from.clone(), to.clone());
     let mut ret = Vec::new();
-    for path_inner in path_inners {
+    for path_inner in filter.expand(0, from, a_rel) {
+        if !b_rel.contains(&path_inner) {
+            filter.record(|| SearchEvent::Prune { depth: 1, node: path_inner.get_name(), reason: "not adjacent to the end" });
+            continue;
+        }
         let mut p = path.clone();
         p.push(path_inner);
+        filter.record_found(&p);
         ret.push(p);
     }
-    Ok(ret)
+    ret
 }
 
-pub async fn calc_path_steps_2(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle)
-    -> Result<Vec<Path>> {
-        let a_rel = get_relatives(dao.as_ref(), from).await?;
-        let b_rel = get_relatives(dao.as_ref(), to).await?;
+pub fn calc_path_steps_2(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, filter: &SearchFilter)
+    -> Vec<Path> {
+        let a_rel = graph.relatives(from);
+        let b_rel = graph.relatives(to);
 
         let mut ret = Vec::new();
 
-        for a in a_rel.iter() {
-            for b in b_rel.iter() {
-                if is_two_eles_connected(dao.as_ref(), a, b).await? {
+        let mut b_rel = b_rel.iter().filter(|b| filter.allows(b)).cloned().collect::<Vec<_>>();
+        b_rel.sort();
+        for a in filter.expand(0, from, a_rel) {
+            let mut found = false;
+            for b in &b_rel {
+                if graph.relatives(&a).contains(b) {
                     let mut p = Path::new(from.clone(), to.clone());
                     p.push(a.clone());
                     p.push(b.clone());
+                    filter.record_found(&p);
                     ret.push(p);
+                    found = true;
                 }
             }
+            if !found {
+                filter.record(|| SearchEvent::Prune { depth: 1, node: a.get_name(), reason: "not adjacent to the end" });
+            }
         }
 
-        Ok(ret)
+        ret
+}
+
+
+/// The extra weight of a step already placed on the research board, it costs nothing to reuse.
+const ON_BOARD_BONUS: f64 = 1.0;
+
+/// The parameters of the path weighting.
+#[derive(Clone)]
+pub struct WeightConfig {
+    map_to_value: NumberMapToValue,
+    /// how much of an element's weight is its own, the rest is the inverse of its decomposition's.
+    blend_rate: f64,
+    on_board: HashSet<ElementHandle>,
+    holdings: HashMap<ElementHandle, f64>,
+    /// the half-life in seconds and the unix seconds of now, see `decay_holdings`.
+    decay: Option<(f64, i64)>,
+    /// how many paths' weights are calculated at a time.
+    concurrency: usize,
+    /// the `holdings_hash` taken once for a whole search, see `with_holdings_hash`.
+    holdings_hash: Option<u64>,
 }
 
+/// The default `WeightConfig::blend_rate`.
+const BLEND_RATE: f64 = 0.7;
 
-static MAP_TO_VALUE: LazyLock<NumberMapToValue> = LazyLock::new(|| NumberMapToValue::default());
-pub async fn calc_weight_single(dao: Arc<DAO>, ele: &ElementHandle) -> Result<f64> {
+impl Default for WeightConfig {
+    fn default() -> Self {
+        Self {
+            map_to_value: NumberMapToValue::default(),
+            blend_rate: BLEND_RATE,
+            on_board: HashSet::new(),
+            holdings: HashMap::new(),
+            decay: None,
+            // the weights mostly wait on sqlite, more tasks than cores only contend for it.
+            concurrency: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            holdings_hash: None,
+        }
+    }
+}
+
+impl WeightConfig {
+    /// the default weighting, but the holdings mapped with `alpha`, see `NumberMapToValue`.
+    pub fn with_alpha(alpha: f64) -> crate::math::Result<Self> {
+        Ok(Self {
+            map_to_value: NumberMapToValue::new(alpha)?,
+            ..Default::default()
+        })
+    }
+
+    /// mark aspects already placed on the research board, paths reusing them are favored.
+    pub fn place_on_board(&mut self, eles: impl IntoIterator<Item = ElementHandle>) {
+        self.on_board.extend(eles);
+    }
+
+    /// the alpha the holdings are mapped with.
+    pub fn alpha(&self) -> f64 {
+        self.map_to_value.alpha()
+    }
+
+    /// calculate `n` paths' weights at a time, at least one.
+    pub fn set_concurrency(&mut self, n: usize) {
+        self.concurrency = n.max(1);
+    }
+
+    /// blend `rate` of an element's own weight with the rest from its decomposition, within [0, 1].
+    /// 1 weighs the element alone.
+    pub fn set_blend_rate(&mut self, rate: f64) -> crate::math::Result<()> {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(crate::math::MathError::Domain {
+                valid_region: "[0, 1]".to_string(),
+                inputted: rate,
+            });
+        }
+        self.blend_rate = rate;
+        Ok(())
+    }
+
+    /// weight `ele` as if holding `num` of it, without changing the database.
+    pub fn override_holding(&mut self, ele: ElementHandle, num: f64) {
+        self.holdings.insert(ele, num);
+    }
+
+    /// count the holdings as halved every `half_life` seconds since they were last changed, at `now`.
+    /// The holdings never changed by the tool and the overridden ones don't decay.
+    pub fn decay_holdings(&mut self, half_life: f64, now: i64) {
+        self.decay = Some((half_life, now));
+    }
+}
+
+/// `ele`'s holding, overridden by `config`'s, decayed by its age if `config` says so.
+async fn holding_of(dao: &DAO, ele: &ElementHandle, config: &WeightConfig) -> Result<f64> {
+    if let Some(num) = config.holdings.get(ele) {
+        return Ok(*num);
+    }
+    let num = dao.get_element_num_holding(ele).await.context(DatabaseSnafu)?;
+    let Some((half_life, now)) = config.decay else {
+        return Ok(num);
+    };
+    Ok(match dao.get_holding_last_updated(ele).await.context(DatabaseSnafu)? {
+        Some(updated) => {
+            let age = (now - updated).max(0) as f64;
+            num * 0.5f64.powf(age / half_life)
+        },
+        None => num,
+    })
+}
+
+pub async fn calc_weight_single(dao: Arc<DAO>, ele: &ElementHandle, config: &WeightConfig) -> Result<f64> {
     let base_value = dao.get_element_base_value(ele).await.context(DatabaseSnafu)?;
-    let element_holding = dao.get_element_num_holding(ele).await.context(DatabaseSnafu)?;
-    let weight1 = MAP_TO_VALUE.eval(element_holding as f64).context(MathSnafu)?;
+    let element_holding = holding_of(dao.as_ref(), ele, config).await?;
+    let weight1 = config.map_to_value.eval(element_holding).context(MathSnafu)?;
     let weight = weight1 / base_value;
     Ok(weight)
 }
 
-pub async fn crack_element_until_primary(dao: Arc<DAO>, ele: &ElementHandle) -> Result<HashMap<ElementHandle, usize>> {
-    let tree = constructing_tree(dao.clone(), ele).await?;
-    let mut ret = HashMap::new();
-    tree.nodes().filter(|a| {
-        !a.has_children() 
-    })
-    .for_each(|a| {
-        if let Some(c) = ret.get_mut(a.value()) {
-            *c += 1;
-        } else {
-            ret.insert(a.value().clone(), 1);
-        }
-    });
+/// `calc_weight_single` for a holding known to be within `holding`, the weights at its two ends.
+pub async fn calc_weight_single_range(dao: Arc<DAO>, ele: &ElementHandle, holding: (f64, f64), config: &WeightConfig)
+    -> Result<(f64, f64)> {
+        let base_value = dao.get_element_base_value(ele).await.context(DatabaseSnafu)?;
+        let low = config.map_to_value.eval(holding.0).context(MathSnafu)?;
+        let high = config.map_to_value.eval(holding.1).context(MathSnafu)?;
+        Ok((low / base_value, high / base_value))
+}
 
-    let primal_elements =
-        dao.get_primary_elements().await.context(DatabaseSnafu)?
-        .into_iter().map(|a| {
-            (a, 0usize)
-        }).collect::<HashMap<ElementHandle, usize>>();
+/// `calc_weight` with every holding off by up to `uncertainty`, a fraction of it, as the least
+/// and the most weights: the holdings all at the low ends, and all at the high ends.
+pub async fn calc_weight_interval(dao: Arc<DAO>, ele: &ElementHandle, config: &WeightConfig, uncertainty: f64)
+    -> Result<(f64, f64)> {
+        let tree = constructing_tree(dao.clone(), ele).await?;
+        let range_of = async |x: &ElementHandle| -> Result<(f64, f64)> {
+            let holding = holding_of(dao.as_ref(), x, config).await?;
+            let range = ((holding * (1. - uncertainty)).max(0.), holding * (1. + uncertainty));
+            calc_weight_single_range(dao.clone(), x, range, config).await
+        };
 
-    for primary in primal_elements {
-        if !ret.contains_key(&primary.0) {
-            ret.insert(primary.0, primary.1);
+        let rate = config.blend_rate;
+        let root = range_of(tree.root().value()).await?;
+        let mut sub_weight = (1f64, 1f64);
+        for x in tree.nodes() {
+            if x != tree.root() {
+                let (low, high) = range_of(x.value()).await?;
+                sub_weight.0 += low;
+                sub_weight.1 += high;
+            }
         }
-    }
+        let low = rate * root.0 + (1.0 - rate) * (1.0 / sub_weight.0);
+        let high = rate * root.1 + (1.0 - rate) * (1.0 / sub_weight.1);
+        Ok((low.min(high), low.max(high)))
+}
 
-    Ok(ret)
+/// `calc_weight_path` as an interval, see `calc_weight_interval`.
+pub async fn calc_weight_path_interval(dao: Arc<DAO>, path: &Path, config: &WeightConfig, uncertainty: f64)
+    -> Result<(f64, f64)> {
+        if path.path.is_empty() {
+            let start = calc_weight_interval(dao.clone(), &path.start, config, uncertainty).await?;
+            let end = calc_weight_interval(dao.clone(), &path.end, config, uncertainty).await?;
+            return Ok(((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0));
+        }
+        let mut accumulated = (0f64, 0f64);
+        for x in &path.path {
+            let (low, high) = calc_weight_interval(dao.clone(), x, config, uncertainty).await?;
+            let bonus = if config.on_board.contains(x) { ON_BOARD_BONUS } else { 0. };
+            accumulated.0 += low + bonus;
+            accumulated.1 += high + bonus;
+        }
+        Ok(accumulated)
 }
 
-async fn constructing_tree(dao: Arc<DAO>, ele: &ElementHandle) -> Result<Tree<ElementHandle>> {
-    let mut tree = ego_tree::Tree::new(ele.clone());
-    let pn = tree.root();
-    use std::cell::RefCell;
-    let level = RefCell::new(vec![pn.id()]);
-
-    loop {
-        let mut new_level = vec![];
-        for nodeid in level.borrow().iter() {
-            let mut pn = tree.get_mut(nodeid.clone()).unwrap();
-            match dao.get_element_components(&pn.value()).await.context(DatabaseSnafu) {
-                Ok((ca, cb)) => {
-                    new_level.push(pn.append(ca).id());
-                    new_level.push(pn.append(cb).id());
-                },
-                Err(e) => {
-                    match e {
-                        T4ACHError::Database { source, .. }
-                        if matches!(source, crate::dao::Errors::FetchedZeroRow(..)) => {
-                            // leaf node
-                        },
-                            _ => {
-                                return Err(e);
-                        }
-                    }
+/// How stocked up the intermediates of `path` are, in [0, 1]: the least of their holdings mapped
+/// by `config`, relative to the most they map to. A step not held scores 0, a direct connection 1.
+pub async fn path_readiness(dao: Arc<DAO>, path: &Path, config: &WeightConfig) -> Result<f64> {
+    let mut readiness = 1f64;
+    for x in &path.path {
+        let holding = holding_of(dao.as_ref(), x, config).await?;
+        let value = config.map_to_value.eval(holding).context(MathSnafu)? / NumberMapToValue::MAX_VALUE;
+        readiness = readiness.min(value);
+    }
+    Ok(readiness.clamp(0., 1.))
+}
+
+/// How many of each aspect placing the path's intermediates spends, one each.
+/// With `to_primals`, every intermediate is counted as the primary elements it's made of.
+pub async fn path_aspect_cost(dao: Arc<DAO>, path: &Path, to_primals: bool) -> Result<HashMap<ElementHandle, usize>> {
+    let mut cost = HashMap::new();
+    for x in &path.path {
+        if to_primals {
+            for (primal, n) in crack_element_until_primary(dao.clone(), x).await? {
+                if n != 0 {
+                    *cost.entry(primal).or_default() += n;
                 }
             }
-        }
-        if new_level.len() != 0 {
-            level.swap(&RefCell::new(new_level));
         } else {
-            break;
+            *cost.entry(x.clone()).or_default() += 1;
         }
     }
-    Ok(tree)
+    Ok(cost)
 }
 
-/// An element's weight = map_to_value(element_holding) / base_value + (components' weight)
-pub async fn calc_weight(dao: Arc<DAO>, ele: &ElementHandle) -> Result<f64> {
+/// Every distinct aspect of `ele`'s decomposition tree, `ele` itself, the intermediates and
+/// the primary elements, sorted by name. `crack_element_until_primary` only gives the leaves.
+pub async fn all_aspects_in_tree(dao: Arc<DAO>, ele: &ElementHandle) -> Result<Vec<ElementHandle>> {
     let tree = constructing_tree(dao.clone(), ele).await?;
+    let mut ret = tree.values()
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    ret.sort();
+    Ok(ret)
+}
 
-    let rate = 0.7f64;
-    let mut weight = calc_weight_single(dao.clone(), tree.root().value()).await?;
-    let mut sub_weight = 1f64;
-    for x in tree.nodes() {
-        if x != tree.root() {
-            sub_weight += calc_weight_single(dao.clone(), x.value()).await?;
+/// The elements decomposing into exactly `k` primary elements in total, counted with repetition
+/// (Bestia is Aer, Ordo, Aqua and Terra, so 4), sorted by name.
+pub async fn aspects_by_primal_count(dao: Arc<DAO>, k: usize) -> Result<Vec<ElementHandle>> {
+    let mut ret = vec![];
+    for e in dao.list_elements().await.context(DatabaseSnafu)? {
+        let ele = ElementHandle::from(e.name);
+        let total = crack_element_until_primary(dao.clone(), &ele).await?
+            .values()
+            .sum::<usize>();
+        if total == k {
+            ret.push(ele);
         }
     }
-    weight = rate * weight + (1.0 - rate) * (1.0/sub_weight);
-    Ok(weight)
+    ret.sort();
+    Ok(ret)
 }
 
-pub async fn calc_weight_path(dao: Arc<DAO>, path: &Path) -> Result<f64> {
-    let mut accumulated = 0f64;
-    for x in &path.path {
-        accumulated += calc_weight(dao.clone(), x).await?;
-    }
-    Ok(accumulated)
+/// A per-path metric `rank_by_criteria` can combine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    /// fewer intermediates.
+    Length,
+    /// better stocked intermediates, by their mean mapped holding.
+    Holdings,
+    /// commoner intermediates, by their mean base value.
+    Rarity,
 }
 
-pub async fn calc_path_order_by_weight(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize)
-    -> Result<Vec<Path>> {
-        let mut pathes = calc_path(dao.clone(), from, to, steps_n).await?;
-        for path in &mut pathes {
-            let weight = calc_weight_path(dao.clone(), path).await?;
-            path.cached_weight = Some(weight);
-        }
-        pathes.sort_unstable_by(
-            |a, b| {
-                let av = a.cached_weight.unwrap();
-                let bv = b.cached_weight.unwrap();
-                // inverse less
-                if av > bv {
-                    Ordering::Less
-                } else if av == bv {
-                    Ordering::Equal
-                } else {
-                    Ordering::Greater
-                }
-            }
-        );
-        Ok(pathes)
-}
+/// The metrics to rank the paths by and their weights, parsed from
+/// `length:0.5,holdings:0.3,rarity:0.2`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Criteria(Vec<(Criterion, f64)>);
 
-pub async fn calc_path(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize)
-    -> Result<Vec<Path>> {
-        if steps_n == 0 {
-            if is_two_eles_connected(dao.as_ref(), from, to).await? {
-                return Ok(vec![Path::new(from.clone(), to.clone())]);
-            } else {
-                return Ok(vec![]);
+impl std::str::FromStr for Criteria {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut criteria: Vec<(Criterion, f64)> = vec![];
+        for item in s.split(',') {
+            let (name, weight) = item.split_once(':')
+                .ok_or(format!("expect `metric:weight`, but got `{item}`"))?;
+            let criterion = match name.trim() {
+                "length" => Criterion::Length,
+                "holdings" => Criterion::Holdings,
+                "rarity" => Criterion::Rarity,
+                other => return Err(format!("unknown metric `{other}`, expect length, holdings or rarity")),
+            };
+            let weight = weight.trim().parse::<f64>()
+                .ok()
+                .filter(|w| w.is_finite() && *w >= 0.)
+                .ok_or(format!("the weight of {} must be a non-negative number", name.trim()))?;
+            if criteria.iter().any(|(c, _)| *c == criterion) {
+                return Err(format!("{} is given twice", name.trim()));
             }
-        } else if steps_n == 1 {
-            return calc_path_steps_1(dao.clone(), from, to).await;
-        } else if steps_n == 2 {
-            return calc_path_steps_2(dao.clone(), from, to).await;
-        } else {
-            let mut stack_f: Vec<Vec<ElementHandle>> = vec![vec![from.clone()]];
-            let mut result_pathes = Vec::new();
-            let end_relatives = get_relatives(dao.as_ref(), to).await?;
-
-            'outer: loop {
-                #[cfg(debug_assertions)]
-                {
-                    eprintln!("-- start");
-                    for (i, x) in stack_f.iter().enumerate() {
-                        eprintln!("--{i} - {x:?}");
-                    }
-                    eprintln!("-- end");
-                }
+            criteria.push((criterion, weight));
+        }
+        if criteria.iter().all(|(_, w)| *w == 0.) {
+            return Err("at least one weight must be positive".to_string());
+        }
+        Ok(Self(criteria))
+    }
+}
 
-                if let Some(last_v) = stack_f.last() {
-                    // test if stepped on the last step.
-                    if stack_f.len() - 1 != steps_n {
-                        let p = last_v.last().unwrap();
-                        let new_elements
-                            = get_relatives(dao.as_ref(), p)
-                            .await?
-                            .iter()
-                            .cloned()
-                            .collect::<Vec<_>>();
-                        // MARK push
-                        stack_f.push(new_elements);
-                    } else {
-                        for x in last_v {
-                            if end_relatives.contains(&x) {
-                                let mut dest_path = Path::new(
-                                    from.clone(),
-                                    to.clone());
-
-
-                                for x in 1..(stack_f.len() - 1) {
-                                    let x = stack_f.get(x).unwrap();
-                                    dest_path.push(x.last().unwrap().clone());
-                                }
-                                dest_path.push(x.clone());
-                                result_pathes.push(dest_path);
-                            }
+/// Order `pathes` by the weighted sum of their metrics, the best first, along with that sum.
+/// Each metric is normalized over `pathes` to 0 for the worst and 1 for the best of them,
+/// so a metric on which all of them are equal doesn't count. Ties keep their order.
+pub async fn rank_by_criteria(dao: Arc<DAO>, pathes: Vec<Path>, criteria: &Criteria, config: &WeightConfig)
+    -> Result<Vec<(Path, f64)>> {
+        let mean = |v: &[f64], empty: f64| if v.is_empty() { empty } else { v.iter().sum::<f64>() / v.len() as f64 };
+        // every metric as the higher the better.
+        let mut metrics = vec![vec![]; criteria.0.len()];
+        for path in &pathes {
+            for (row, (criterion, _)) in metrics.iter_mut().zip(&criteria.0) {
+                let value = match criterion {
+                    Criterion::Length => -(path.path.len() as f64),
+                    Criterion::Holdings => {
+                        let mut mapped = vec![];
+                        for x in &path.path {
+                            let holding = holding_of(dao.as_ref(), x, config).await?;
+                            mapped.push(config.map_to_value.eval(holding).context(MathSnafu)?
+                                / NumberMapToValue::MAX_VALUE);
                         }
-
-                        stack_f.pop();
-                        let stack_f_last_index = stack_f.len() - 1;
-                        stack_f
-                            .get_mut(stack_f_last_index)
-                            .unwrap()
-                            .pop();
-                        if stack_f.last().unwrap().is_empty() {
-                            stack_f.pop();
-
-                            while let Some(v) = stack_f.last() {
-                                if v.len() == 1 {
-                                    stack_f.pop();
-                                    if stack_f.is_empty() {
-                                        break 'outer;
-                                    }
-                                    let stack_f_last_index = stack_f.len() - 1;
-                                    stack_f
-                                        .get_mut(stack_f_last_index)
-                                        .unwrap()
-                                        .pop();
-
-                                    if stack_f.len() == 1 && stack_f.last().unwrap().len() == 0 {
-                                        stack_f.pop();
-                                    }
-                                } else if v.len() == 0 {
-                                    stack_f.pop();
-                                } else {
-                                    let stack_f_last_index = stack_f.len() - 1;
-                                    stack_f
-                                        .get_mut(stack_f_last_index)
-                                        .unwrap()
-                                        .pop();
-
-                                    break;
-                                }
-                            }
+                        mean(&mapped, 1.)
+                    },
+                    Criterion::Rarity => {
+                        let mut base_values = vec![];
+                        for x in &path.path {
+                            base_values.push(dao.get_element_base_value(x).await.context(DatabaseSnafu)?);
                         }
-                    }
-                } else {
-                    // stack_f is empty now.
-                    break 'outer;
-                }
+                        -mean(&base_values, 0.)
+                    },
+                };
+                row.push(value);
             }
-            return Ok(result_pathes);
         }
+        let normalized = metrics.iter()
+            .map(|row| {
+                let min = row.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = row.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                row.iter()
+                    .map(|v| if max > min { (v - min) / (max - min) } else { 0. })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut ret = pathes.into_iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let score = criteria.0.iter()
+                    .zip(&normalized)
+                    .map(|((_, weight), row)| weight * row[i])
+                    .sum::<f64>();
+                (path, score)
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ret)
+}
+
+/// Parse the `Crack` arguments like `Sano Aer 48 Ira 11 Superbia`, the aspects each followed by
+/// an optional quantity, into each aspect's total quantity. The aspects are spelled as stored.
+pub async fn parse_crack_args(dao: &DAO, args: &[String]) -> Result<HashMap<ElementHandle, usize>> {
+    ensure!(!args.is_empty(), ParsingAspectsSnafu { reason: "at least one aspect is needed" });
+    let names = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let exist = dao.which_elements_exist(&names).await.context(DatabaseSnafu)?;
+    let mut ret = HashMap::new();
+    let mut idx = 0usize;
+    while idx < args.len() {
+        let name = &args[idx];
+        if name.parse::<usize>().is_ok() {
+            return ParsingAspectsSnafu {
+                reason: if idx == 0 {
+                    format!("the first must be an aspect, but input is {name}")
+                } else {
+                    format!("the quantity {name} follows another quantity")
+                },
+            }.fail();
+        }
+        let Some(ele) = exist[name].clone() else {
+            return ElementNotFoundSnafu { element_name: name.clone(), context: "the aspects to crack" }.fail();
+        };
+        let quantity = match args.get(idx + 1).map(|a| a.parse::<usize>()) {
+            Some(Ok(n)) => {
+                idx += 2;
+                n
+            },
+            _ => {
+                idx += 1;
+                1
+            },
+        };
+        *ret.entry(ele).or_insert(0) += quantity;
     }
+    Ok(ret)
+}
 
-#[cfg(test)]
-mod tests {
-    use crate::{dao::DAO, pathes::calc_path_order_by_weight, recipes::ElementHandle};
+pub async fn crack_element_until_primary(dao: Arc<DAO>, ele: &ElementHandle) -> Result<HashMap<ElementHandle, usize>> {
+    let mut ret = crack_element_to_depth(dao.clone(), ele, usize::MAX).await?;
 
-    use super::calc_path;
+    let primal_elements =
+        dao.get_primary_elements().await.context(DatabaseSnafu)?
+        .into_iter().map(|a| {
+            (a, 0usize)
+        }).collect::<HashMap<ElementHandle, usize>>();
 
-    use std::sync::{Arc, LazyLock};
+    for primary in primal_elements {
+        ret.entry(primary.0).or_insert(primary.1);
+    }
 
-    static INIT_SQLX_DRIVERS: LazyLock<()> = LazyLock::new(|| {
-        sqlx::any::install_default_drivers();
-    });
+    Ok(ret)
+}
 
-    #[tokio::test]
+/// Count the aspects `ele` cracks into `max_depth` recipes down, or the primary ones before.
+/// `ele` itself at the depth 0.
+pub async fn crack_element_to_depth(dao: Arc<DAO>, ele: &ElementHandle, max_depth: usize)
+    -> Result<HashMap<ElementHandle, usize>> {
+    let tree = constructing_trees(dao, ele, 1, max_depth).await?.swap_remove(0);
+    let mut ret = HashMap::new();
+    for leaf in tree.nodes().filter(|a| !a.has_children()) {
+        *ret.entry(leaf.value().clone()).or_insert(0) += 1;
+    }
+    Ok(ret)
+}
+
+/// The sum of `counts` per mod of the aspects, those without a mod under `UNKNOWN_MOD`.
+pub async fn counts_by_mod(dao: &DAO, counts: &HashMap<ElementHandle, usize>) -> Result<std::collections::BTreeMap<String, usize>> {
+    let mut ret = std::collections::BTreeMap::new();
+    for (ele, n) in counts {
+        let mod_name = dao.element_mod(ele).await.context(DatabaseSnafu)?
+            .unwrap_or(UNKNOWN_MOD.to_string());
+        *ret.entry(mod_name).or_insert(0) += n;
+    }
+    Ok(ret)
+}
+
+/// `ele`'s decomposition taking the first recipe of each element, see `constructing_trees`.
+async fn constructing_tree(dao: Arc<DAO>, ele: &ElementHandle) -> Result<Tree<ElementHandle>> {
+    Ok(constructing_trees(dao, ele, 1, usize::MAX).await?.swap_remove(0))
+}
+
+/// `ele`'s decomposition trees, one for each choice of the recipes of the elements on the way,
+/// at most `limit` of them. The children of each node are appended level by level, and the
+/// first tree takes the first recipe of every element. The nodes `max_depth` below the root
+/// aren't decomposed. A recipe making an element of itself, maybe through others, is skipped;
+/// it's a `CyclicRecipe` error if no tree is left.
+async fn constructing_trees(dao: Arc<DAO>, ele: &ElementHandle, limit: usize, max_depth: usize)
+    -> Result<Vec<Tree<ElementHandle>>> {
+    let tree = ego_tree::Tree::new(ele.clone());
+    let root = tree.root().id();
+    // the trees to go on with, each with its nodes still to decompose in order.
+    let mut pending = vec![(tree, std::collections::VecDeque::from([root]))];
+    let mut ret = vec![];
+    let mut first_cycle = None;
+    while let Some((mut tree, mut todo)) = pending.pop() {
+        let Some(nodeid) = todo.pop_front() else {
+            ret.push(tree);
+            if ret.len() == limit {
+                break;
+            }
+            continue;
+        };
+        if tree.get(nodeid).unwrap().ancestors().count() >= max_depth {
+            pending.push((tree, todo));
+            continue;
+        }
+        let recipes = dao.get_all_element_components(tree.get(nodeid).unwrap().value()).await
+            .context(DatabaseSnafu)?;
+        if recipes.is_empty() {
+            // leaf node
+            pending.push((tree, todo));
+            continue;
+        }
+        // the root down to this node, a component among them would be decomposed forever.
+        let node = tree.get(nodeid).unwrap();
+        let mut branch = node.ancestors().map(|a| a.value().get_name()).collect::<Vec<_>>();
+        branch.reverse();
+        branch.push(node.value().get_name());
+        let mut acyclic = vec![];
+        for (ca, cb) in recipes {
+            let looped = [&ca, &cb].into_iter()
+                .find_map(|c| branch.iter().position(|a| *a == c.get_name()).map(|i| (i, c.get_name())));
+            match looped {
+                Some((i, component)) => {
+                    if first_cycle.is_none() {
+                        let mut cycle = branch[i..].to_vec();
+                        cycle.push(component);
+                        first_cycle = Some(cycle);
+                    }
+                },
+                None => acyclic.push((ca, cb)),
+            }
+        }
+        // the alternatives stay pending even for one tree, the first one can run into a cycle deeper down.
+        // the stack is popped from the back.
+        for (i, (ca, cb)) in acyclic.into_iter().enumerate().rev() {
+            let (mut tree, mut todo) = if i == 0 {
+                (std::mem::replace(&mut tree, Tree::new(ele.clone())), std::mem::take(&mut todo))
+            } else {
+                (tree.clone(), todo.clone())
+            };
+            let mut pn = tree.get_mut(nodeid).unwrap();
+            todo.push_back(pn.append(ca).id());
+            todo.push_back(pn.append(cb).id());
+            pending.push((tree, todo));
+        }
+    }
+    if ret.is_empty() && let Some(cycle) = first_cycle {
+        return CyclicRecipeSnafu { cycle }.fail();
+    }
+    Ok(ret)
+}
+
+/// The most decompositions kept for an element in `all_decompositions`.
+pub const MAX_DECOMPOSITIONS: usize = 256;
+
+type Profile = std::collections::BTreeMap<ElementHandle, usize>;
+
+fn profiles_of(ele: &ElementHandle, recipes: &HashMap<ElementHandle, Vec<(ElementHandle, ElementHandle)>>,
+    memo: &mut HashMap<ElementHandle, Vec<Profile>>, visiting: &mut HashSet<ElementHandle>)
+    -> Vec<Profile> {
+        if let Some(v) = memo.get(ele) {
+            return v.clone();
+        }
+        let Some(choices) = recipes.get(ele) else {
+            return vec![Profile::from([(ele.clone(), 1)])];
+        };
+        visiting.insert(ele.clone());
+        let mut ret = vec![];
+        for (ca, cb) in choices {
+            // a recipe on a cycle never decomposes.
+            if visiting.contains(ca) || visiting.contains(cb) {
+                continue;
+            }
+            let of_b = profiles_of(cb, recipes, memo, visiting);
+            for pa in profiles_of(ca, recipes, memo, visiting) {
+                for pb in &of_b {
+                    let mut merged = pa.clone();
+                    for (p, n) in pb {
+                        *merged.entry(p.clone()).or_default() += n;
+                    }
+                    ret.push(merged);
+                }
+            }
+        }
+        visiting.remove(ele);
+        ret.sort();
+        ret.dedup();
+        ret.truncate(MAX_DECOMPOSITIONS);
+        memo.insert(ele.clone(), ret.clone());
+        ret
+}
+
+/// Every distinct primary elements counts `ele` can crack into, choosing each recipe of each
+/// element on the way, sorted. At most `MAX_DECOMPOSITIONS` of them are kept for each element.
+pub async fn all_decompositions(dao: Arc<DAO>, ele: &ElementHandle) -> Result<Vec<HashMap<ElementHandle, usize>>> {
+    let mut recipes: HashMap<ElementHandle, Vec<(ElementHandle, ElementHandle)>> = HashMap::new();
+    for (name, ca, cb) in dao.list_recipes().await.context(DatabaseSnafu)? {
+        recipes.entry(name).or_default().push((ca, cb));
+    }
+    Ok(profiles_of(ele, &recipes, &mut HashMap::new(), &mut HashSet::new())
+        .into_iter()
+        .map(|p| p.into_iter().collect())
+        .collect())
+}
+
+/// `ele`'s decomposition as a Mermaid `graph TD`, each aspect pointing to its two components.
+/// An aspect met twice gets a node each time, the primary elements are rounded and of class `primal`.
+pub async fn decomposition_mermaid(dao: Arc<DAO>, ele: &ElementHandle) -> Result<String> {
+    use std::fmt::Write;
+    let tree = constructing_tree(dao.clone(), ele).await?;
+    let ids = tree.nodes()
+        .enumerate()
+        .map(|(i, a)| (a.id(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut out = String::from("graph TD\n    classDef primal fill:#fd6,stroke:#a80\n");
+    for node in tree.nodes() {
+        let name = node.value().get_name();
+        let _ = if node.has_children() {
+            writeln!(out, "    n{}[\"{}\"]", ids[&node.id()], name)
+        } else {
+            writeln!(out, "    n{}([\"{}\"]):::primal", ids[&node.id()], name)
+        };
+    }
+    for node in tree.nodes() {
+        for child in node.children() {
+            let _ = writeln!(out, "    n{} --> n{}", ids[&node.id()], ids[&child.id()]);
+        }
+    }
+    Ok(out)
+}
+
+/// A compound element cracking to a primary element, see `best_source_for_primal`.
+#[derive(Debug, PartialEq)]
+pub struct PrimalSource {
+    pub element: ElementHandle,
+    /// how many of the primary element cracking one of `element` yields.
+    pub yield_per_craft: usize,
+    /// how many of `element` to crack to reach the needed count.
+    pub crafts_needed: usize,
+    /// `yield_per_craft` per base value, higher is cheaper.
+    pub efficiency: f64,
+}
+
+/// Rank the compound elements yielding `primal` when cracked by their yield per base value,
+/// the most efficient first.
+pub async fn best_source_for_primal(dao: Arc<DAO>, primal: &ElementHandle, needed: usize)
+    -> Result<Vec<PrimalSource>> {
+        let mut ret = vec![];
+        for e in dao.list_elements().await.context(DatabaseSnafu)? {
+            let element = ElementHandle::from(e.name);
+            if element == *primal || dao.is_primary_element(&element).await.context(DatabaseSnafu)? {
+                continue;
+            }
+            let cracked = crack_element_until_primary(dao.clone(), &element).await?;
+            let yield_per_craft = cracked.get(primal).copied().unwrap_or(0);
+            if yield_per_craft == 0 {
+                continue;
+            }
+            ret.push(PrimalSource {
+                element,
+                yield_per_craft,
+                crafts_needed: needed.div_ceil(yield_per_craft),
+                efficiency: yield_per_craft as f64 / e.base_value,
+            });
+        }
+        ret.sort_by(|a, b| b.efficiency.total_cmp(&a.efficiency).then_with(|| a.element.cmp(&b.element)));
+        Ok(ret)
+}
+
+/// Group the elements cracking to the same primary elements counts, they are interchangeable
+/// by primal cost. Only the groups of two or more elements are returned, each sorted by name.
+pub async fn find_equivalent_aspects(dao: Arc<DAO>) -> Result<Vec<Vec<ElementHandle>>> {
+    let mut groups: HashMap<Vec<(ElementHandle, usize)>, Vec<ElementHandle>> = HashMap::new();
+    for e in dao.list_elements().await.context(DatabaseSnafu)? {
+        let element = ElementHandle::from(e.name);
+        let mut profile = crack_element_until_primary(dao.clone(), &element).await?
+            .into_iter()
+            .filter(|(_, n)| *n != 0)
+            .collect::<Vec<_>>();
+        profile.sort_unstable();
+        groups.entry(profile).or_default().push(element);
+    }
+    let mut ret = groups.into_values()
+        .filter(|g| g.len() > 1)
+        .map(|mut g| {
+            g.sort();
+            g
+        })
+        .collect::<Vec<_>>();
+    ret.sort();
+    Ok(ret)
+}
+
+/// The elements craftable from the holdings within `max_crafts` combination steps, by depth:
+/// the first are made of two held elements, the next use those too and so on.
+/// Each depth is sorted by name, it stops early when nothing more can be made.
+pub async fn craftable_within_budget(dao: Arc<DAO>, max_crafts: usize) -> Result<Vec<Vec<ElementHandle>>> {
+    let mut available = dao.list_elements_holding().await.context(DatabaseSnafu)?
+        .into_iter()
+        .filter(|(_, n)| *n > 0.)
+        .map(|(e, _)| e)
+        .collect::<HashSet<_>>();
+    let recipes = dao.list_recipes().await.context(DatabaseSnafu)?;
+    let mut ret = vec![];
+    for _ in 0..max_crafts {
+        let mut made = recipes.iter()
+            .filter(|(name, a, b)| !available.contains(name) && available.contains(a) && available.contains(b))
+            .map(|(name, _, _)| name.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if made.is_empty() {
+            break;
+        }
+        made.sort();
+        available.extend(made.iter().cloned());
+        ret.push(made);
+    }
+    Ok(ret)
+}
+
+/// Each element's base value as a percentile rank: the share of the elements, in percent,
+/// whose base value is at most its own. The rarest are at 100, equal base values rank the same.
+pub async fn base_value_percentiles(dao: Arc<DAO>) -> Result<HashMap<ElementHandle, f64>> {
+    let elements = dao.list_elements().await.context(DatabaseSnafu)?;
+    let mut values = elements.iter().map(|e| e.base_value).collect::<Vec<_>>();
+    values.sort_by(f64::total_cmp);
+    let n = values.len() as f64;
+    Ok(elements.into_iter()
+        .map(|e| {
+            let at_most = values.partition_point(|v| *v <= e.base_value);
+            (ElementHandle::from(e.name), at_most as f64 / n * 100.)
+        })
+        .collect())
+}
+
+/// The compound element whose primal profile is the most like the holdings' one, by cosine
+/// similarity, with that similarity. The holdings are cracked into primary elements and summed first,
+/// the infinite ones left out. `None` if nothing finite is held.
+pub async fn holdings_centroid(dao: Arc<DAO>) -> Result<Option<(ElementHandle, f64)>> {
+    let mut distribution: HashMap<ElementHandle, f64> = HashMap::new();
+    for (ele, holding) in dao.list_elements_holding().await.context(DatabaseSnafu)? {
+        if !holding.is_finite() || holding <= 0. {
+            continue;
+        }
+        for (primal, n) in crack_element_until_primary(dao.clone(), &ele).await? {
+            *distribution.entry(primal).or_default() += holding * n as f64;
+        }
+    }
+    let norm = distribution.values().map(|a| a * a).sum::<f64>().sqrt();
+    if norm == 0. {
+        return Ok(None);
+    }
+
+    let mut best: Option<(ElementHandle, f64)> = None;
+    let mut compounds = dao.list_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|e| ElementHandle::from(e.name))
+        .collect::<Vec<_>>();
+    compounds.sort();
+    for element in compounds {
+        if dao.is_primary_element(&element).await.context(DatabaseSnafu)? {
+            continue;
+        }
+        let profile = crack_element_until_primary(dao.clone(), &element).await?;
+        let dot = profile.iter()
+            .map(|(p, n)| *n as f64 * distribution.get(p).copied().unwrap_or(0.))
+            .sum::<f64>();
+        let profile_norm = profile.values().map(|n| (n * n) as f64).sum::<f64>().sqrt();
+        let similarity = dot / (norm * profile_norm);
+        if best.as_ref().is_none_or(|(_, s)| similarity > *s) {
+            best = Some((element, similarity));
+        }
+    }
+    Ok(best)
+}
+
+/// How much two elements' decompositions share, see `decomposition_overlap`.
+#[derive(Debug, PartialEq)]
+pub struct DecompositionOverlap {
+    /// the primary elements both crack into, sorted by name.
+    pub shared: Vec<ElementHandle>,
+    /// the shared primary elements out of all those either cracks into.
+    pub jaccard: f64,
+    /// the primary elements counted in both, each the lesser of its two counts.
+    pub count_overlap: usize,
+}
+
+pub async fn decomposition_overlap(dao: Arc<DAO>, a: &ElementHandle, b: &ElementHandle)
+    -> Result<DecompositionOverlap> {
+        // every primary element is counted, even the absent ones.
+        let mut of_a = crack_element_until_primary(dao.clone(), a).await?;
+        of_a.retain(|_, n| *n != 0);
+        let mut of_b = crack_element_until_primary(dao.clone(), b).await?;
+        of_b.retain(|_, n| *n != 0);
+        let mut shared = of_a.keys()
+            .filter(|p| of_b.contains_key(*p))
+            .cloned()
+            .collect::<Vec<_>>();
+        shared.sort();
+        let union = of_a.keys().chain(of_b.keys()).collect::<HashSet<_>>().len();
+        let count_overlap = shared.iter().map(|p| of_a[p].min(of_b[p])).sum();
+        Ok(DecompositionOverlap {
+            jaccard: if union == 0 { 0. } else { shared.len() as f64 / union as f64 },
+            shared,
+            count_overlap,
+        })
+}
+
+/// Bucket the compound elements by the primary element they crack into the most of,
+/// the ties going to the primary element first by name. Every primary element is listed,
+/// sorted by name, with its compounds sorted by name.
+pub async fn group_by_dominant_primal(dao: Arc<DAO>) -> Result<Vec<(ElementHandle, Vec<ElementHandle>)>> {
+    let mut groups = dao.get_primary_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|p| (p, vec![]))
+        .collect::<HashMap<_, Vec<ElementHandle>>>();
+    for e in dao.list_elements().await.context(DatabaseSnafu)? {
+        let element = ElementHandle::from(e.name);
+        if groups.contains_key(&element) {
+            continue;
+        }
+        let dominant = crack_element_until_primary(dao.clone(), &element).await?
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(p, _)| p);
+        if let Some(dominant) = dominant {
+            groups.entry(dominant).or_default().push(element);
+        }
+    }
+    let mut ret = groups.into_iter()
+        .map(|(p, mut g)| {
+            g.sort();
+            (p, g)
+        })
+        .collect::<Vec<_>>();
+    ret.sort();
+    Ok(ret)
+}
+
+/// The holdings already past `threshold` of the value they map to at most, collecting more of
+/// them barely changes the weighting. Each with its fraction, the highest first.
+pub async fn wasted_holdings(dao: Arc<DAO>, threshold: f64, config: &WeightConfig)
+    -> Result<Vec<(ElementHandle, f64)>> {
+        let mut ret = vec![];
+        for (ele, holding) in dao.list_elements_holding().await.context(DatabaseSnafu)? {
+            let fraction = config.map_to_value.eval(holding).context(MathSnafu)? / NumberMapToValue::MAX_VALUE;
+            if fraction >= threshold {
+                ret.push((ele, fraction));
+            }
+        }
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ret)
+}
+
+/// How far a compound's primal counts `got` are from the `wanted` ones, see `find_closest_compound`.
+fn profile_distance(wanted: &HashMap<ElementHandle, usize>, got: &HashMap<ElementHandle, usize>, cover: bool)
+    -> usize {
+        let shortfall = wanted.iter()
+            .map(|(e, n)| n.saturating_sub(got.get(e).copied().unwrap_or(0)))
+            .sum::<usize>();
+        if cover {
+            return shortfall;
+        }
+        let surplus = got.iter()
+            .map(|(e, n)| n.saturating_sub(wanted.get(e).copied().unwrap_or(0)))
+            .sum::<usize>();
+        shortfall + surplus
+}
+
+/// Rank the compound elements by how close their decomposition is to the `wanted` primal counts,
+/// the closest first, each with its distance.
+///
+/// By default the distance counts both the missing and the surplus primals.
+/// With `cover` only the missing ones count, the compound of the least surplus breaking the ties,
+/// so the first one is the compound leaving the least gaps.
+pub async fn find_closest_compound(dao: Arc<DAO>, wanted: &HashMap<ElementHandle, usize>, cover: bool)
+    -> Result<Vec<(ElementHandle, usize)>> {
+        let mut ranked = vec![];
+        for e in dao.list_elements().await.context(DatabaseSnafu)? {
+            let element = ElementHandle::from(e.name);
+            if dao.is_primary_element(&element).await.context(DatabaseSnafu)? {
+                continue;
+            }
+            let got = crack_element_until_primary(dao.clone(), &element).await?;
+            let total = got.values().sum::<usize>();
+            ranked.push((profile_distance(wanted, &got, cover), total, element));
+        }
+        ranked.sort();
+        Ok(ranked.into_iter().map(|(d, _, e)| (e, d)).collect())
+}
+
+/// A compound whose base value doesn't match its decomposition, see `verify_base_values`.
+#[derive(Debug)]
+pub struct BaseValueDeviation {
+    pub element: ElementHandle,
+    pub base_value: f64,
+    /// the primary elements it cracks into, each weighted by its own base value.
+    pub primal_cost: f64,
+    /// `(base_value - primal_cost) / primal_cost`.
+    pub deviation: f64,
+}
+
+/// Flag the compound elements whose base value is off from their primal cost by more than
+/// `tolerance`, relatively. Those are likely data errors. The wildest first.
+pub async fn verify_base_values(dao: Arc<DAO>, tolerance: f64) -> Result<Vec<BaseValueDeviation>> {
+    let mut ret = vec![];
+    for e in dao.list_elements().await.context(DatabaseSnafu)? {
+        let element = ElementHandle::from(e.name);
+        if dao.is_primary_element(&element).await.context(DatabaseSnafu)? {
+            continue;
+        }
+        let mut primal_cost = 0.;
+        for (primal, n) in crack_element_until_primary(dao.clone(), &element).await? {
+            primal_cost += n as f64 * dao.get_element_base_value(&primal).await.context(DatabaseSnafu)?;
+        }
+        let deviation = (e.base_value - primal_cost) / primal_cost;
+        if deviation.abs() > tolerance {
+            ret.push(BaseValueDeviation {
+                element,
+                base_value: e.base_value,
+                primal_cost,
+                deviation,
+            });
+        }
+    }
+    ret.sort_by(|a, b| b.deviation.abs().total_cmp(&a.deviation.abs()).then_with(|| a.element.cmp(&b.element)));
+    Ok(ret)
+}
+
+/// Whether to get a primary element directly or by cracking compounds, see `craft_vs_farm`.
+#[derive(Debug, PartialEq)]
+pub enum Recommendation {
+    Farm,
+    Craft,
+}
+
+/// The estimated costs of getting some of a primary element, by the base values.
+#[derive(Debug)]
+pub struct CraftVsFarm {
+    /// `needed` times the primary element's base value.
+    pub farm_cost: f64,
+    /// the cheapest compound to crack and what cracking enough of it costs, if any yields the primary element.
+    pub craft: Option<(PrimalSource, f64)>,
+    pub recommendation: Recommendation,
+}
+
+/// Compare farming `needed` of `primal` with cracking the compound costing the least to yield as many.
+/// Farming is recommended on a tie.
+pub async fn craft_vs_farm(dao: Arc<DAO>, primal: &ElementHandle, needed: usize) -> Result<CraftVsFarm> {
+    let farm_cost = needed as f64 * dao.get_element_base_value(primal).await.context(DatabaseSnafu)?;
+    let mut craft: Option<(PrimalSource, f64)> = None;
+    for source in best_source_for_primal(dao.clone(), primal, needed).await? {
+        let cost = source.crafts_needed as f64 * dao.get_element_base_value(&source.element).await
+            .context(DatabaseSnafu)?;
+        if craft.as_ref().is_none_or(|a| cost < a.1) {
+            craft = Some((source, cost));
+        }
+    }
+    let recommendation = match &craft {
+        Some((_, cost)) if *cost < farm_cost => Recommendation::Craft,
+        _ => Recommendation::Farm,
+    };
+    Ok(CraftVsFarm { farm_cost, craft, recommendation })
+}
+
+/// The candidate new links of `best_new_link`, each with how many more pairs of aspects it connects.
+#[derive(Debug, PartialEq)]
+pub struct NewLink {
+    pub a: ElementHandle,
+    pub b: ElementHandle,
+    pub gain: usize,
+}
+
+/// A new compound made of `component_a` and `component_b` would relate them both,
+/// see `suggest_bridging_recipes`.
+#[derive(Debug, PartialEq)]
+pub struct BridgeRecipe {
+    pub component_a: ElementHandle,
+    pub component_b: ElementHandle,
+    /// the fewest intermediates between the bridged aspects once the compound is added.
+    pub steps: usize,
+}
+
+/// The links from `from` to every element of its component, by a breadth-first search.
+async fn links_from(dao: &DAO, from: &ElementHandle) -> Result<HashMap<ElementHandle, usize>> {
+    let mut links = HashMap::from([(from.clone(), 0usize)]);
+    let mut level = vec![from.clone()];
+    let mut depth = 0;
+    while !level.is_empty() {
+        depth += 1;
+        let mut next = vec![];
+        for x in &level {
+            for r in get_relatives(dao, x).await? {
+                if let Entry::Vacant(e) = links.entry(r.clone()) {
+                    e.insert(depth);
+                    next.push(r);
+                }
+            }
+        }
+        level = next;
+    }
+    Ok(links)
+}
+
+/// The relatives of the last aspect of the `partial` chain from which `to` is still reachable
+/// within `remaining_steps` intermediates, counting the suggested one. The best weighted first,
+/// the aspects already in the chain left out.
+pub async fn suggest_next_step(dao: Arc<DAO>, partial: &[ElementHandle], to: &ElementHandle, remaining_steps: usize,
+    config: &WeightConfig)
+    -> Result<Vec<(ElementHandle, f64)>> {
+        let Some(last) = partial.last() else {
+            return Ok(vec![]);
+        };
+        if remaining_steps == 0 {
+            return Ok(vec![]);
+        }
+        let to_links = links_from(dao.as_ref(), to).await?;
+        let mut ret = vec![];
+        for r in get_relatives(dao.as_ref(), last).await? {
+            if r == *to || partial.contains(&r) {
+                continue;
+            }
+            // placing `r` leaves `remaining_steps - 1` intermediates, so that many + 1 links.
+            if to_links.get(&r).is_some_and(|links| *links <= remaining_steps) {
+                let weight = calc_weight(dao.clone(), &r, config).await?;
+                ret.push((r, weight));
+            }
+        }
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ret)
+}
+
+/// The recipes bridging `a` and `b` when they're in separate components. A single recipe always
+/// does: a new compound made of one aspect of each component. The candidates pair every aspect
+/// of `a`'s component with every aspect of `b`'s, the shortest resulting connection first.
+/// Empty if they're already connected.
+pub async fn suggest_bridging_recipes(dao: Arc<DAO>, a: &ElementHandle, b: &ElementHandle)
+    -> Result<Vec<BridgeRecipe>> {
+        let from_a = links_from(dao.as_ref(), a).await?;
+        if from_a.contains_key(b) {
+            return Ok(vec![]);
+        }
+        let from_b = links_from(dao.as_ref(), b).await?;
+        let mut ret = vec![];
+        for (p, la) in &from_a {
+            for (q, lb) in &from_b {
+                ret.push(BridgeRecipe {
+                    component_a: p.clone(),
+                    component_b: q.clone(),
+                    // a .. p, the compound, q .. b
+                    steps: la + lb + 1,
+                });
+            }
+        }
+        ret.sort_by(|x, y| x.steps.cmp(&y.steps)
+            .then_with(|| (&x.component_a, &x.component_b).cmp(&(&y.component_a, &y.component_b))));
+        Ok(ret)
+}
+
+/// The elements sorted by name, and the relatives of each by their indices.
+fn indexed(adjacency: &Adjacency) -> (Vec<ElementHandle>, Vec<Vec<usize>>) {
+    let mut eles = adjacency.keys().cloned().collect::<Vec<_>>();
+    eles.sort();
+    let index = eles.iter().enumerate().map(|(i, e)| (e.clone(), i)).collect::<HashMap<_, _>>();
+    let adjacent = eles.iter()
+        .map(|e| adjacency[e].iter().filter_map(|r| index.get(r).copied()).collect())
+        .collect();
+    (eles, adjacent)
+}
+
+/// How many pairs of the elements not `removed` are disconnected.
+fn disconnected_pairs(adjacent: &[Vec<usize>], removed: Option<usize>) -> usize {
+    let n = adjacent.len();
+    let mut seen = vec![false; n];
+    if let Some(r) = removed {
+        seen[r] = true;
+    }
+    let alive = n - removed.iter().count();
+    let mut connected = 0;
+    for start in 0..n {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut size = 0;
+        let mut frontier = vec![start];
+        while let Some(x) = frontier.pop() {
+            size += 1;
+            for &y in &adjacent[x] {
+                if !seen[y] {
+                    seen[y] = true;
+                    frontier.push(y);
+                }
+            }
+        }
+        connected += size * (size - 1) / 2;
+    }
+    alive * alive.saturating_sub(1) / 2 - connected
+}
+
+/// For each aspect, how many more pairs of the other aspects would be disconnected
+/// if it were removed from the recipe graph, the most critical first.
+///
+/// With `sample` only that many aspects, evenly spaced by name, are evaluated.
+pub fn most_critical_aspect(adjacency: &Adjacency, sample: Option<usize>) -> Vec<(ElementHandle, usize)> {
+    let (eles, adjacent) = indexed(adjacency);
+    let n = eles.len();
+    let mut candidates = (0..n).collect::<Vec<_>>();
+    if let Some(sample) = sample.filter(|a| *a < n) {
+        candidates = (0..sample).map(|i| i * n / sample).collect();
+    }
+
+    // the components of the whole graph, to tell the pairs already disconnected.
+    let mut component = vec![usize::MAX; n];
+    let mut sizes = vec![];
+    for start in 0..n {
+        if component[start] != usize::MAX {
+            continue;
+        }
+        let id = sizes.len();
+        component[start] = id;
+        let mut size = 0;
+        let mut frontier = vec![start];
+        while let Some(x) = frontier.pop() {
+            size += 1;
+            for &y in &adjacent[x] {
+                if component[y] == usize::MAX {
+                    component[y] = id;
+                    frontier.push(y);
+                }
+            }
+        }
+        sizes.push(size);
+    }
+    let before = disconnected_pairs(&adjacent, None);
+
+    let mut ret = candidates.into_iter()
+        .map(|v| {
+            // the pairs `v` was one end of are gone either way.
+            let lost = n - sizes[component[v]];
+            let after = disconnected_pairs(&adjacent, Some(v));
+            (eles[v].clone(), after - (before - lost))
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ret
+}
+
+/// The elements sorted by name, and the links between each two of them by their indices,
+/// `usize::MAX` if they aren't connected.
+fn all_pairs_links(adjacency: &Adjacency) -> (Vec<ElementHandle>, Vec<Vec<usize>>) {
+    let (eles, adjacent) = indexed(adjacency);
+
+    // by breadth-first searches.
+    let n = eles.len();
+    let mut dist = vec![vec![usize::MAX; n]; n];
+    for (start, row) in dist.iter_mut().enumerate() {
+        row[start] = 0;
+        let mut level = vec![start];
+        while !level.is_empty() {
+            let mut next = vec![];
+            for x in level {
+                for &y in &adjacent[x] {
+                    if row[y] == usize::MAX {
+                        row[y] = row[x] + 1;
+                        next.push(y);
+                    }
+                }
+            }
+            level = next;
+        }
+    }
+    (eles, dist)
+}
+
+/// The most intermediates needed to connect any two connected elements,
+/// `None` if no two elements are connected.
+pub fn graph_diameter(adjacency: &Adjacency) -> Option<usize> {
+    let (_, dist) = all_pairs_links(adjacency);
+    dist.iter()
+        .flatten()
+        .filter(|links| **links != 0 && **links != usize::MAX)
+        .max()
+        .map(|links| links - 1)
+}
+
+/// For the pairs of aspects not related yet, how many more pairs of aspects would be connected
+/// within `max_steps` intermediates if they were, the best first.
+///
+/// It's quartic in the number of aspects, so with `sample` only that many candidate pairs,
+/// evenly spaced among all of them, are evaluated.
+pub fn best_new_link(adjacency: &Adjacency, max_steps: usize, sample: Option<usize>) -> Vec<NewLink> {
+    let (eles, dist) = all_pairs_links(adjacency);
+
+    let max_links = max_steps + 1;
+    let mut candidates = vec![];
+    for (u, row) in dist.iter().enumerate() {
+        for (v, d) in row.iter().enumerate().skip(u + 1) {
+            if *d != 1 {
+                candidates.push((u, v));
+            }
+        }
+    }
+    if let Some(sample) = sample.filter(|a| *a < candidates.len()) {
+        candidates = (0..sample).map(|i| candidates[i * candidates.len() / sample]).collect();
+    }
+
+    let mut ret = vec![];
+    for (u, v) in candidates {
+        let via = |x: usize, p: usize, q: usize, y: usize| {
+            dist[x][p].saturating_add(1).saturating_add(dist[q][y])
+        };
+        let mut gain = 0;
+        for (x, row) in dist.iter().enumerate() {
+            for (y, d) in row.iter().enumerate().skip(x + 1) {
+                if *d > max_links && usize::min(via(x, u, v, y), via(x, v, u, y)) <= max_links {
+                    gain += 1;
+                }
+            }
+        }
+        ret.push(NewLink { a: eles[u].clone(), b: eles[v].clone(), gain });
+    }
+    ret.sort_by(|a, b| b.gain.cmp(&a.gain).then_with(|| (&a.a, &a.b).cmp(&(&b.a, &b.b))));
+    ret
+}
+
+/// Each element's minimal decomposition depth to the primary elements, its tier.
+/// Primary elements are tier 0, the ones made of two primary elements are tier 1 and so on.
+/// An element with several recipes takes the shallowest one,
+/// the elements which can't be decomposed to primary elements (in a cyclic recipe) are left out.
+pub async fn aspect_tiers(dao: Arc<DAO>) -> Result<HashMap<ElementHandle, usize>> {
+    let mut tiers = dao.get_primary_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|a| (a, 0usize))
+        .collect::<HashMap<_, _>>();
+    let recipes = dao.list_recipes().await.context(DatabaseSnafu)?;
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, ca, cb) in &recipes {
+            if let (Some(ta), Some(tb)) = (tiers.get(ca), tiers.get(cb)) {
+                let tier = 1 + usize::max(*ta, *tb);
+                if tiers.get(name).is_none_or(|t| tier < *t) {
+                    tiers.insert(name.clone(), tier);
+                    changed = true;
+                }
+            }
+        }
+    }
+    Ok(tiers)
+}
+
+/// A hash of every holding, `config`'s overrides applied, and of its blend rate.
+/// The overrides of the elements without a holding row are in it too. FNV-1a, so it's the same across runs.
+async fn holdings_hash(dao: &DAO, config: &WeightConfig) -> Result<u64> {
+    let mut holdings = dao.list_elements_holding().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|(e, n)| (e.get_name(), n))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    holdings.extend(config.holdings.iter().map(|(e, n)| (e.get_name(), *n)));
+
+    let mut hash = 0xcbf29ce484222325u64;
+    // the weights are blended by the rate too.
+    let blend = config.blend_rate.to_bits().to_le_bytes();
+    for (name, num) in holdings {
+        for byte in name.bytes().chain([0]).chain(num.to_bits().to_le_bytes()).chain(blend) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    Ok(hash)
+}
+
+/// `config` with its `holdings_hash` taken now, so the weights of a search don't read every
+/// holding again each. The holdings must not change until it's dropped.
+async fn with_holdings_hash(dao: &DAO, config: &WeightConfig) -> Result<WeightConfig> {
+    let mut config = config.clone();
+    if config.decay.is_none() {
+        config.holdings_hash = Some(holdings_hash(dao, &config).await?);
+    }
+    Ok(config)
+}
+
+/// An element's weight = map_to_value(element_holding) / base_value + (components' weight)
+///
+/// It's taken from the `weight_cache` table `precompute_weights` fills, by the alpha, while the
+/// holdings stay the same. Not when the holdings decay. The searches only read the table.
+pub async fn calc_weight(dao: Arc<DAO>, ele: &ElementHandle, config: &WeightConfig) -> Result<f64> {
+    if config.decay.is_some() {
+        // the decayed holdings change by the second.
+        return calc_weight_uncached(dao, ele, config).await;
+    }
+    let alpha = config.map_to_value.alpha();
+    let hash = match config.holdings_hash {
+        Some(hash) => hash,
+        None => holdings_hash(dao.as_ref(), config).await?,
+    };
+    if let Some(weight) = dao.get_cached_weight(ele, alpha, hash).await.context(DatabaseSnafu)? {
+        return Ok(weight);
+    }
+    calc_weight_uncached(dao, ele, config).await
+}
+
+async fn calc_weight_uncached(dao: Arc<DAO>, ele: &ElementHandle, config: &WeightConfig) -> Result<f64> {
+    let tree = constructing_tree(dao.clone(), ele).await?;
+
+    let rate = config.blend_rate;
+    let mut weight = calc_weight_single(dao.clone(), tree.root().value(), config).await?;
+    let mut sub_weight = 1f64;
+    for x in tree.nodes() {
+        if x != tree.root() {
+            sub_weight += calc_weight_single(dao.clone(), x.value(), config).await?;
+        }
+    }
+    weight = rate * weight + (1.0 - rate) * (1.0/sub_weight);
+    Ok(weight)
+}
+
+/// The elements weighing more than `min_weight` by `calc_weight`, the heaviest first.
+pub async fn aspects_above_weight(dao: Arc<DAO>, min_weight: f64, config: &WeightConfig)
+    -> Result<Vec<(ElementHandle, f64)>> {
+        let config = &with_holdings_hash(dao.as_ref(), config).await?;
+        let mut ret = vec![];
+        for e in dao.list_elements().await.context(DatabaseSnafu)? {
+            let ele = ElementHandle::from(e.name);
+            let weight = calc_weight(dao.clone(), &ele, config).await?;
+            if weight > min_weight {
+                ret.push((ele, weight));
+            }
+        }
+        ret.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ret)
+}
+
+/// Calculate every element's weight into the `weight_cache` table, `concurrency` of them at a time.
+/// `progress` is told how many are done out of how many after each one.
+/// The weights are written once all are calculated, sorted by name, so they're the same as
+/// calculating them one by one. Returns how many weights are cached.
+pub async fn precompute_weights(dao: Arc<DAO>, config: &WeightConfig, concurrency: usize,
+    mut progress: impl FnMut(usize, usize))
+    -> Result<u64> {
+        let mut pending = dao.list_elements().await.context(DatabaseSnafu)?
+            .into_iter()
+            .map(|e| ElementHandle::from(e.name))
+            .collect::<Vec<_>>();
+        pending.sort();
+        pending.reverse();
+        let total = pending.len();
+        if config.decay.is_some() {
+            // nothing to cache, see `calc_weight`.
+            return Ok(0);
+        }
+        let alpha = config.map_to_value.alpha();
+        let hash = holdings_hash(dao.as_ref(), config).await?;
+        let config = Arc::new(config.clone());
+
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut weights = Vec::with_capacity(total);
+        while !pending.is_empty() || !tasks.is_empty() {
+            while tasks.len() < concurrency.max(1) && let Some(ele) = pending.pop() {
+                let (dao, config) = (dao.clone(), config.clone());
+                tasks.spawn(async move {
+                    let weight = calc_weight_uncached(dao, &ele, &config).await;
+                    weight.map(|w| (ele, w))
+                });
+            }
+            if let Some(done) = tasks.join_next().await {
+                weights.push(done.expect("A weight task panicked.")?);
+                progress(weights.len(), total);
+            }
+        }
+        weights.sort_by(|a, b| a.0.cmp(&b.0));
+        for (ele, weight) in &weights {
+            dao.cache_weight(ele, alpha, hash, *weight).await.context(DatabaseSnafu)?;
+        }
+        Ok(weights.len() as u64)
+}
+
+/// The sum of the intermediates' weights, the on board ones getting a bonus.
+/// A direct connection has no intermediate, it takes the average weight of its endpoints instead,
+/// so that the direct connections can be ranked too.
+pub async fn calc_weight_path(dao: Arc<DAO>, path: &Path, config: &WeightConfig) -> Result<f64> {
+    if path.path.is_empty() {
+        let start = calc_weight(dao.clone(), &path.start, config).await?;
+        let end = calc_weight(dao.clone(), &path.end, config).await?;
+        return Ok((start + end) / 2.0);
+    }
+    let mut accumulated = 0f64;
+    for x in &path.path {
+        accumulated += calc_weight(dao.clone(), x, config).await?;
+        if config.on_board.contains(x) {
+            accumulated += ON_BOARD_BONUS;
+        }
+    }
+    Ok(accumulated)
+}
+
+pub async fn calc_path_order_by_weight(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    config: &WeightConfig)
+    -> Result<Vec<Path>> {
+        calc_path_order_by_weight_filtered(dao, from, to, steps_n, &SearchFilter::default(), config).await
+}
+
+pub async fn calc_path_order_by_weight_filtered(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter, config: &WeightConfig)
+    -> Result<Vec<Path>> {
+        let mut pathes = calc_path_filtered(dao.clone(), from, to, steps_n, filter).await?;
+        cache_weights(dao, &mut pathes, config).await?;
+        sort_by_cached_weight(&mut pathes);
+        Ok(pathes)
+}
+
+/// Cache every path's `calc_weight_path`, `config.concurrency` of them calculated at a time.
+async fn cache_weights(dao: Arc<DAO>, pathes: &mut [Path], config: &WeightConfig) -> Result<()> {
+    let mut pending = (0..pathes.len()).rev().collect::<Vec<_>>();
+    let concurrency = config.concurrency;
+    let config = Arc::new(with_holdings_hash(dao.as_ref(), config).await?);
+
+    let mut tasks = tokio::task::JoinSet::new();
+    while !pending.is_empty() || !tasks.is_empty() {
+        while tasks.len() < concurrency && let Some(idx) = pending.pop() {
+            let (dao, config, path) = (dao.clone(), config.clone(), pathes[idx].clone());
+            tasks.spawn(async move {
+                let weight = calc_weight_path(dao, &path, &config).await;
+                weight.map(|w| (idx, w))
+            });
+        }
+        if let Some(done) = tasks.join_next().await {
+            let (idx, weight) = done.expect("A weight task panicked.")?;
+            pathes[idx].cached_weight = Some(weight);
+        }
+    }
+    Ok(())
+}
+
+/// The alphas `suggest_alpha` tries, in twentieths: 0.05 to 0.95.
+const ALPHA_GRID: std::ops::RangeInclusive<usize> = 1..=19;
+
+/// The alpha of the grid under which the best path from `from` to `to` outweighs the second
+/// best the most, and that gap. The smallest such alpha on a tie,
+/// `None` if there aren't two paths to tell apart.
+pub async fn suggest_alpha(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize)
+    -> Result<Option<(f64, f64)>> {
+        let pathes = calc_path(dao.clone(), from, to, steps_n).await?;
+        if pathes.len() < 2 {
+            return Ok(None);
+        }
+        let mut best: Option<(f64, f64)> = None;
+        for i in ALPHA_GRID {
+            let alpha = i as f64 * 0.05;
+            let config = WeightConfig::with_alpha(alpha).context(MathSnafu)?;
+            let config = with_holdings_hash(dao.as_ref(), &config).await?;
+            let mut weights = vec![];
+            for path in &pathes {
+                weights.push(calc_weight_path(dao.clone(), path, &config).await?);
+            }
+            weights.sort_by(|a, b| b.total_cmp(a));
+            let gap = weights[0] - weights[1];
+            if best.is_none_or(|(_, g)| gap > g) {
+                best = Some((alpha, gap));
+            }
+        }
+        Ok(best)
+}
+
+/// the highest weight first, every path must have its weight cached. The path names
+/// break the ties, so the order doesn't depend on the search order.
+fn sort_by_cached_weight(pathes: &mut [Path]) {
+        pathes.sort_by(|a, b| b.cached_weight.unwrap().total_cmp(&a.cached_weight.unwrap())
+            .then_with(|| (&a.start, &a.path, &a.end).cmp(&(&b.start, &b.path, &b.end))));
+}
+
+/// How many candidates more than asked `calc_path_order_by_weight_lazy` weights exactly.
+pub const LAZY_EXTRA_CANDIDATES: usize = 16;
+
+/// Same as `calc_path_order_by_weight_filtered` truncated to the best `limit` paths,
+/// but only the `limit + LAZY_EXTRA_CANDIDATES` paths whose intermediates sum the least base values
+/// get their weights calculated.
+///
+/// This is an approximation: the pre-ranking ignores the holdings and the components,
+/// so a path through rare but well stocked aspects may be cut before being weighted.
+pub async fn calc_path_order_by_weight_lazy(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter, config: &WeightConfig, limit: usize)
+    -> Result<Vec<Path>> {
+        let pathes = calc_path_filtered(dao.clone(), from, to, steps_n, filter).await?;
+
+        let mut base_values: HashMap<ElementHandle, f64> = HashMap::new();
+        let mut pre_ranked = Vec::with_capacity(pathes.len());
+        for path in pathes {
+            let mut sum = 0f64;
+            for x in &path.path {
+                if !base_values.contains_key(x) {
+                    let bv = dao.get_element_base_value(x).await.context(DatabaseSnafu)?;
+                    base_values.insert(x.clone(), bv);
+                }
+                sum += base_values[x];
+            }
+            pre_ranked.push((sum, path));
+        }
+        // the path names break the ties, so the candidates don't depend on the search order.
+        pre_ranked.sort_by(|a, b| a.0.total_cmp(&b.0)
+            .then_with(|| format!("{:?}", a.1).cmp(&format!("{:?}", b.1))));
+
+        let mut candidates = pre_ranked.into_iter()
+            .take(limit + LAZY_EXTRA_CANDIDATES)
+            .map(|a| a.1)
+            .collect::<Vec<_>>();
+        cache_weights(dao, &mut candidates, config).await?;
+        sort_by_cached_weight(&mut candidates);
+        candidates.truncate(limit);
+        Ok(candidates)
+}
+
+/// One of the paths from `from` to `to` of the fewest intermediates, at most `max_steps` of them.
+/// A breadth-first search, `None` if there's no such path.
+pub async fn calc_shortest_path(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, max_steps: usize)
+    -> Result<Option<Path>> {
+        if let Some(steps) = dao.get_cached_distance(from, to).await.context(DatabaseSnafu)? {
+            if steps > max_steps {
+                return Ok(None);
+            }
+            if let Some(path) = shortest_path_by_cache(dao.as_ref(), from, to, steps).await? {
+                return Ok(Some(path));
+            }
+        }
+        // each reached aspect and the one it's first reached from.
+        let mut parents: HashMap<ElementHandle, ElementHandle> = HashMap::new();
+        let mut level = vec![from.clone()];
+        for _ in 0..=max_steps {
+            let mut next_level = vec![];
+            for x in &level {
+                let mut relatives = get_relatives(dao.as_ref(), x).await?.into_iter().collect::<Vec<_>>();
+                // the first found path doesn't depend on the hash order.
+                relatives.sort();
+                for r in relatives {
+                    if r == *from || parents.contains_key(&r) {
+                        continue;
+                    }
+                    parents.insert(r.clone(), x.clone());
+                    if r == *to {
+                        let mut path = Path::new(from.clone(), to.clone());
+                        let mut steps = vec![];
+                        let mut cur = x;
+                        while cur != from {
+                            steps.push(cur.clone());
+                            cur = &parents[cur];
+                        }
+                        steps.into_iter().rev().for_each(|a| path.push(a));
+                        return Ok(Some(path));
+                    }
+                    next_level.push(r);
+                }
+            }
+            level = next_level;
+        }
+        Ok(None)
+}
+
+/// Walk from `from` to `to` in `steps` intermediates along the cached distances, stepping on
+/// the first relative by name that is one step closer each time. `None` if the cache is incomplete.
+async fn shortest_path_by_cache(dao: &DAO, from: &ElementHandle, to: &ElementHandle, steps: usize)
+    -> Result<Option<Path>> {
+        let mut path = Path::new(from.clone(), to.clone());
+        let mut cur = from.clone();
+        for remaining in (0..steps).rev() {
+            let mut relatives = get_relatives(dao, &cur).await?.into_iter().collect::<Vec<_>>();
+            relatives.sort();
+            let mut next = None;
+            for r in relatives {
+                if r != *to && dao.get_cached_distance(&r, to).await.context(DatabaseSnafu)? == Some(remaining) {
+                    next = Some(r);
+                    break;
+                }
+            }
+            let Some(next) = next else {
+                return Ok(None);
+            };
+            path.push(next.clone());
+            cur = next;
+        }
+        Ok(Some(path))
+}
+
+/// The fewest intermediates connecting `from` to `to` and all the paths of them, trying
+/// `calc_path` from 0 up to `max_steps` intermediates. `None` if there are more.
+pub async fn calc_path_min_steps(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, max_steps: usize)
+    -> Result<Option<(usize, Vec<Path>)>> {
+        if is_two_eles_connected(dao.as_ref(), from, to).await? {
+            return Ok(Some((0, vec![Path::new(from.clone(), to.clone())])));
+        }
+        let graph = RecipeGraph::load(dao.as_ref()).await?;
+        for steps_n in 1..=max_steps {
+            let pathes = calc_path_in(&graph, from, to, steps_n, &SearchFilter::default());
+            if !pathes.is_empty() {
+                return Ok(Some((steps_n, pathes)));
+            }
+        }
+        Ok(None)
+}
+
+/// Cache the fewest intermediates between every two connected elements, for `calc_shortest_path`.
+/// Returns how many distances are stored.
+pub async fn precompute_distances(dao: Arc<DAO>, adjacency: &Adjacency) -> Result<u64> {
+    let (eles, dist) = all_pairs_links(adjacency);
+    let mut distances = vec![];
+    for (u, row) in dist.iter().enumerate() {
+        for (v, links) in row.iter().enumerate() {
+            if u != v && *links != usize::MAX {
+                distances.push((eles[u].clone(), eles[v].clone(), links - 1));
+            }
+        }
+    }
+    dao.store_distances(&distances).await.context(DatabaseSnafu)
+}
+
+/// The cost of stepping on `ele`, the inverse of its weight: well weighted aspects are cheap.
+/// `calc_weight` is always positive, so is the cost.
+pub async fn calc_step_cost(dao: Arc<DAO>, ele: &ElementHandle, config: &WeightConfig) -> Result<f64> {
+    Ok(1.0 / calc_weight(dao, ele, config).await?)
+}
+
+/// The sum of the intermediates' step costs, see `best_weighted_connection`.
+pub async fn calc_cost_path(dao: Arc<DAO>, path: &Path, config: &WeightConfig) -> Result<f64> {
+    let mut cost = 0f64;
+    for x in &path.path {
+        cost += calc_step_cost(dao.clone(), x, config).await?;
+    }
+    Ok(cost)
+}
+
+/// The path of the least `calc_cost_path` from `from` to `to` with at most `max_steps` intermediates,
+/// whatever its length. A uniform-cost search over (aspect, intermediates taken), instead of
+/// enumerating every path of each length. `None` if they can't be connected in `max_steps`.
+pub async fn best_weighted_connection(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, max_steps: usize,
+    config: &WeightConfig)
+    -> Result<Option<Path>> {
+        type State = (ElementHandle, usize);
+        let mut step_costs: HashMap<ElementHandle, f64> = HashMap::new();
+        // the least cost found to each state, and the state it's reached from.
+        let mut best: HashMap<State, (f64, Option<State>)> = HashMap::new();
+        let mut settled: HashSet<State> = HashSet::new();
+        let mut frontier: Vec<State> = vec![(from.clone(), 0)];
+        best.insert((from.clone(), 0), (0., None));
+
+        let mut reached = None;
+        while let Some(idx) = frontier.iter()
+            .enumerate()
+            .min_by(|a, b| best[a.1].0.total_cmp(&best[b.1].0))
+            .map(|a| a.0) {
+                let state = frontier.swap_remove(idx);
+                if !settled.insert(state.clone()) {
+                    continue;
+                }
+                if state.0 == *to {
+                    reached = Some(state);
+                    break;
+                }
+                let cost = best[&state].0;
+                for next in get_relatives(dao.as_ref(), &state.0).await? {
+                    let (next_state, next_cost) = if next == *to {
+                        ((next, state.1), cost)
+                    } else if next == *from || state.1 == max_steps {
+                        continue;
+                    } else {
+                        if !step_costs.contains_key(&next) {
+                            let c = calc_step_cost(dao.clone(), &next, config).await?;
+                            step_costs.insert(next.clone(), c);
+                        }
+                        let c = cost + step_costs[&next];
+                        ((next, state.1 + 1), c)
+                    };
+                    if best.get(&next_state).is_none_or(|a| next_cost < a.0) {
+                        best.insert(next_state.clone(), (next_cost, Some(state.clone())));
+                        frontier.push(next_state);
+                    }
+                }
+        }
+
+        let Some(mut state) = reached else {
+            return Ok(None);
+        };
+        let mut steps = vec![];
+        while let Some(prev) = best[&state].1.clone() {
+            if prev.0 != *from {
+                steps.push(prev.0.clone());
+            }
+            state = prev;
+        }
+        let mut path = Path::new(from.clone(), to.clone());
+        steps.into_iter().rev().for_each(|a| path.push(a));
+        path.cached_weight = Some(calc_weight_path(dao.clone(), &path, config).await?);
+        Ok(Some(path))
+}
+
+/// The best weighted path of each length in `lengths` between every two primary elements,
+/// grouped by the starting one, both sorted by name. A pair or length without a path is left out.
+pub async fn all_primal_paths(dao: Arc<DAO>, lengths: &[usize], config: &WeightConfig)
+    -> Result<Vec<(ElementHandle, Vec<Path>)>> {
+    let mut primals = dao.get_primary_elements().await.context(DatabaseSnafu)?;
+    primals.sort();
+    let mut ret = vec![];
+    for from in &primals {
+        let mut best = vec![];
+        for to in primals.iter().filter(|a| *a != from) {
+            for steps_n in lengths {
+                let pathes = calc_path_order_by_weight(dao.clone(), from, to, *steps_n, config).await?;
+                best.extend(pathes.into_iter().next());
+            }
+        }
+        ret.push((from.clone(), best));
+    }
+    Ok(ret)
+}
+
+/// How many times each intermediate is on the best weighted path of `steps_n` intermediates
+/// connecting each of `pairs`, all the pairs of primary elements if `None`. The most frequent first,
+/// an aspect counted once per path however many times it's stepped on.
+pub async fn pivot_aspects(dao: Arc<DAO>, pairs: Option<&[(ElementHandle, ElementHandle)]>, steps_n: usize,
+    config: &WeightConfig)
+    -> Result<Vec<(ElementHandle, usize)>> {
+        let pairs = match pairs {
+            Some(pairs) => pairs.to_vec(),
+            None => {
+                let mut primals = dao.get_primary_elements().await.context(DatabaseSnafu)?;
+                primals.sort();
+                let mut pairs = vec![];
+                for (i, a) in primals.iter().enumerate() {
+                    for b in &primals[i + 1..] {
+                        pairs.push((a.clone(), b.clone()));
+                    }
+                }
+                pairs
+            },
+        };
+        let mut counts: HashMap<ElementHandle, usize> = HashMap::new();
+        for (a, b) in &pairs {
+            if let Some(best) = calc_path_order_by_weight(dao.clone(), a, b, steps_n, config).await?.first() {
+                for x in best.steps().iter().collect::<HashSet<_>>() {
+                    *counts.entry(x.clone()).or_default() += 1;
+                }
+            }
+        }
+        let mut ret = counts.into_iter().collect::<Vec<_>>();
+        ret.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ret)
+}
+
+/// A path's ranks in two rankings of the same pathes, see `rank_changes`.
+#[derive(Debug, PartialEq)]
+pub struct RankChange {
+    pub path: Path,
+    /// 1 is the top.
+    pub before: usize,
+    pub after: usize,
+}
+
+impl RankChange {
+    /// positive if the path moved up.
+    pub fn delta(&self) -> isize {
+        self.before as isize - self.after as isize
+    }
+}
+
+/// The ranks of every path of `before` in both rankings, in `after`'s order.
+/// A path missing in one of them is left out.
+pub fn rank_changes(before: &[Path], after: &[Path]) -> Vec<RankChange> {
+    let before_ranks = before.iter()
+        .enumerate()
+        .map(|(i, p)| (p, i + 1))
+        .collect::<HashMap<_, _>>();
+    after.iter()
+        .enumerate()
+        .filter_map(|(i, p)| before_ranks.get(p).map(|b| RankChange {
+            path: p.clone(),
+            before: *b,
+            after: i + 1,
+        }))
+        .collect()
+}
+
+/// Look for a path of the same endpoints and length that weights more than `path`.
+/// Returns `None` if `path` is already the top result, otherwise the best one found.
+/// Both `path`'s and the returned path's weights are cached in them.
+pub async fn find_better_path(dao: Arc<DAO>, path: &mut Path, config: &WeightConfig) -> Result<Option<Path>> {
+    let weight = calc_weight_path(dao.clone(), path, config).await?;
+    path.cached_weight = Some(weight);
+
+    let pathes = calc_path_order_by_weight(dao.clone(), &path.start, &path.end, path.path.len(), config).await?;
+    match pathes.into_iter().next() {
+        Some(best) if best.cached_weight.unwrap() > weight => Ok(Some(best)),
+        _ => Ok(None),
+    }
+}
+
+/// Split the search at the pinned aspects of `filter`, search between every two consecutive of them,
+/// then concatenate the sub-pathes. Every way to share the steps among the segments is tried.
+fn calc_path_through(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter)
+    -> Vec<Path> {
+        let pins = &filter.through;
+        if pins.len() > steps_n {
+            return vec![];
+        }
+
+        let mut stops = vec![from.clone()];
+        stops.extend(pins.iter().cloned());
+        stops.push(to.clone());
+        let segments_n = stops.len() - 1;
+
+        // the steps of each segment, like an odometer over all the compositions of the free steps.
+        let free_steps = steps_n - pins.len();
+        let mut shares = vec![0usize; segments_n];
+        let mut segment_pathes: HashMap<(usize, usize), Vec<Path>> = HashMap::new();
+        let mut ret = HashSet::new();
+        loop {
+            if shares.iter().sum::<usize>() == free_steps {
+                let mut combined: Vec<Vec<ElementHandle>> = vec![vec![]];
+                for (i, share) in shares.iter().enumerate() {
+                    if let Entry::Vacant(e) = segment_pathes.entry((i, *share)) {
+                        e.insert(calc_path_unpinned(graph, &stops[i], &stops[i + 1], *share, filter));
+                    }
+                    let pathes = &segment_pathes[&(i, *share)];
+                    let mut new_combined = vec![];
+                    for prefix in &combined {
+                        for p in pathes {
+                            let mut v = prefix.clone();
+                            if i != 0 {
+                                v.push(stops[i].clone());
+                            }
+                            v.extend(p.path.iter().cloned());
+                            new_combined.push(v);
+                        }
+                    }
+                    combined = new_combined;
+                    if combined.is_empty() {
+                        break;
+                    }
+                }
+                for inner in combined {
+                    let mut p = Path::new(from.clone(), to.clone());
+                    inner.into_iter().for_each(|a| p.push(a));
+                    ret.insert(p);
+                }
+            }
+
+            let mut idx = 0;
+            loop {
+                if idx == segments_n {
+                    return ret.into_iter().collect();
+                }
+                if shares[idx] < free_steps {
+                    shares[idx] += 1;
+                    break;
+                }
+                shares[idx] = 0;
+                idx += 1;
+            }
+        }
+}
+
+/// The connectors to place and the links between them found by `minimal_connector_tree`.
+#[derive(Debug, Default)]
+pub struct ConnectorTree {
+    pub connectors: Vec<ElementHandle>,
+    pub edges: Vec<(ElementHandle, ElementHandle)>,
+    /// the paths the edges were taken from, in the order they joined the tree.
+    pub paths: Vec<Path>,
+    /// the targets that can't be linked to the others within `max_steps`.
+    pub unreached: Vec<ElementHandle>,
+}
+
+/// The shortest path within `max_steps` steps, the highest weight one of them if there are many.
+async fn cheapest_path(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, max_steps: usize,
+    config: &WeightConfig)
+    -> Result<Option<Path>> {
+        for steps_n in 0..=max_steps {
+            let pathes = calc_path_order_by_weight(dao.clone(), from, to, steps_n, config).await?;
+            if let Some(p) = pathes.into_iter().next() {
+                return Ok(Some(p));
+            }
+        }
+        Ok(None)
+}
+
+/// Find a small set of connector aspects linking all the `targets` together,
+/// an approximation of the minimal Steiner tree over the recipe graph.
+///
+/// Greedy: starting from the first target, repeatedly link the remaining target which costs
+/// the fewest intermediates (then the highest weight) to any aspect already in the tree.
+pub async fn minimal_connector_tree(dao: Arc<DAO>, targets: &[ElementHandle], max_steps: usize,
+    config: &WeightConfig)
+    -> Result<ConnectorTree> {
+        let mut ret = ConnectorTree::default();
+        let mut remaining = Vec::new();
+        for t in targets {
+            if !remaining.contains(t) {
+                remaining.push(t.clone());
+            }
+        }
+        if remaining.is_empty() {
+            return Ok(ret);
+        }
+        let mut tree_nodes = vec![remaining.remove(0)];
+
+        while !remaining.is_empty() {
+            let mut best: Option<Path> = None;
+            for t in &remaining {
+                for n in &tree_nodes {
+                    let bound = best.as_ref().map_or(max_steps, |b| b.path.len());
+                    if let Some(p) = cheapest_path(dao.clone(), n, t, bound, config).await? {
+                        let better = match &best {
+                            None => true,
+                            Some(b) => p.path.len() < b.path.len()
+                                || (p.path.len() == b.path.len()
+                                    && p.cached_weight.unwrap() > b.cached_weight.unwrap()),
+                        };
+                        if better {
+                            best = Some(p);
+                        }
+                    }
+                }
+            }
+
+            let Some(best) = best else {
+                ret.unreached = remaining;
+                break;
+            };
+            let mut last = best.start.clone();
+            for x in best.path.iter().chain(std::iter::once(&best.end)) {
+                ret.edges.push((last, x.clone()));
+                if !tree_nodes.contains(x) {
+                    if *x != best.end {
+                        ret.connectors.push(x.clone());
+                    }
+                    tree_nodes.push(x.clone());
+                }
+                last = x.clone();
+            }
+            remaining.retain(|a| *a != best.end);
+            ret.paths.push(best);
+        }
+        Ok(ret)
+}
+
+/// A layout of the research board linking all the targets, see `plan_board`.
+#[derive(Debug, Default)]
+pub struct BoardPlan {
+    /// the connections to lay, each from an aspect already on the board.
+    pub connections: Vec<Path>,
+    /// every aspect to place which isn't a target, each once, sorted.
+    pub connectors: Vec<ElementHandle>,
+    /// the targets that can't be linked to the others within `max_steps`.
+    pub unreached: Vec<ElementHandle>,
+}
+
+impl BoardPlan {
+    fn from_connections(connections: Vec<Path>, targets: &[ElementHandle]) -> Self {
+        let mut connectors = connections.iter()
+            .flat_map(|p| std::iter::once(&p.start).chain(&p.path).chain(std::iter::once(&p.end)))
+            .filter(|a| !targets.contains(a))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        connectors.sort();
+        Self { connections, connectors, unreached: vec![] }
+    }
+}
+
+/// Plan the board linking all the `targets`, with the fewest connectors of two heuristics:
+/// the greedy tree of `minimal_connector_tree`, and a single hub aspect every target is linked
+/// to by its shortest best weighted path. The tree is kept on a tie.
+pub async fn plan_board(dao: Arc<DAO>, targets: &[ElementHandle], max_steps: usize, config: &WeightConfig)
+    -> Result<BoardPlan> {
+    let tree = minimal_connector_tree(dao.clone(), targets, max_steps, config).await?;
+    let mut plan = BoardPlan::from_connections(tree.paths, targets);
+    plan.unreached = tree.unreached;
+
+    let mut links = vec![];
+    for t in targets {
+        links.push(links_from(dao.as_ref(), t).await?);
+    }
+    let mut hubs = dao.list_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|e| ElementHandle::from(e.name))
+        .filter(|h| links.iter().all(|l| l.get(h).is_some_and(|n| *n <= max_steps + 1)))
+        .collect::<Vec<_>>();
+    hubs.sort();
+    for hub in hubs {
+        let mut connections = vec![];
+        for (t, l) in targets.iter().zip(&links) {
+            if *t == hub || connections.iter().any(|p: &Path| p.end == *t) {
+                continue;
+            }
+            let pathes = calc_path_order_by_weight(dao.clone(), &hub, t, l[&hub] - 1, config).await?;
+            connections.extend(pathes.into_iter().next());
+        }
+        let candidate = BoardPlan::from_connections(connections, targets);
+        if !plan.unreached.is_empty() || candidate.connectors.len() < plan.connectors.len() {
+            plan = candidate;
+        }
+    }
+    Ok(plan)
+}
+
+/// The mod whose aspects are the base every other mod integrates with.
+pub const BASE_MOD: &str = "Thaumcraft";
+
+/// Where the elements without a mod are reported.
+pub const UNKNOWN_MOD: &str = "<unknown>";
+
+/// How many aspects of a mod connect to the base aspects, see `mod_integration_report`.
+#[derive(Debug, PartialEq)]
+pub struct ModIntegration {
+    pub mod_name: String,
+    pub total: usize,
+    pub connected: usize,
+}
+
+impl ModIntegration {
+    pub fn fraction(&self) -> f64 {
+        self.connected as f64 / self.total as f64
+    }
+
+    /// no aspect of the mod connects to the base.
+    pub fn is_isolated(&self) -> bool {
+        self.connected == 0
+    }
+}
+
+/// whether any of `targets` is within `hops` links of `ele`.
+async fn reaches_any(dao: &DAO, ele: &ElementHandle, hops: usize, targets: &HashSet<ElementHandle>)
+    -> Result<bool> {
+        let mut visited = HashSet::from([ele.clone()]);
+        let mut frontier = vec![ele.clone()];
+        for _ in 0..hops {
+            let mut new_frontier = vec![];
+            for x in &frontier {
+                for r in get_relatives(dao, x).await? {
+                    if targets.contains(&r) {
+                        return Ok(true);
+                    }
+                    if visited.insert(r.clone()) {
+                        new_frontier.push(r);
+                    }
+                }
+            }
+            frontier = new_frontier;
+        }
+        Ok(false)
+}
+
+/// For each mod except `BASE_MOD`, count its aspects connecting to a base aspect within `steps_n` steps.
+/// The elements without a mod are reported as `UNKNOWN_MOD`.
+pub async fn mod_integration_report(dao: Arc<DAO>, steps_n: usize) -> Result<Vec<ModIntegration>> {
+    let elements = dao.list_elements().await.context(DatabaseSnafu)?;
+    let base = elements.iter()
+        .filter(|e| e.belongs_to_mod.as_deref() == Some(BASE_MOD))
+        .map(|e| ElementHandle::from(e.name.clone()))
+        .collect::<HashSet<_>>();
+
+    let mut report: Vec<ModIntegration> = vec![];
+    for e in &elements {
+        let mod_name = e.belongs_to_mod.clone().unwrap_or(UNKNOWN_MOD.to_string());
+        if mod_name == BASE_MOD {
+            continue;
+        }
+        let connected = reaches_any(dao.as_ref(), &ElementHandle::from(e.name.clone()), steps_n + 1, &base).await?;
+        let idx = match report.iter().position(|a| a.mod_name == mod_name) {
+            Some(idx) => idx,
+            None => {
+                report.push(ModIntegration { mod_name, total: 0, connected: 0 });
+                report.len() - 1
+            }
+        };
+        report[idx].total += 1;
+        if connected {
+            report[idx].connected += 1;
+        }
+    }
+    report.sort_by(|a, b| a.mod_name.cmp(&b.mod_name));
+    Ok(report)
+}
+
+pub async fn calc_path(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize)
+    -> Result<Vec<Path>> {
+        calc_path_filtered(dao, from, to, steps_n, &SearchFilter::default()).await
+}
+
+/// Same as `calc_path`, but only steps on the intermediates `filter` allows.
+pub async fn calc_path_filtered(dao: Arc<DAO>, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter)
+    -> Result<Vec<Path>> {
+        let graph = RecipeGraph::load(dao.as_ref()).await?;
+        Ok(calc_path_in(&graph, from, to, steps_n, filter))
+}
+
+/// Same as `calc_path_filtered`, over the recipes already in `graph`.
+pub fn calc_path_in(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter)
+    -> Vec<Path> {
+        // relatives are symmetric, so a search starting in `to`'s component never leaves it,
+        // and one starting outside can't reach `to`: don't wander through that subgraph for nothing.
+        if !graph.are_in_same_component(from, to) {
+            return vec![];
+        }
+        if filter.through.is_empty() {
+            calc_path_unpinned(graph, from, to, steps_n, filter)
+        } else {
+            calc_path_through(graph, from, to, steps_n, filter)
+        }
+}
+
+fn calc_path_unpinned(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter)
+    -> Vec<Path> {
+        if steps_n == 0 {
+            if graph.relatives(from).contains(to) {
+                vec![Path::new(from.clone(), to.clone())]
+            } else {
+                vec![]
+            }
+        } else if steps_n == 1 {
+            calc_path_steps_1(graph, from, to, filter)
+        } else if steps_n == 2 {
+            calc_path_steps_2(graph, from, to, filter)
+        } else {
+            calc_path_bidirectional(graph, from, to, steps_n, filter)
+        }
+    }
+
+/// The walks reaching each aspect of a search frontier, their intermediates in stepping order.
+type Frontier = std::collections::BTreeMap<ElementHandle, Vec<Vec<ElementHandle>>>;
+
+/// Step every walk of `frontier` on each allowed relative of the aspect it reached, at `depth` links
+/// from the start.
+fn step_frontier(graph: &RecipeGraph, filter: &SearchFilter, depth: usize, frontier: Frontier) -> Frontier {
+        let mut next = Frontier::new();
+        for (node, walks) in frontier {
+            let children = filter.expand(depth, &node, graph.relatives(&node));
+            if children.is_empty() {
+                filter.record(|| SearchEvent::Prune { depth, node: node.get_name(), reason: "dead end" });
+            }
+            for child in children {
+                next.entry(child.clone()).or_default()
+                    .extend(walks.iter().map(|w| {
+                        let mut w = w.clone();
+                        w.push(child.clone());
+                        w
+                    }));
+            }
+        }
+        next
+}
+
+/// Expand the walks from `from` and the ones from `to`, meeting on the intermediate
+/// `steps_n / 2` steps away from `to`. Gives every walk the depth first search would, in its order.
+fn calc_path_bidirectional(graph: &RecipeGraph, from: &ElementHandle, to: &ElementHandle, steps_n: usize,
+    filter: &SearchFilter)
+    -> Vec<Path> {
+        let back_n = steps_n / 2;
+        let meet = steps_n - back_n + 1;
+
+        let mut forth = Frontier::from([(from.clone(), vec![vec![]])]);
+        for depth in 0..meet {
+            forth = step_frontier(graph, filter, depth, forth);
+        }
+        let mut back = Frontier::from([(to.clone(), vec![vec![]])]);
+        for depth in (meet + 1..=steps_n + 1).rev() {
+            back = step_frontier(graph, filter, depth, back);
+        }
+
+        let mut result_pathes = Vec::new();
+        for (node, forth_walks) in forth {
+            let Some(back_walks) = back.get(&node) else {
+                filter.record(|| SearchEvent::Prune {
+                    depth: meet, node: node.get_name(), reason: "not reachable from the end" });
+                continue;
+            };
+            for f in &forth_walks {
+                for b in back_walks {
+                    let mut dest_path = Path::new(from.clone(), to.clone());
+                    // the met aspect ends both walks.
+                    f.iter().chain(b.iter().rev().skip(1)).for_each(|a| dest_path.push(a.clone()));
+                    result_pathes.push(dest_path);
+                }
+            }
+        }
+        result_pathes.sort_by(|a, b| a.path.cmp(&b.path));
+        for p in &result_pathes {
+            filter.record_found(p);
+        }
+        result_pathes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{dao::DAO, pathes::calc_path_order_by_weight, recipes::ElementHandle};
+
+    use super::calc_path;
+
+    use std::sync::{Arc, LazyLock};
+
+    static INIT_SQLX_DRIVERS: LazyLock<()> = LazyLock::new(|| {
+        sqlx::any::install_default_drivers();
+    });
+
+    #[tokio::test]
     async fn test_calc_path1() {
         let _ = &*INIT_SQLX_DRIVERS;
 
@@ -427,8 +2691,8 @@ mod tests {
                 &ElementHandle::from("Ignis"), 1).await.expect("1");
             // under 4.2.3.5 database
             assert_eq!(pathes.len(), 1usize);
-            let p = pathes.get(0).unwrap();
-            assert_eq!(p.path.get(0).unwrap().get_name(), "Lux")
+            let p = pathes.first().unwrap();
+            assert_eq!(p.path.first().unwrap().get_name(), "Lux")
         }
         {
             let pathes = calc_path(dao.clone(),
@@ -436,114 +2700,1699 @@ mod tests {
                 &ElementHandle::from("Ignis"), 1).await.expect("1");
             // under 4.2.3.5 database
             assert_eq!(pathes.len(), 1usize);
-            let p = pathes.get(0).unwrap();
-            assert_eq!(p.path.get(0).unwrap().get_name(), "Telum")
+            let p = pathes.first().unwrap();
+            assert_eq!(p.path.first().unwrap().get_name(), "Telum")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path2() {
+        let _ = &*INIT_SQLX_DRIVERS;
+
+        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
+        {
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Aer"),
+            &ElementHandle::from("Ignis"),
+            2).await.expect("1");
+            assert_eq!(pathes.len(), 0);
+        }
+        {
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Humanus"),
+            &ElementHandle::from("Ignis"),
+            2).await.expect("1");
+            assert_eq!(format!("{pathes:?}"),
+                "[Humanus->Instrumentum->Telum->Ignis]");
+            // under 4.2.3.5 database
+            /*
+            assert_eq!(pathes.len(), 1usize);
+            let p = pathes.first().unwrap();
+            assert_eq!(p.path.first().unwrap().get_name(), "Lux")
+            */
+        }
+        {
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Machina"),
+            &ElementHandle::from("Cognitio"),
+            2).await.expect("1");
+            assert_eq!(format!("{pathes:?}"), "[Machina->Instrumentum->Humanus->Cognitio]");
+        }
+        {
+            use std::collections::HashSet;
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Bestia"),
+            &ElementHandle::from("Spiritus"),
+            2).await.expect("1");
+            let pathes_strs = pathes.iter()
+                .map(|a| format!("{a:?}"))
+                .collect::<HashSet<_>>();
+            let right_strs =
+                "Bestia->Humanus->Cognitio->Spiritus, Bestia->Victus->Mortuus->Spiritus, Bestia->Corpus->Mortuus->Spiritus"
+    .split(", ")
+    .map(|a| a.to_string())
+    .collect::<HashSet<_>>();
+            let res = &pathes_strs - &right_strs;
+            assert!(res.is_empty(), "{pathes_strs:?}\n - \n{right_strs:?}\n = \n {res:?}");
+        }
+    }
+
+    use super::is_path_viable;
+    #[tokio::test]
+    async fn test_calc_path3() {
+        let _ = &*INIT_SQLX_DRIVERS;
+        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
+        {
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Motus"),
+            &ElementHandle::from("Mortuus"),
+            3).await.expect("1");
+            for x in &pathes {
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
+        }
+        {
+            let pathes = calc_path(dao.clone(),
+            &ElementHandle::from("Perditio"),
+            &ElementHandle::from("Motus"),
+            3)
+                .await.expect("1");
+            // println!("finds {} ways: {pathes:?}", pathes.len(), );
+            for x in &pathes {
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path3_with_weight() {
+        let _ = &*INIT_SQLX_DRIVERS;
+        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
+        {
+            let pathes = calc_path_order_by_weight(dao.clone(),
+            &ElementHandle::from("Motus"),
+            &ElementHandle::from("Mortuus"),
+            3, &WeightConfig::default()).await.expect("1");
+            println!("finds {} ways: {pathes:?}", pathes.len(), );
+            for x in &pathes {
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
+        }
+        {
+            let pathes = calc_path_order_by_weight(dao.clone(),
+            &ElementHandle::from("Perditio"),
+            &ElementHandle::from("Motus"),
+            3, &WeightConfig::default())
+                .await.expect("1");
+            println!("finds {} ways: {pathes:?}", pathes.len(), );
+            for x in &pathes {
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_with_weight_ties() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        // every holding is infinite, the paths of the same base values tie and go by name.
+        for (from, to, steps_n, expected) in [
+            ("Aer", "Tenebrae", 1, vec![vec!["Lux"], vec!["Vacuos"]]),
+            ("Aer", "Ordo", 2, vec![vec!["Vacuos", "Tempus"], vec!["Volatus", "Motus"]]),
+        ] {
+            let pathes = calc_path_order_by_weight(dao.clone(), &h(from), &h(to), steps_n, &WeightConfig::default()).await.expect("1");
+            assert_eq!(pathes[0].cached_weight, pathes[1].cached_weight);
+            assert_eq!(pathes[..2].iter()
+                .map(|a| a.path.iter().map(|e| e.get_name()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(), expected);
+            let again = calc_path_order_by_weight(dao.clone(), &h(from), &h(to), steps_n, &WeightConfig::default()).await.expect("2");
+            assert_eq!(format!("{again:?}"), format!("{pathes:?}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_with_weight_concurrently() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let (from, to) = (ElementHandle::from("Motus"), ElementHandle::from("Mortuus"));
+        let mut config = WeightConfig::default();
+        config.holdings.insert(ElementHandle::from("Bestia"), 10.);
+        let mut ranked = vec![];
+        for n in [1, 8] {
+            config.set_concurrency(n);
+            let pathes = calc_path_order_by_weight_filtered(dao.clone(), &from, &to, 3,
+                &SearchFilter::default(), &config).await.expect("1");
+            assert!(pathes.len() > 8, "{pathes:?}");
+            ranked.push(format!("{pathes:?}"));
+        }
+        assert_eq!(ranked[0], ranked[1]);
+    }
+
+    use super::{find_better_path, Path};
+    #[tokio::test]
+    async fn test_find_better_path() {
+        let _ = &*INIT_SQLX_DRIVERS;
+        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
+        {
+            let mut path = Path::new(ElementHandle::from("Bestia"), ElementHandle::from("Spiritus"));
+            path.push(ElementHandle::from("Humanus"));
+            path.push(ElementHandle::from("Cognitio"));
+            assert!(is_path_viable(dao.as_ref(), &path).await.expect("bigger problem"));
+
+            let best = find_better_path(dao.clone(), &mut path, &WeightConfig::default()).await.expect("1")
+                .expect("Humanus->Cognitio isn't the best way.");
+            assert_eq!(format!("{:?}", Path { cached_weight: None, ..best }),
+                "Bestia->Victus->Mortuus->Spiritus");
+        }
+        {
+            let mut path = Path::new(ElementHandle::from("Bestia"), ElementHandle::from("Spiritus"));
+            path.push(ElementHandle::from("Victus"));
+            path.push(ElementHandle::from("Mortuus"));
+            assert!(find_better_path(dao.clone(), &mut path, &WeightConfig::default()).await.expect("1").is_none());
+        }
+    }
+
+    use super::{calc_path_filtered, SearchFilter};
+    #[tokio::test]
+    async fn test_calc_path_exclude_tag() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        dao.add_tag(&ElementHandle::from("Mortuus"), "ignore").await.expect("1");
+        dao.add_tag(&ElementHandle::from("Lux"), "ignore").await.expect("1");
+
+        let mut filter = SearchFilter::default();
+        filter.exclude(dao.list_by_tag("ignore").await.expect("1"));
+        {
+            let pathes = calc_path_filtered(dao.clone(),
+            &ElementHandle::from("Aer"),
+            &ElementHandle::from("Ignis"),
+            1, &filter).await.expect("1");
+            assert!(pathes.is_empty(), "{pathes:?}");
+        }
+        {
+            let pathes = calc_path_filtered(dao.clone(),
+            &ElementHandle::from("Bestia"),
+            &ElementHandle::from("Spiritus"),
+            2, &filter).await.expect("1");
+            assert_eq!(format!("{pathes:?}"), "[Bestia->Humanus->Cognitio->Spiritus]");
+        }
+        {
+            let pathes = calc_path_filtered(dao.clone(),
+            &ElementHandle::from("Motus"),
+            &ElementHandle::from("Mortuus"),
+            3, &filter).await.expect("1");
+            assert!(!pathes.is_empty());
+            for x in &pathes {
+                assert!(!x.path.contains(&ElementHandle::from("Mortuus")), "{x:?}");
+                assert!(!x.path.contains(&ElementHandle::from("Lux")), "{x:?}");
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
+        }
+    }
+
+    use super::minimal_connector_tree;
+    #[tokio::test]
+    async fn test_minimal_connector_tree() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let targets = ["Aer", "Aqua", "Victus"].map(ElementHandle::from);
+        let tree = minimal_connector_tree(dao.clone(), &targets, 3, &WeightConfig::default()).await.expect("1");
+        // Tempestas = Aer + Aqua, Victus = Aqua + Terra
+        assert_eq!(tree.connectors, vec![ElementHandle::from("Tempestas")]);
+        assert_eq!(tree.edges, vec![
+            (ElementHandle::from("Aer"), ElementHandle::from("Tempestas")),
+            (ElementHandle::from("Tempestas"), ElementHandle::from("Aqua")),
+            (ElementHandle::from("Aqua"), ElementHandle::from("Victus")),
+        ]);
+        assert!(tree.unreached.is_empty());
+    }
+
+    use super::plan_board;
+    #[tokio::test]
+    async fn test_plan_board() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        // Lux = Aer + Ignis, Tenebrae = Vacuos + Lux: both connections share Lux.
+        let targets = ["Aer", "Ignis", "Tenebrae"].map(h);
+        let plan = plan_board(dao.clone(), &targets, 3, &WeightConfig::default()).await.expect("1");
+        assert_eq!(plan.connectors, vec![h("Lux")]);
+        assert!(plan.unreached.is_empty());
+        for p in &plan.connections {
+            assert!(is_path_viable(dao.as_ref(), p).await.expect("2"), "{p:?}");
+        }
+        for t in &targets {
+            assert!(plan.connections.iter().any(|p| p.start() == t || p.end() == t), "{t:?}");
+        }
+
+        // no two targets linked at all.
+        let single = plan_board(dao.clone(), &[h("Aer")], 3, &WeightConfig::default()).await.expect("3");
+        assert!(single.connections.is_empty() && single.connectors.is_empty());
+    }
+
+    use super::parse_crack_args;
+    #[tokio::test]
+    async fn test_parse_crack_args() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        let h = ElementHandle::from;
+        let args = |a: &[&str]| a.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let parsed = parse_crack_args(&dao, &args(&["Sano", "aer", "48", "Ira", "11", "Aer"])).await.expect("1");
+        assert_eq!(parsed, [(h("Sano"), 1), (h("Aer"), 49), (h("Ira"), 11)].into_iter().collect());
+
+        assert!(matches!(parse_crack_args(&dao, &[]).await, Err(crate::errors::T4ACHError::ParsingAspects { .. })));
+        assert!(matches!(parse_crack_args(&dao, &args(&["48", "Aer"])).await,
+            Err(crate::errors::T4ACHError::ParsingAspects { .. })));
+        assert!(matches!(parse_crack_args(&dao, &args(&["Aer", "4", "8"])).await,
+            Err(crate::errors::T4ACHError::ParsingAspects { .. })));
+        match parse_crack_args(&dao, &args(&["Aer", "Ingis"])).await {
+            Err(crate::errors::T4ACHError::ElementNotFound { element_name, .. }) => assert_eq!(element_name, "Ingis"),
+            _ => panic!("Ingis was found"),
+        }
+    }
+
+    use super::get_relatives;
+    #[tokio::test]
+    async fn test_relatives_cache() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        let cache = dao.relatives_cache();
+        let lux = ElementHandle::from("Lux");
+        let first = get_relatives(&dao, &lux).await.expect("1");
+        assert_eq!((cache.hits(), cache.misses(), cache.size().await), (0, 1, 1));
+        let second = get_relatives(&dao, &lux).await.expect("1");
+        assert_eq!((cache.hits(), cache.misses(), cache.size().await), (1, 1, 1));
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_recipe_graph() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        let graph = RecipeGraph::load(&dao).await.expect("1");
+        for e in dao.list_elements().await.expect("2") {
+            let ele = ElementHandle::from(e.name);
+            assert_eq!(graph.relatives(&ele), &get_relatives(&dao, &ele).await.expect("3"), "{ele:?}");
+        }
+        assert!(graph.relatives(&ElementHandle::from("Nope")).is_empty());
+    }
+
+    use super::{get_relatives_cached, RelativesCache};
+    #[tokio::test]
+    async fn test_get_relatives_cached() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        let cache = RelativesCache::default();
+        let lux = ElementHandle::from("Lux");
+        let first = get_relatives_cached(&dao, &cache, &lux).await.expect("1");
+        let second = get_relatives_cached(&dao, &cache, &lux).await.expect("2");
+        // the second lookup didn't query.
+        assert_eq!((cache.hits(), cache.misses(), cache.size().await), (1, 1, 1));
+        assert_eq!(first, second);
+        assert_eq!(first, ["Aer", "Ignis", "Tenebrae"]
+            .map(ElementHandle::from).into_iter().collect::<std::collections::HashSet<_>>());
+        // the DAO's own cache is left alone.
+        assert_eq!(dao.relatives_cache().misses(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_blend_rate() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let tenebrae = ElementHandle::from("Tenebrae");
+        let mut config = WeightConfig::default();
+        let blended = calc_weight(dao.clone(), &tenebrae, &config).await.expect("1");
+        config.set_blend_rate(1.0).expect("2");
+        // the decomposition's term vanishes.
+        let own = calc_weight(dao.clone(), &tenebrae, &config).await.expect("3");
+        assert_eq!(own, super::calc_weight_single(dao.clone(), &tenebrae, &config).await.expect("4"));
+        assert_ne!(own, blended);
+        // the cached weights of another rate aren't taken.
+        config.set_blend_rate(0.7).expect("5");
+        assert_eq!(calc_weight(dao.clone(), &tenebrae, &config).await.expect("6"), blended);
+
+        assert!(config.set_blend_rate(1.5).is_err());
+        assert!(config.set_blend_rate(-0.1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_weight_cache() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let (lux, aer) = (ElementHandle::from("Lux"), ElementHandle::from("Aer"));
+        let default = WeightConfig::default();
+        let other_alpha = WeightConfig::with_alpha(0.5).unwrap();
+        let lux_weight = calc_weight(dao.clone(), &lux, &default).await.expect("1");
+        let aer_weight = calc_weight(dao.clone(), &aer, &default).await.expect("1");
+
+        // tamper the cached weights, to tell a cached one from a recalculated one.
+        let hash = super::holdings_hash(dao.as_ref(), &default).await.expect("1");
+        dao.cache_weight(&lux, 0.7, hash, lux_weight + 100.).await.expect("1");
+        dao.cache_weight(&aer, 0.7, hash, aer_weight + 100.).await.expect("1");
+        assert_eq!(calc_weight(dao.clone(), &lux, &default).await.expect("1"), lux_weight + 100.);
+
+        // another alpha is calculated, and doesn't replace the cached one.
+        let lux_other = calc_weight(dao.clone(), &lux, &other_alpha).await.expect("1");
+        assert!(lux_other < 100.);
+        assert_ne!(lux_other, lux_weight);
+        assert_eq!(calc_weight(dao.clone(), &lux, &default).await.expect("1"), lux_weight + 100.);
+        assert_eq!(calc_weight(dao.clone(), &aer, &default).await.expect("1"), aer_weight + 100.);
+        // the hash taken once for a search finds them too.
+        let pinned = super::with_holdings_hash(dao.as_ref(), &default).await.expect("1");
+        assert_eq!(pinned.holdings_hash, Some(hash));
+        assert_eq!(calc_weight(dao.clone(), &lux, &pinned).await.expect("1"), lux_weight + 100.);
+
+        // a changed holding invalidates them all.
+        dao.change_element_holding(&ElementHandle::from("Ordo"), 5).await.expect("1");
+        assert_eq!(calc_weight(dao.clone(), &lux, &default).await.expect("1"), lux_weight);
+
+        // only the tampered ones, the calculated weights aren't written.
+        assert_eq!(dao.clear_weight_cache().await.expect("1"), 2);
+
+        // an override of an element without a holding row changes the hash too.
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            DELETE FROM elements_holding WHERE name = 'Lux';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let mut overridden = default.clone();
+        overridden.override_holding(lux.clone(), 5.);
+        assert_ne!(super::holdings_hash(dao.as_ref(), &overridden).await.expect("1"),
+            super::holdings_hash(dao.as_ref(), &default).await.expect("1"));
+    }
+
+    use super::all_primal_paths;
+    #[tokio::test]
+    async fn test_all_primal_paths() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let primals = dao.get_primary_elements().await.expect("1");
+        let grouped = all_primal_paths(dao.clone(), &[1, 2], &WeightConfig::default()).await.expect("1");
+        assert_eq!(grouped.len(), primals.len());
+        assert_eq!(grouped[0].0, ElementHandle::from("Aer"));
+        for (from, pathes) in &grouped {
+            assert!(!pathes.is_empty());
+            for path in pathes {
+                assert_eq!(path.start(), from);
+                assert!(primals.contains(path.end()) && path.end() != from);
+                assert!([1, 2].contains(&path.steps().len()));
+                assert!(is_path_viable(dao.as_ref(), path).await.expect("1"), "{path:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_weight_without_holding_row() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 0;
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'Lux', 'Potentia');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let config = WeightConfig::default();
+        // as if holding 0, the same as Tenebrae, both are made of two compounds of primals.
+        let fulgur = calc_weight(dao.clone(), &ElementHandle::from("Fulgur"), &config).await.expect("1");
+        let tenebrae = calc_weight(dao.clone(), &ElementHandle::from("Tenebrae"), &config).await.expect("1");
+        assert!(fulgur > 0.);
+        assert_eq!(fulgur, tenebrae);
+    }
+
+    use super::unheld_elements;
+    /// every walk of `steps_n` intermediates from `from` to `to`, by brute force.
+    async fn all_walks(dao: &DAO, from: &ElementHandle, to: &ElementHandle, steps_n: usize)
+        -> std::collections::HashSet<Path> {
+            let mut walks = vec![vec![from.clone()]];
+            for _ in 0..steps_n {
+                let mut next = vec![];
+                for walk in walks {
+                    for r in get_relatives(dao, walk.last().unwrap()).await.expect("relatives") {
+                        let mut w = walk.clone();
+                        w.push(r);
+                        next.push(w);
+                    }
+                }
+                walks = next;
+            }
+            let mut ret = std::collections::HashSet::new();
+            for walk in walks {
+                if get_relatives(dao, walk.last().unwrap()).await.expect("relatives").contains(to) {
+                    let mut p = Path::new(from.clone(), to.clone());
+                    walk.into_iter().skip(1).for_each(|a| p.push(a));
+                    ret.insert(p);
+                }
+            }
+            ret
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_symmetric() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        for (a, b) in [("Aer", "Victus"), ("Bestia", "Spiritus"), ("Ignis", "Tenebrae"), ("Motus", "Mortuus"), ("Lux", "Lux")] {
+            for steps_n in 0..=4 {
+                let forth = calc_path(dao.clone(), &h(a), &h(b), steps_n).await.expect("1");
+                let back = calc_path(dao.clone(), &h(b), &h(a), steps_n).await.expect("2");
+                assert_eq!(forth.len(), back.len(), "{a} {b} {steps_n}: duplicates or asymmetry");
+                let forth = forth.into_iter().collect::<std::collections::HashSet<_>>();
+                let back = back.iter().map(Path::reversed).collect::<std::collections::HashSet<_>>();
+                assert_eq!(forth, back, "{a} {b} {steps_n}");
+                assert_eq!(forth, all_walks(dao.as_ref(), &h(a), &h(b), steps_n).await, "{a} {b} {steps_n}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_long() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        for steps_n in 5..=6 {
+            let pathes = calc_path(dao.clone(), &h("Aer"), &h("Mortuus"), steps_n).await.expect("1");
+            let mut walks = all_walks(dao.as_ref(), &h("Aer"), &h("Mortuus"), steps_n).await
+                .into_iter().collect::<Vec<_>>();
+            // in the depth first order, by the names of the intermediates.
+            walks.sort_by(|a, b| a.path.cmp(&b.path));
+            assert!(!pathes.is_empty());
+            assert_eq!(pathes, walks, "{steps_n}");
+        }
+    }
+
+    use super::{SearchEvent, SearchTrace};
+    #[tokio::test]
+    async fn test_search_trace() {
+        // Aer - Lux - Flamma - Magma - Terra, and Ignis besides Lux and Flamma
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Magma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Magma', 'Flamma', 'Terra');
+            ").await);
+        let h = ElementHandle::from;
+        let trace = SearchTrace::default();
+        let mut filter = SearchFilter::default();
+        filter.exclude([h("Ignis")]);
+        filter.record_into(trace.clone());
+        let pathes = calc_path_filtered(dao.clone(), &h("Aer"), &h("Terra"), 3, &filter).await.expect("1");
+        assert_eq!(format!("{pathes:?}"), "[Aer->Lux->Flamma->Magma->Terra]");
+
+        let events = trace.events();
+        let names = |a: &[&str]| a.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(events[0], SearchEvent::Expand { depth: 0, node: "Aer".to_string(), children: names(&["Lux"]) });
+        assert_eq!(events[1], SearchEvent::Expand { depth: 1, node: "Lux".to_string(), children: names(&["Aer", "Flamma"]) });
+        assert!(events.contains(&SearchEvent::Prune { depth: 2, node: "Ignis".to_string(), reason: "excluded" }));
+        assert!(events.contains(&SearchEvent::Expand { depth: 2, node: "Flamma".to_string(), children: names(&["Lux", "Magma"]) }));
+        assert!(events.contains(&SearchEvent::Found { path: names(&["Aer", "Lux", "Flamma", "Magma", "Terra"]) }));
+        let found = events.iter().filter(|e| matches!(e, SearchEvent::Found { .. })).count();
+        assert_eq!(found, 1);
+
+        let json = serde_json::to_value(&events).expect("2");
+        assert_eq!(json[0]["event"], "expand");
+        assert_eq!(json[0]["node"], "Aer");
+    }
+
+    use super::elements_of_mod;
+    #[tokio::test]
+    async fn test_calc_path_avoid_mod() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        let forbidden = elements_of_mod(dao.as_ref(), "Forbidden Magic").await.expect("1");
+        assert_eq!(forbidden.len(), 7);
+        let mut filter = SearchFilter::default();
+        filter.exclude(forbidden.clone());
+
+        // Infernus = Ignis + Praecantatio
+        let unfiltered = calc_path(dao.clone(), &h("Ignis"), &h("Praecantatio"), 1).await.expect("2");
+        assert!(unfiltered.iter().any(|p| p.steps().iter().any(|x| forbidden.contains(x))));
+        // the endpoints may be of the avoided mod.
+        for (from, to, steps) in [("Ignis", "Praecantatio", 1), ("Infernus", "Aer", 2), ("Gula", "Superbia", 2)] {
+            let pathes = calc_path_filtered(dao.clone(), &h(from), &h(to), steps, &filter).await.expect("3");
+            assert!(!pathes.is_empty(), "{from} {to}");
+            for p in &pathes {
+                assert!(p.steps().iter().all(|x| !forbidden.contains(x)), "{p:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_owned_only() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 0 WHERE name = 'Potentia';
+            DELETE FROM elements_holding WHERE name = 'Vacuos';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let (aer, tenebrae) = (ElementHandle::from("Aer"), ElementHandle::from("Tenebrae"));
+        let mut unheld = unheld_elements(dao.as_ref()).await.expect("1");
+        unheld.sort();
+        assert_eq!(unheld, ["Potentia", "Vacuos"].map(ElementHandle::from));
+
+        // Tenebrae = Vacuos + Lux
+        assert_eq!(calc_path(dao.clone(), &aer, &tenebrae, 1).await.expect("1").len(), 2);
+        let mut filter = SearchFilter::default();
+        filter.exclude(unheld);
+        let pathes = calc_path_filtered(dao.clone(), &aer, &tenebrae, 1, &filter).await.expect("1");
+        assert_eq!(format!("{pathes:?}"), "[Aer->Lux->Tenebrae]");
+        let pathes = calc_path_filtered(dao.clone(), &aer, &tenebrae, 3, &filter).await.expect("1");
+        assert!(!pathes.is_empty());
+        assert!(pathes.iter().all(|a| a.steps().iter().all(|x| filter.allows(x))), "{pathes:?}");
+    }
+
+    use super::decomposition_mermaid;
+    #[tokio::test]
+    async fn test_decomposition_mermaid() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let mermaid = decomposition_mermaid(dao.clone(), &ElementHandle::from("Tenebrae")).await.expect("1");
+        // Tenebrae = Vacuos + Lux, Vacuos = Aer + Perditio, Lux = Aer + Ignis
+        assert_eq!(mermaid, "graph TD
+    classDef primal fill:#fd6,stroke:#a80
+    n0[\"Tenebrae\"]
+    n1[\"Vacuos\"]
+    n2[\"Lux\"]
+    n3([\"Aer\"]):::primal
+    n4([\"Perditio\"]):::primal
+    n5([\"Aer\"]):::primal
+    n6([\"Ignis\"]):::primal
+    n0 --> n1
+    n0 --> n2
+    n1 --> n3
+    n1 --> n4
+    n2 --> n5
+    n2 --> n6
+");
+    }
+
+    use super::{constructing_trees, crack_element_until_primary, is_two_eles_connected, MAX_DECOMPOSITIONS};
+    #[tokio::test]
+    async fn test_several_recipes() {
+        // Fulgur = Lux + Potentia, or Ignis + Aer
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'Lux', 'Potentia');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'Ignis', 'Aer');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        let relatives = get_relatives(dao.as_ref(), &h("Fulgur")).await.expect("1");
+        assert_eq!(relatives, ["Lux", "Potentia", "Ignis", "Aer"].map(h).into_iter().collect());
+        assert!(is_two_eles_connected(dao.as_ref(), &h("Aer"), &h("Fulgur")).await.expect("2"));
+
+        let trees = constructing_trees(dao.clone(), &h("Fulgur"), MAX_DECOMPOSITIONS, usize::MAX).await.expect("3");
+        let leaves = trees.iter()
+            .map(|t| t.nodes().filter(|a| !a.has_children()).map(|a| a.value().get_name()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        // Lux = Aer + Ignis, Potentia = Ordo + Ignis
+        assert_eq!(leaves, vec![vec!["Ignis", "Aer"], vec!["Aer", "Ignis", "Ordo", "Ignis"]]);
+
+        // the crack takes the first recipe.
+        let cracked = crack_element_until_primary(dao.clone(), &h("Fulgur")).await.expect("4");
+        assert_eq!((cracked[&h("Aer")], cracked[&h("Ignis")], cracked[&h("Ordo")]), (1, 1, 0));
+    }
+
+    use super::crack_element_to_depth;
+    #[tokio::test]
+    async fn test_crack_element_to_depth() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        let counts = |a: &[(&'static str, usize)]| a.iter().map(|&(e, n)| (h(e), n)).collect::<std::collections::HashMap<_, _>>();
+        // Tenebrae = Vacuos + Lux, Vacuos = Aer + Perditio, Lux = Aer + Ignis
+        let tenebrae = h("Tenebrae");
+        assert_eq!(crack_element_to_depth(dao.clone(), &tenebrae, 0).await.expect("1"), counts(&[("Tenebrae", 1)]));
+        assert_eq!(crack_element_to_depth(dao.clone(), &tenebrae, 1).await.expect("2"),
+            counts(&[("Vacuos", 1), ("Lux", 1)]));
+        assert_eq!(crack_element_to_depth(dao.clone(), &tenebrae, 2).await.expect("3"),
+            counts(&[("Aer", 2), ("Perditio", 1), ("Ignis", 1)]));
+        // the primary ones reached early are counted.
+        assert_eq!(crack_element_to_depth(dao.clone(), &h("Lux"), 5).await.expect("4"),
+            counts(&[("Aer", 1), ("Ignis", 1)]));
+        let full = crack_element_until_primary(dao.clone(), &tenebrae).await.expect("5");
+        assert_eq!(full.into_iter().filter(|(_, n)| *n > 0).collect::<std::collections::HashMap<_, _>>(),
+            counts(&[("Aer", 2), ("Perditio", 1), ("Ignis", 1)]));
+    }
+
+    use super::counts_by_mod;
+    #[tokio::test]
+    async fn test_counts_by_mod() {
+        let seed = format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Nemo', NULL, 1.0);
+            ", crate::dao::tests::ASPECTS_4_2_3_5);
+        let dao = crate::dao::tests::memory_dao(&seed).await;
+        let h = ElementHandle::from;
+        let counts = [(h("Aer"), 2), (h("Ignis"), 3), (h("Fulgur"), 1), (h("Nemo"), 4), (h("Nullus"), 5)]
+            .into_iter().collect::<std::collections::HashMap<_, _>>();
+        let by_mod = counts_by_mod(&dao, &counts).await.expect("1");
+        assert_eq!(by_mod.into_iter().collect::<Vec<_>>(), vec![
+            ("<unknown>".to_string(), 9),
+            ("Test".to_string(), 1),
+            ("Thaumcraft".to_string(), 5),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_cyclic_recipe() {
+        // Alpha = Beta + Ignis, Beta = Alpha + Aer
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Alpha', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Beta', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Alpha', 'Beta', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Beta', 'Alpha', 'Aer');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        match crack_element_until_primary(dao.clone(), &ElementHandle::from("Alpha")).await {
+            Err(crate::errors::T4ACHError::CyclicRecipe { cycle, .. }) => assert_eq!(cycle, ["Alpha", "Beta", "Alpha"]),
+            other => panic!("expected a cyclic recipe, got {:?}", other.map(|_| ())),
         }
+        // the elements off the cycle still crack.
+        let lux = crack_element_until_primary(dao.clone(), &ElementHandle::from("Lux")).await.expect("1");
+        assert_eq!(lux[&ElementHandle::from("Aer")], 1);
     }
 
     #[tokio::test]
-    async fn test_calc_path2() {
-        let _ = &*INIT_SQLX_DRIVERS;
+    async fn test_cyclic_alternative_recipe() {
+        // Zed = Aer + Ignis, Zed = Lux + Zed
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Zed', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Zed', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Zed', 'Lux', 'Zed');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let zed = crack_element_until_primary(dao.clone(), &ElementHandle::from("Zed")).await.expect("1");
+        assert_eq!(zed[&ElementHandle::from("Aer")], 1);
+        assert_eq!(zed[&ElementHandle::from("Ignis")], 1);
+        assert_eq!(zed.values().sum::<usize>(), 2);
 
-        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
+        // the cyclic recipe comes first: Yod = Lux + Yod, Yod = Terra + Ignis
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Yod', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Yod', 'Lux', 'Yod');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Yod', 'Terra', 'Ignis');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let yod = crack_element_until_primary(dao.clone(), &ElementHandle::from("Yod")).await.expect("2");
+        assert_eq!(yod[&ElementHandle::from("Terra")], 1);
+        assert_eq!(yod[&ElementHandle::from("Ignis")], 1);
+        assert_eq!(yod.values().sum::<usize>(), 2);
+
+        // the first recipe's cycle is deeper down: Xa = Aa + Ignis, Xa = Bb + Ignis, Aa = Xa + Aer, Bb = Terra + Aer
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Xa', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aa', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Bb', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Xa', 'Aa', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Xa', 'Bb', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Aa', 'Xa', 'Aer');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Bb', 'Terra', 'Aer');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let xa = crack_element_until_primary(dao.clone(), &ElementHandle::from("Xa")).await.expect("3");
+        for primal in ["Aer", "Ignis", "Terra"] {
+            assert_eq!(xa[&ElementHandle::from(primal)], 1, "{primal}");
+        }
+        assert_eq!(xa.values().sum::<usize>(), 3);
+    }
+
+    use super::verify_symmetry;
+    #[tokio::test]
+    async fn test_verify_symmetry() {
+        let dao = crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await;
+        assert_eq!(verify_symmetry(&dao).await.expect("1"), vec![]);
+
+        // a stale cache entry: Lux doesn't relate to Terra.
+        let (lux, terra) = (ElementHandle::from("Lux"), ElementHandle::from("Terra"));
+        let mut relatives = get_relatives(&dao, &lux).await.expect("1");
+        relatives.insert(terra.clone());
+        dao.relatives_cache().relatives.lock().await.insert(lux.clone(), relatives);
+        assert_eq!(verify_symmetry(&dao).await.expect("1"), vec![(lux, terra)]);
+    }
+
+    use super::RecipeGraph;
+    #[tokio::test]
+    async fn test_calc_path_stays_in_component() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}{}",
+            crate::dao::tests::ASPECTS_4_2_3_5, crate::dao::tests::ISOLATED)).await);
+        let isolated = ["Solus", "Unus", "Duo"].map(ElementHandle::from);
+        let aer = ElementHandle::from("Aer");
+        let graph = RecipeGraph::load(dao.as_ref()).await.expect("1");
+        assert_eq!(graph.connected_component(&isolated[2]), std::collections::HashSet::from(isolated.clone()));
+        assert!(graph.are_in_same_component(&ElementHandle::from("Lux"), &aer));
+        assert!(!graph.are_in_same_component(&isolated[0], &aer));
+
+        assert!(calc_path(dao.clone(), &isolated[0], &aer, 4).await.expect("1").is_empty());
+        assert!(!calc_path(dao.clone(), &aer, &ElementHandle::from("Tenebrae"), 1).await.expect("1").is_empty());
+        // the searches look the relatives up in the graph, not the database.
+        assert_eq!(dao.relatives_cache().misses(), 0);
+    }
+
+    use super::{calc_path_order_by_weight_filtered, WeightConfig};
+    #[tokio::test]
+    async fn test_on_board_preferred() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let (from, to) = (ElementHandle::from("Bestia"), ElementHandle::from("Spiritus"));
+        let pathes = calc_path_order_by_weight_filtered(dao.clone(), &from, &to, 2,
+            &SearchFilter::default(), &WeightConfig::default()).await.expect("1");
+        assert_eq!(pathes.first().unwrap().path, ["Victus", "Mortuus"].map(ElementHandle::from));
+
+        let mut config = WeightConfig::default();
+        config.place_on_board([ElementHandle::from("Humanus")]);
+        let pathes = calc_path_order_by_weight_filtered(dao.clone(), &from, &to, 2,
+            &SearchFilter::default(), &config).await.expect("1");
+        assert_eq!(pathes.first().unwrap().path, ["Humanus", "Cognitio"].map(ElementHandle::from));
+    }
+
+    use super::{mod_integration_report, ModIntegration};
+    #[tokio::test]
+    async fn test_mod_integration_report() {
+        let seed = format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Integrated', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Tonitrus', 'Integrated', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'Lux', 'Potentia');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Tonitrus', 'Fulgur', 'Tempestas');
+            {}", crate::dao::tests::ASPECTS_4_2_3_5, crate::dao::tests::ISOLATED);
+        let dao = Arc::new(crate::dao::tests::memory_dao(&seed).await);
+        let report = mod_integration_report(dao.clone(), 0).await.expect("1")
+            .into_iter()
+            .filter(|a| a.mod_name.starts_with("I"))
+            .collect::<Vec<_>>();
+        assert_eq!(report, vec![
+            ModIntegration { mod_name: "Integrated".to_string(), total: 2, connected: 2 },
+            ModIntegration { mod_name: "Isolated".to_string(), total: 3, connected: 0 },
+        ]);
+        assert!(report[1].is_isolated());
+        assert!((report[0].fraction() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_calc_path_through() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
         {
-            let pathes = calc_path(dao.clone(),
-            &ElementHandle::from("Aer"),
-            &ElementHandle::from("Ignis"),
-            2).await.expect("1");
-            assert_eq!(pathes.len(), 0);
+            let mut filter = SearchFilter::default();
+            filter.pass_through([ElementHandle::from("Humanus")]);
+            let pathes = calc_path_filtered(dao.clone(),
+            &ElementHandle::from("Bestia"),
+            &ElementHandle::from("Spiritus"),
+            2, &filter).await.expect("1");
+            assert_eq!(format!("{pathes:?}"), "[Bestia->Humanus->Cognitio->Spiritus]");
         }
         {
-            let pathes = calc_path(dao.clone(),
+            let pins = ["Victus", "Bestia"].map(ElementHandle::from);
+            let mut filter = SearchFilter::default();
+            filter.pass_through(pins.clone());
+            let pathes = calc_path_filtered(dao.clone(),
+            &ElementHandle::from("Aqua"),
             &ElementHandle::from("Humanus"),
-            &ElementHandle::from("Ignis"),
-            2).await.expect("1");
-            assert_eq!(format!("{pathes:?}"),
-                "[Humanus->Instrumentum->Telum->Ignis]");
-            // under 4.2.3.5 database
-            /*
-            assert_eq!(pathes.len(), 1usize);
-            let p = pathes.get(0).unwrap();
-            assert_eq!(p.path.get(0).unwrap().get_name(), "Lux")
-            */
+            3, &filter).await.expect("1");
+            assert!(!pathes.is_empty());
+            for x in &pathes {
+                let victus = x.path.iter().position(|a| *a == pins[0]).expect("Victus pinned");
+                let bestia = x.path.iter().position(|a| *a == pins[1]).expect("Bestia pinned");
+                assert!(victus < bestia, "{x:?}");
+                assert_eq!(x.path.len(), 3);
+                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+            }
         }
-        {
-            let pathes = calc_path(dao.clone(),
-            &ElementHandle::from("Machina"),
-            &ElementHandle::from("Cognitio"),
-            2).await.expect("1");
-            assert_eq!(format!("{pathes:?}"), "[Machina->Instrumentum->Humanus->Cognitio]");
+    }
+
+    use super::aspect_tiers;
+    #[tokio::test]
+    async fn test_aspect_tiers() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let tiers = aspect_tiers(dao.clone()).await.expect("1");
+        assert_eq!(tiers[&ElementHandle::from("Ignis")], 0);
+        // Lux = Aer + Ignis
+        assert_eq!(tiers[&ElementHandle::from("Lux")], 1);
+        // Tenebrae = Vacuos + Lux, Vacuos = Aer + Perditio
+        assert_eq!(tiers[&ElementHandle::from("Tenebrae")], 2);
+        assert_eq!(tiers.len(), dao.list_elements().await.expect("1").len());
+    }
+
+    #[tokio::test]
+    async fn test_override_holding_preview() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let from = ElementHandle::from("Aer");
+        let to = ElementHandle::from("Victus");
+        let motus = ElementHandle::from("Motus");
+        let committed = calc_path_order_by_weight(dao.clone(), &from, &to, 2, &WeightConfig::default()).await.expect("1");
+
+        let mut config = WeightConfig::default();
+        config.override_holding(motus.clone(), 5000.);
+        let preview = calc_path_order_by_weight_filtered(dao.clone(), &from, &to, 2,
+            &SearchFilter::default(), &config).await.expect("1");
+        // the pathes through Motus go up.
+        assert_ne!(preview, committed);
+        assert!(preview[0].steps().contains(&motus));
+        assert!(rank_changes(&committed, &preview).iter()
+            .filter(|a| a.delta() > 0)
+            .all(|a| a.path.steps().contains(&motus)));
+
+        dao.change_element_holding(&motus, 5000).await.expect("1");
+        let changed = calc_path_order_by_weight(dao.clone(), &from, &to, 2, &WeightConfig::default()).await.expect("1");
+        assert_eq!(changed.iter().map(|a| a.weight()).collect::<Vec<_>>(),
+            preview.iter().map(|a| a.weight()).collect::<Vec<_>>());
+    }
+
+    use super::calc_shortest_path;
+    #[tokio::test]
+    async fn test_calc_shortest_path() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        for (from, to, steps_n) in [("Aer", "Lux", 0), ("Aer", "Tenebrae", 1), ("Aer", "Victus", 2)] {
+            let path = calc_shortest_path(dao.clone(), &h(from), &h(to), 4).await.expect("1").expect(to);
+            assert_eq!(path.steps().len(), steps_n, "{path:?}");
+            assert!(is_path_viable(dao.as_ref(), &path).await.expect("1"));
+            if steps_n > 0 {
+                assert!(calc_path(dao.clone(), &h(from), &h(to), steps_n - 1).await.expect("1").is_empty());
+            }
         }
-        {
-            use std::collections::HashSet;
-            let pathes = calc_path(dao.clone(),
-            &ElementHandle::from("Bestia"),
-            &ElementHandle::from("Spiritus"),
-            2).await.expect("1");
-            let pathes_strs = pathes.iter()
-                .map(|a| format!("{a:?}"))
-                .collect::<HashSet<_>>();
-            let right_strs =
-                "Bestia->Humanus->Cognitio->Spiritus, Bestia->Victus->Mortuus->Spiritus, Bestia->Corpus->Mortuus->Spiritus"
-    .split(", ")
-    .map(|a| a.to_string())
-    .collect::<HashSet<_>>();
-            let res = &pathes_strs - &right_strs;
-            assert!(res.is_empty(), "{pathes_strs:?}\n - \n{right_strs:?}\n = \n {res:?}");
+        assert!(calc_shortest_path(dao.clone(), &h("Aer"), &h("Victus"), 1).await.expect("1").is_none());
+    }
+
+    use super::calc_path_min_steps;
+    #[tokio::test]
+    async fn test_calc_path_min_steps() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        for (from, to, steps_n) in [("Aer", "Lux", 0), ("Aer", "Tenebrae", 1), ("Aer", "Victus", 2)] {
+            let (found, pathes) = calc_path_min_steps(dao.clone(), &h(from), &h(to), 6).await.expect("1").expect(to);
+            assert_eq!(found, steps_n);
+            assert_eq!(pathes, calc_path(dao.clone(), &h(from), &h(to), steps_n).await.expect("2"));
         }
+        assert!(calc_path_min_steps(dao.clone(), &h("Aer"), &h("Victus"), 1).await.expect("3").is_none());
     }
 
-    use super::is_path_viable;
+    use super::precompute_distances;
     #[tokio::test]
-    async fn test_calc_path3() {
-        let _ = &*INIT_SQLX_DRIVERS;
-        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
-        {
-            let pathes = calc_path(dao.clone(),
-            &ElementHandle::from("Motus"),
-            &ElementHandle::from("Mortuus"),
-            3).await.expect("1");
-            for x in &pathes {
-                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+    async fn test_precomputed_distances_agree() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        let targets = dao.list_elements().await.expect("1")
+            .into_iter()
+            .map(|e| ElementHandle::from(e.name))
+            .filter(|e| *e != h("Aer"))
+            .collect::<Vec<_>>();
+        let mut live = vec![];
+        for to in &targets {
+            live.push(calc_shortest_path(dao.clone(), &h("Aer"), to, 8).await.expect("1")
+                .map(|p| p.steps().len()));
+        }
+        assert!(precompute_distances(dao.clone(), &dao.load_full_adjacency().await.expect("adjacency")).await.expect("2") > 0);
+        for (to, live) in targets.iter().zip(live) {
+            assert_eq!(dao.get_cached_distance(&h("Aer"), to).await.expect("3"), live, "{to:?}");
+            let cached = calc_shortest_path(dao.clone(), &h("Aer"), to, 8).await.expect("3");
+            assert_eq!(cached.as_ref().map(|p| p.steps().len()), live, "{to:?}");
+            if let Some(path) = cached {
+                assert!(is_path_viable(dao.as_ref(), &path).await.expect("3"));
             }
         }
-        {
-            let pathes = calc_path(dao.clone(),
-            &ElementHandle::from("Perditio"),
-            &ElementHandle::from("Motus"),
-            3)
-                .await.expect("1");
-            // println!("finds {} ways: {pathes:?}", pathes.len(), );
-            for x in &pathes {
-                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+        assert!(calc_shortest_path(dao.clone(), &h("Aer"), &h("Victus"), 1).await.expect("4").is_none());
+
+        // the new recipe relates Fulgur, the distances are stale
+        dao.add_recipe(&h("Fulgur"), &h("Aer"), &h("Ignis")).await.expect("5");
+        assert_eq!(dao.get_cached_distance(&h("Aer"), &h("Lux")).await.expect("5"), None);
+        let path = calc_shortest_path(dao.clone(), &h("Aer"), &h("Fulgur"), 8).await.expect("5").expect("6");
+        assert_eq!(path.steps().len(), 0);
+    }
+
+    use super::{best_weighted_connection, calc_cost_path};
+    #[tokio::test]
+    async fn test_best_weighted_connection() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            UPDATE elements_holding SET num = 5000 WHERE name IN ('Lux', 'Motus', 'Bestia');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let from = ElementHandle::from("Aer");
+        let to = ElementHandle::from("Corpus");
+        let max_steps = 2;
+
+        // the least cost under the given weighting, not the default one.
+        for config in [WeightConfig::default(), WeightConfig::with_alpha(0.3).expect("1")] {
+            let best = best_weighted_connection(dao.clone(), &from, &to, max_steps, &config).await
+                .expect("1").expect("connected");
+            assert!(is_path_viable(dao.as_ref(), &best).await.expect("1"));
+            assert!(best.steps().len() <= max_steps);
+            assert_eq!(best.cached_weight, Some(calc_weight_path(dao.clone(), &best, &config).await.expect("1")));
+            let best_cost = calc_cost_path(dao.clone(), &best, &config).await.expect("1");
+
+            // brute force over every length
+            let mut brute_force = f64::INFINITY;
+            for steps_n in 0..=max_steps {
+                for path in calc_path(dao.clone(), &from, &to, steps_n).await.expect("1") {
+                    brute_force = brute_force.min(calc_cost_path(dao.clone(), &path, &config).await.expect("1"));
+                }
             }
+            assert!((best_cost - brute_force).abs() < 1e-9, "{best:?} {best_cost} {brute_force}");
         }
+
+        assert!(best_weighted_connection(dao.clone(), &from, &to, 0, &WeightConfig::default()).await.expect("1").is_none());
+    }
+
+    use super::PathDto;
+    #[test]
+    fn test_path_dto() {
+        let h = ElementHandle::from;
+        let mut path = Path::new(h("Aer"), h("Tenebrae"));
+        path.push(h("Lux"));
+        path.cached_weight = Some(0.5);
+        let direct = Path::new(h("Aer"), h("Lux"));
+        // the JSON of try-connect, a web UI parses it.
+        let v = [&path, &direct].into_iter().map(PathDto::from).collect::<Vec<_>>();
+        assert_eq!(serde_json::to_string(&v).expect("1"),
+            r#"[{"start":"Aer","end":"Tenebrae","intermediate":["Lux"],"weight":0.5},{"start":"Aer","end":"Lux","intermediate":[],"weight":null}]"#);
+        // and its text.
+        assert_eq!(format!("{path:?}"), "Aer->Lux->Tenebrae: weight 0.5");
+        assert_eq!(format!("{direct:?}"), "Aer->Lux");
     }
 
+    use super::{enrich_path, calc_weight, calc_weight_path};
     #[tokio::test]
-    async fn test_calc_path3_with_weight() {
-        let _ = &*INIT_SQLX_DRIVERS;
-        let dao = Arc::new(DAO::new_str("sqlite://aspects.sqlite3").await);
-        {
-            let pathes = calc_path_order_by_weight(dao.clone(),
-            &ElementHandle::from("Motus"),
-            &ElementHandle::from("Mortuus"),
-            3).await.expect("1");
-            println!("finds {} ways: {pathes:?}", pathes.len(), );
-            for x in &pathes {
-                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+    async fn test_enrich_path() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 300 WHERE name = 'Lux';
+            UPDATE elements SET base_value = 2.0 WHERE name = 'Lux';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let config = WeightConfig::default();
+        let mut path = Path::new(ElementHandle::from("Aer"), ElementHandle::from("Tenebrae"));
+        path.push(ElementHandle::from("Lux"));
+        let enriched = enrich_path(dao.clone(), &path, &config).await.expect("1");
+
+        assert_eq!(enriched.steps.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), ["Aer", "Lux", "Tenebrae"]);
+        for step in &enriched.steps {
+            let ele = ElementHandle::from(step.name.as_str());
+            assert_eq!(step.base_value, dao.get_element_base_value(&ele).await.expect("1"));
+            let holding = dao.get_element_num_holding(&ele).await.expect("1");
+            assert_eq!(step.holding, holding.is_finite().then_some(holding));
+            assert_eq!(step.weight, calc_weight(dao.clone(), &ele, &config).await.expect("1"));
+        }
+        assert_eq!(enriched.steps[1].holding, Some(300.));
+        assert_eq!(enriched.steps[1].base_value, 2.0);
+        assert_eq!(enriched.steps[0].holding, None);
+        assert_eq!(enriched.weight, calc_weight_path(dao.clone(), &path, &config).await.expect("1"));
+
+        let json = serde_json::to_value(&enriched).expect("1");
+        assert_eq!(json["steps"][1]["name"], "Lux");
+        assert_eq!(json["steps"][1]["holding"], 300.);
+        assert!(json["steps"][0]["holding"].is_null());
+    }
+
+    use super::wasted_holdings;
+    #[tokio::test]
+    async fn test_wasted_holdings() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            UPDATE elements_holding SET num = 20000 WHERE name = 'Lux';
+            UPDATE elements_holding SET num = 1500 WHERE name = 'Ignis';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let wasted = wasted_holdings(dao.clone(), 0.95, &WeightConfig::default()).await.expect("1");
+        assert_eq!(wasted.iter().map(|a| a.0.clone()).collect::<Vec<_>>(), vec![ElementHandle::from("Lux")]);
+        assert!(wasted[0].1 > 0.95 && wasted[0].1 <= 1.0);
+        // M(1500) is about 0.88
+        let wasted = wasted_holdings(dao.clone(), 0.8, &WeightConfig::default()).await.expect("1");
+        assert_eq!(wasted.iter().map(|a| a.0.clone()).collect::<Vec<_>>(),
+            vec![ElementHandle::from("Lux"), ElementHandle::from("Ignis")]);
+    }
+
+    use super::find_closest_compound;
+    #[tokio::test]
+    async fn test_find_closest_compound_cover() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Pyro', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Pyro', 'Ignis', 'Terra');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Pyro');
+            ").await);
+        let wanted = std::collections::HashMap::from([(ElementHandle::from("Aer"), 1), (ElementHandle::from("Ignis"), 2)]);
+        // Lux misses an Ignis, Flamma = Aer + Ignis * 2 + Terra has a Terra more.
+        let symmetric = find_closest_compound(dao.clone(), &wanted, false).await.expect("1");
+        let cover = find_closest_compound(dao.clone(), &wanted, true).await.expect("1");
+        assert_eq!(symmetric[0], (ElementHandle::from("Lux"), 1));
+        assert_eq!(cover[0], (ElementHandle::from("Flamma"), 0));
+        assert_eq!(cover[1], (ElementHandle::from("Lux"), 1));
+        assert_eq!(symmetric.len(), 3);
+        assert_ne!(symmetric[0].0, cover[0].0);
+    }
+
+    use super::find_equivalent_aspects;
+    #[tokio::test]
+    async fn test_find_equivalent_aspects() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Pulvis', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Ignis', 'Aer');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Pulvis', 'Terra', 'Ignis');
+            ").await);
+        let groups = find_equivalent_aspects(dao.clone()).await.expect("1");
+        assert_eq!(groups, vec![vec![ElementHandle::from("Flamma"), ElementHandle::from("Lux")]]);
+    }
+
+    use super::{craft_vs_farm, Recommendation};
+    #[tokio::test]
+    async fn test_craft_vs_farm() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 10.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 2.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 3.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            ").await);
+        // farming 8 Ignis costs 80, cracking 4 Flamma costs 12 and 8 Lux 16.
+        let res = craft_vs_farm(dao.clone(), &ElementHandle::from("Ignis"), 8).await.expect("1");
+        assert_eq!(res.farm_cost, 80.);
+        let (source, cost) = res.craft.expect("1");
+        assert_eq!((source.element, source.crafts_needed, cost), (ElementHandle::from("Flamma"), 4, 12.));
+        assert_eq!(res.recommendation, Recommendation::Craft);
+
+        // nothing cracks to Aer cheaper than 1 each.
+        let res = craft_vs_farm(dao.clone(), &ElementHandle::from("Aer"), 8).await.expect("1");
+        assert_eq!(res.recommendation, Recommendation::Farm);
+    }
+
+    use super::{best_new_link, NewLink};
+    #[tokio::test]
+    async fn test_best_new_link() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aqua', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Limus', 'Thaumcraft', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Limus', 'Terra', 'Aqua');
+            ").await);
+        // two stars, linking their centers connects each center to the other star.
+        let adjacency = dao.load_full_adjacency().await.expect("adjacency");
+        let links = best_new_link(&adjacency, 1, None);
+        assert_eq!(links[0], NewLink { a: ElementHandle::from("Limus"), b: ElementHandle::from("Lux"), gain: 5 });
+        // Aer + Terra connects Aer to Terra and Limus, Terra to Lux.
+        assert!(links.contains(&NewLink { a: ElementHandle::from("Aer"), b: ElementHandle::from("Terra"), gain: 3 }));
+        // Aer and Ignis are connected already.
+        assert!(links.iter().find(|a| a.a == ElementHandle::from("Aer") && a.b == ElementHandle::from("Ignis"))
+            .is_some_and(|a| a.gain == 0));
+
+        assert_eq!(best_new_link(&adjacency, 1, Some(3)).len(), 3);
+    }
+
+    use super::best_source_for_primal;
+    #[tokio::test]
+    async fn test_best_source_for_primal() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 4.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            ").await);
+        let sources = best_source_for_primal(dao.clone(), &ElementHandle::from("Ignis"), 64).await.expect("1");
+        // Lux yields 1 Ignis per base value 1.0, Flamma yields 2 per 4.0
+        let brief = sources.iter()
+            .map(|a| (a.element.get_name(), a.yield_per_craft, a.crafts_needed))
+            .collect::<Vec<_>>();
+        assert_eq!(brief, vec![("Lux".to_string(), 1, 64), ("Flamma".to_string(), 2, 32)]);
+        assert!(sources[0].efficiency > sources[1].efficiency);
+    }
+
+    #[tokio::test]
+    async fn test_direct_connection_weight() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 10 WHERE name = 'Lux';
+            UPDATE elements_holding SET num = 1000 WHERE name = 'Tempestas';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let config = WeightConfig::default();
+        let aer = ElementHandle::from("Aer");
+        let mut weights = vec![];
+        for to in ["Lux", "Tempestas"] {
+            let to = ElementHandle::from(to);
+            let pathes = calc_path_order_by_weight(dao.clone(), &aer, &to, 0, &WeightConfig::default()).await.expect("1");
+            assert_eq!(pathes.len(), 1);
+            let expected = (calc_weight(dao.clone(), &aer, &config).await.expect("2")
+                + calc_weight(dao.clone(), &to, &config).await.expect("2")) / 2.0;
+            assert_eq!(pathes[0].weight(), Some(expected));
+            weights.push(expected);
+        }
+        assert!(weights[1] > weights[0], "{weights:?}");
+    }
+
+    use super::holdings_centroid;
+    #[tokio::test]
+    async fn test_holdings_centroid() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aqua', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Tempestas', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Tempestas', 'Aer', 'Aqua');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            INSERT INTO elements_holding(name, num) VALUES('Ignis', 1000);
+            INSERT INTO elements_holding(name, num) VALUES('Aer', 10);
+            INSERT INTO elements_holding(name, num) VALUES('Aqua', 1e999);
+            ").await);
+        // mostly Ignis, a bit of Aer: Flamma is 2 Ignis and 1 Aer
+        let (centroid, similarity) = holdings_centroid(dao.clone()).await.expect("1").expect("2");
+        assert_eq!(centroid, ElementHandle::from("Flamma"));
+        assert!(similarity > 0.85 && similarity <= 1., "{similarity}");
+
+        let empty = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            ").await);
+        assert_eq!(holdings_centroid(empty).await.expect("3"), None);
+    }
+
+    use super::all_decompositions;
+    #[tokio::test]
+    async fn test_all_decompositions() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aqua', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aqua', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Ignis', 'Aer');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            ").await);
+        let h = ElementHandle::from;
+        let lux = all_decompositions(dao.clone(), &h("Lux")).await.expect("1");
+        assert_eq!(lux, vec![
+            std::collections::HashMap::from([(h("Aer"), 1), (h("Ignis"), 1)]),
+            std::collections::HashMap::from([(h("Aqua"), 1), (h("Ignis"), 1)]),
+        ]);
+        let flamma = all_decompositions(dao.clone(), &h("Flamma")).await.expect("2");
+        assert_eq!(flamma.len(), 2);
+        assert!(flamma.contains(&std::collections::HashMap::from([(h("Aqua"), 1), (h("Ignis"), 2)])));
+        assert_eq!(all_decompositions(dao.clone(), &h("Aer")).await.expect("3"), vec![std::collections::HashMap::from([(h("Aer"), 1)])]);
+    }
+
+    use super::suggest_next_step;
+    #[tokio::test]
+    async fn test_suggest_next_step() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 100;
+            UPDATE elements_holding SET num = 5000 WHERE name = 'Motus';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        let suggestions = suggest_next_step(dao.clone(), &[h("Aer")], &h("Victus"), 2, &WeightConfig::default()).await.expect("1");
+        // the intermediates of the five Aer -> Victus pathes of 2 steps
+        let mut names = suggestions.iter().map(|a| a.0.get_name()).collect::<Vec<_>>();
+        assert_eq!(names[0], "Motus");
+        names.sort();
+        assert_eq!(names, ["Arbor", "Motus", "Sensus", "Tempestas", "Vacuos"]);
+
+        let next = suggestions[0].0.clone();
+        let rest = calc_shortest_path(dao.clone(), &next, &h("Victus"), 1).await.expect("2").expect("3");
+        let mut path = Path::new(h("Aer"), h("Victus"));
+        path.push(next);
+        rest.steps().iter().for_each(|a| path.push(a.clone()));
+        assert!(is_path_viable(dao.as_ref(), &path).await.expect("4"));
+
+        assert!(suggest_next_step(dao.clone(), &[h("Aer")], &h("Victus"), 1, &WeightConfig::default()).await.expect("5").is_empty());
+
+        // the given weighting is taken.
+        let mut config = WeightConfig::default();
+        config.override_holding(h("Motus"), 100.);
+        config.override_holding(h("Arbor"), 5000.);
+        let suggestions = suggest_next_step(dao.clone(), &[h("Aer")], &h("Victus"), 2, &config).await.expect("6");
+        assert_eq!(suggestions[0].0, h("Arbor"));
+    }
+
+    use super::decomposition_overlap;
+    #[tokio::test]
+    async fn test_decomposition_overlap() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        // Lux = Aer + Ignis, Tempestas = Aer + Aqua
+        let overlap = decomposition_overlap(dao.clone(), &h("Lux"), &h("Tempestas")).await.expect("1");
+        assert_eq!(overlap.shared, vec![h("Aer")]);
+        assert_eq!(overlap.jaccard, 1. / 3.);
+        assert_eq!(overlap.count_overlap, 1);
+        let same = decomposition_overlap(dao.clone(), &h("Lux"), &h("Lux")).await.expect("2");
+        assert_eq!(same.jaccard, 1.);
+        assert_eq!(same.count_overlap, 2);
+    }
+
+    use super::most_critical_aspect;
+    #[tokio::test]
+    async fn test_most_critical_aspect() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::NODUS_HUB).await);
+        let adjacency = dao.load_full_adjacency().await.expect("adjacency");
+        let ranked = most_critical_aspect(&adjacency, None);
+        assert_eq!(ranked.len(), 7);
+        // three pairs of two left, 15 pairs less 3
+        assert_eq!(ranked[0], (ElementHandle::from("Nodus"), 12));
+        // removing a compound cuts its leaf off the 5 others
+        assert_eq!(ranked[1].1, 5);
+        let leaf = ranked.iter().find(|a| a.0 == ElementHandle::from("Aer")).unwrap();
+        assert_eq!(leaf.1, 0);
+        assert_eq!(most_critical_aspect(&adjacency, Some(2)).len(), 2);
+    }
+
+    use super::precompute_weights;
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_precompute_weights_matches_serial() {
+        let seed = format!("{}
+            UPDATE elements_holding SET num = 300;
+            UPDATE elements_holding SET num = 2500 WHERE name IN ('Lux', 'Motus', 'Potentia');
+            ", crate::dao::tests::ASPECTS_4_2_3_5);
+        let parallel = Arc::new(crate::dao::tests::memory_dao(&seed).await);
+        let serial = Arc::new(crate::dao::tests::memory_dao(&seed).await);
+        let config = WeightConfig::default();
+
+        let mut reported = vec![];
+        let n = precompute_weights(parallel.clone(), &config, 8, |done, total| reported.push((done, total)))
+            .await.expect("1");
+        let elements = serial.list_elements().await.expect("2")
+            .into_iter()
+            .map(|e| ElementHandle::from(e.name))
+            .collect::<Vec<_>>();
+        assert_eq!(n as usize, elements.len());
+        assert_eq!(reported.last(), Some(&(elements.len(), elements.len())));
+
+        let hash = super::holdings_hash(serial.as_ref(), &config).await.expect("3");
+        let alpha = 0.7;
+        for ele in &elements {
+            let a = parallel.get_cached_weight(ele, alpha, hash).await.expect("4");
+            let b = calc_weight(serial.clone(), ele, &config).await.expect("5");
+            assert_eq!(a, Some(b), "{ele:?}");
+        }
+        // the serial weights are calculated, not cached.
+        assert_eq!(serial.clear_weight_cache().await.expect("6"), 0);
+    }
+
+    use super::calc_weight_single;
+    #[tokio::test]
+    async fn test_decayed_holding() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 800, last_updated = 1000 WHERE name = 'Lux';
+            UPDATE elements_holding SET num = 800, last_updated = 1000 + 3600 * 48 WHERE name = 'Motus';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let (old, fresh) = (ElementHandle::from("Lux"), ElementHandle::from("Motus"));
+        let mut config = WeightConfig::default();
+        assert_eq!(calc_weight_single(dao.clone(), &old, &config).await.expect("1"),
+            calc_weight_single(dao.clone(), &fresh, &config).await.expect("1"));
+
+        // a day's half-life, Lux is two days older
+        config.decay_holdings(3600. * 24., 1000 + 3600 * 48);
+        let old_weight = calc_weight_single(dao.clone(), &old, &config).await.expect("2");
+        let fresh_weight = calc_weight_single(dao.clone(), &fresh, &config).await.expect("2");
+        assert!(old_weight < fresh_weight, "{old_weight} {fresh_weight}");
+        assert!((old_weight * 4. - fresh_weight).abs() < 1e-9);
+
+        dao.change_element_holding(&old, 800).await.expect("3");
+        assert!(dao.get_holding_last_updated(&old).await.expect("3").unwrap() > 1000 + 3600 * 48);
+    }
+
+    use super::suggest_bridging_recipes;
+    #[tokio::test]
+    async fn test_suggest_bridging_recipes() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            {}", crate::dao::tests::ISOLATED)).await);
+        let h = ElementHandle::from;
+        let bridges = suggest_bridging_recipes(dao.clone(), &h("Aer"), &h("Duo")).await.expect("1");
+        assert_eq!(bridges.len(), 9);
+        assert_eq!(bridges[0], super::BridgeRecipe { component_a: h("Aer"), component_b: h("Duo"), steps: 1 });
+        assert!(bridges[1..].iter().all(|a| a.steps > 1));
+        assert!(suggest_bridging_recipes(dao.clone(), &h("Aer"), &h("Lux")).await.expect("2").is_empty());
+    }
+
+    use super::path_readiness;
+    #[tokio::test]
+    async fn test_path_readiness() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 0 WHERE name = 'Tempestas';
+            UPDATE elements_holding SET num = 500 WHERE name = 'Aqua';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let mut path = Path::new(ElementHandle::from("Aer"), ElementHandle::from("Victus"));
+        path.push(ElementHandle::from("Tempestas"));
+        path.push(ElementHandle::from("Aqua"));
+        let mut config = WeightConfig::default();
+        assert_eq!(path_readiness(dao.clone(), &path, &config).await.expect("1"), 0.);
+
+        config.override_holding(ElementHandle::from("Tempestas"), 2000.);
+        let partly = path_readiness(dao.clone(), &path, &config).await.expect("2");
+        assert!(partly > 0., "{partly}");
+        config.override_holding(ElementHandle::from("Aqua"), 2000.);
+        let stocked = path_readiness(dao.clone(), &path, &config).await.expect("3");
+        assert!(stocked > partly && stocked <= 1., "{partly} {stocked}");
+    }
+
+    use super::pivot_aspects;
+    #[tokio::test]
+    async fn test_pivot_aspects() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::NODUS_HUB).await);
+        let h = ElementHandle::from;
+        let pairs = [(h("Aer"), h("Ignis")), (h("Aer"), h("Terra")), (h("Ignis"), h("Terra"))];
+        let pivots = pivot_aspects(dao.clone(), Some(&pairs), 3, &WeightConfig::default()).await.expect("1");
+        assert_eq!(pivots, vec![(h("Nodus"), 3), (h("Flamma"), 2), (h("Humus"), 2), (h("Ventus"), 2)]);
+
+        // every primal pair, Nodus is a primary element too: only its pairs connect in one step.
+        let pivots = pivot_aspects(dao.clone(), None, 1, &WeightConfig::default()).await.expect("2");
+        assert_eq!(pivots, vec![(h("Flamma"), 1), (h("Humus"), 1), (h("Ventus"), 1)]);
+    }
+
+    use super::aspects_above_weight;
+    #[tokio::test]
+    async fn test_aspects_above_weight() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO elements_holding(name, num) VALUES('Aer', 1e999);
+            INSERT INTO elements_holding(name, num) VALUES('Ignis', 0);
+            INSERT INTO elements_holding(name, num) VALUES('Lux', 1e999);
+            ").await);
+        let config = WeightConfig::default();
+        // Aer 0.7 + 0.3, Ignis 0 + 0.3, Lux 0.7 + 0.3 / (1 + 1 + 0)
+        let top = aspects_above_weight(dao.clone(), 0.5, &config).await.expect("1");
+        let names = top.iter().map(|(e, _)| e.get_name()).collect::<Vec<_>>();
+        assert_eq!(names, ["Aer", "Lux"]);
+        assert!((top[0].1 - 1.0).abs() < 1e-9 && (top[1].1 - 0.85).abs() < 1e-9, "{top:?}");
+        assert_eq!(aspects_above_weight(dao.clone(), 0.2, &config).await.expect("2").len(), 3);
+        assert!(aspects_above_weight(dao.clone(), 1.0, &config).await.expect("3").is_empty());
+    }
+
+    use super::suggest_alpha;
+    #[tokio::test]
+    async fn test_suggest_alpha() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 300;
+            UPDATE elements_holding SET num = 1500 WHERE name IN ('Motus', 'Bestia');
+            UPDATE elements_holding SET num = 50 WHERE name = 'Tempestas';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        let (alpha, gap) = suggest_alpha(dao.clone(), &h("Aer"), &h("Victus"), 2).await.expect("1").expect("2");
+        assert!(alpha > 0. && alpha < 1., "{alpha}");
+        assert!(gap >= 0., "{gap}");
+        // no other alpha of the grid separates them more.
+        let pathes = calc_path(dao.clone(), &h("Aer"), &h("Victus"), 2).await.expect("3");
+        for other in [0.05, 0.5, 0.95] {
+            let config = WeightConfig::with_alpha(other).expect("4");
+            let mut weights = vec![];
+            for p in &pathes {
+                weights.push(calc_weight_path(dao.clone(), p, &config).await.expect("5"));
             }
+            weights.sort_by(|a, b| b.total_cmp(a));
+            assert!(weights[0] - weights[1] <= gap + 1e-12);
         }
-        {
-            let pathes = calc_path_order_by_weight(dao.clone(),
-            &ElementHandle::from("Perditio"),
-            &ElementHandle::from("Motus"),
-            3)
-                .await.expect("1");
-            println!("finds {} ways: {pathes:?}", pathes.len(), );
-            for x in &pathes {
-                assert!(is_path_viable(dao.as_ref(), x).await.expect("bigger problem"), "{x:?} can't viable.");
+
+        // a single path has nothing to separate.
+        assert_eq!(suggest_alpha(dao.clone(), &h("Aer"), &h("Ignis"), 0).await.expect("6"), None);
+    }
+
+    use super::all_aspects_in_tree;
+    #[tokio::test]
+    async fn test_all_aspects_in_tree() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        // Limus = Victus + Aqua, Victus = Aqua + Terra: Aqua is twice in the tree.
+        assert_eq!(all_aspects_in_tree(dao.clone(), &h("Limus")).await.expect("1"),
+            ["Aqua", "Limus", "Terra", "Victus"].map(h));
+        assert_eq!(all_aspects_in_tree(dao.clone(), &h("Aer")).await.expect("2"), vec![h("Aer")]);
+    }
+
+    use super::aspects_by_primal_count;
+    #[tokio::test]
+    async fn test_aspects_by_primal_count() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        let primals = aspects_by_primal_count(dao.clone(), 1).await.expect("1");
+        assert_eq!(primals, ["Aer", "Aqua", "Ignis", "Ordo", "Perditio", "Terra"].map(h));
+
+        // one primal and Victus, which is two
+        let three = aspects_by_primal_count(dao.clone(), 3).await.expect("2");
+        for ele in ["Herba", "Limus", "Mortuus", "Sano"] {
+            assert!(three.contains(&h(ele)), "{ele} in {three:?}");
+        }
+        assert!(!three.contains(&h("Victus")));
+        assert!(!three.contains(&h("Bestia")));
+        assert!(aspects_by_primal_count(dao.clone(), 0).await.expect("3").is_empty());
+    }
+
+    use super::{rank_by_criteria, Criteria};
+    #[tokio::test]
+    async fn test_rank_by_criteria() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 0 WHERE name = 'Tempestas';
+            UPDATE elements SET base_value = 10.0 WHERE name = 'Lux';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        // short but unstocked, and long but stocked and through the rare Lux.
+        let mut short = Path::new(h("Aer"), h("Aqua"));
+        short.push(h("Tempestas"));
+        let mut long = Path::new(h("Aer"), h("Aqua"));
+        long.push(h("Lux"));
+        long.push(h("Ignis"));
+        let config = WeightConfig::default();
+        let first = |criteria: &str| {
+            let dao = dao.clone();
+            let pathes = vec![short.clone(), long.clone()];
+            let criteria = criteria.parse::<Criteria>().expect("parse");
+            let config = config.clone();
+            async move {
+                let ranked = rank_by_criteria(dao, pathes, &criteria, &config).await.expect("rank");
+                ranked[0].0.steps().len()
             }
+        };
+        assert_eq!(first("length:1").await, 1);
+        assert_eq!(first("holdings:1").await, 2);
+        assert_eq!(first("length:0.2,holdings:0.8").await, 2);
+        assert_eq!(first("length:0.8,holdings:0.2").await, 1);
+        assert_eq!(first("holdings:0.6,rarity:0.4").await, 2);
+        assert_eq!(first("holdings:0.4,rarity:0.6").await, 1);
+
+        assert!("length:1,speed:1".parse::<Criteria>().is_err());
+        assert!("length:-1".parse::<Criteria>().is_err());
+        assert!("length:0".parse::<Criteria>().is_err());
+        assert!("length:1,length:2".parse::<Criteria>().is_err());
+        assert!("length".parse::<Criteria>().is_err());
+    }
+
+    use super::{calc_weight_single_range, calc_weight_path_interval};
+    #[tokio::test]
+    async fn test_calc_weight_interval() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 800;
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let h = ElementHandle::from;
+        let config = WeightConfig::default();
+        let (low, high) = calc_weight_single_range(dao.clone(), &h("Lux"), (400., 1200.), &config).await.expect("1");
+        assert!(low < high);
+        let point = calc_weight_single(dao.clone(), &h("Lux"), &config).await.expect("2");
+        assert!(low < point && point < high, "{low} {point} {high}");
+
+        let mut path = Path::new(h("Aer"), h("Victus"));
+        path.push(h("Tempestas"));
+        path.push(h("Aqua"));
+        let width = |(low, high): (f64, f64)| high - low;
+        let none = calc_weight_path_interval(dao.clone(), &path, &config, 0.).await.expect("3");
+        let narrow = calc_weight_path_interval(dao.clone(), &path, &config, 0.1).await.expect("4");
+        let wide = calc_weight_path_interval(dao.clone(), &path, &config, 0.5).await.expect("5");
+        assert!(width(none).abs() < 1e-12, "{none:?}");
+        assert!(width(narrow) > 0., "{narrow:?}");
+        assert!(width(wide) > width(narrow), "{narrow:?} {wide:?}");
+        assert!(wide.0 <= narrow.0 && narrow.1 <= wide.1, "{narrow:?} {wide:?}");
+    }
+
+    use super::path_aspect_cost;
+    #[tokio::test]
+    async fn test_path_aspect_cost() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let h = ElementHandle::from;
+        // Aer->Tempestas->Aqua->Tempestas->Victus, Tempestas is placed twice
+        let mut path = Path::new(h("Aer"), h("Victus"));
+        path.push(h("Tempestas"));
+        path.push(h("Aqua"));
+        path.push(h("Tempestas"));
+        let cost = path_aspect_cost(dao.clone(), &path, false).await.expect("1");
+        assert_eq!(cost, std::collections::HashMap::from([(h("Tempestas"), 2), (h("Aqua"), 1)]));
+
+        // Tempestas is Aer and Aqua
+        let cost = path_aspect_cost(dao.clone(), &path, true).await.expect("2");
+        assert_eq!(cost, std::collections::HashMap::from([(h("Aer"), 2), (h("Aqua"), 3)]));
+
+        let direct = Path::new(h("Aer"), h("Tempestas"));
+        assert!(path_aspect_cost(dao.clone(), &direct, true).await.expect("3").is_empty());
+    }
+
+    use super::base_value_percentiles;
+    #[tokio::test]
+    async fn test_base_value_percentiles() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 2.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 8.0);
+            ").await);
+        let percentiles = base_value_percentiles(dao.clone()).await.expect("1");
+        let p = |a: &str| percentiles[&ElementHandle::from(a)];
+        assert_eq!(p("Flamma"), 100.);
+        assert_eq!(p("Lux"), 75.);
+        assert_eq!(p("Aer"), 50.);
+        assert_eq!(p("Ignis"), p("Aer"));
+    }
+
+    use super::craftable_within_budget;
+    #[tokio::test]
+    async fn test_craftable_within_budget() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Limus', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Limus', 'Terra', 'Aer');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            INSERT INTO elements_holding(name, num) VALUES('Aer', 5);
+            INSERT INTO elements_holding(name, num) VALUES('Ignis', 3);
+            INSERT INTO elements_holding(name, num) VALUES('Terra', 0);
+            ").await);
+        let names = |depths: Vec<Vec<ElementHandle>>| depths.into_iter()
+            .map(|d| d.iter().map(|e| e.get_name()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(names(craftable_within_budget(dao.clone(), 1).await.expect("1")), vec![vec!["Lux"]]);
+        assert_eq!(names(craftable_within_budget(dao.clone(), 2).await.expect("2")),
+            vec![vec!["Lux"], vec!["Flamma"]]);
+        // no Terra, no Limus
+        assert_eq!(names(craftable_within_budget(dao.clone(), 5).await.expect("3")).len(), 2);
+    }
+
+    use super::group_by_dominant_primal;
+    #[tokio::test]
+    async fn test_group_by_dominant_primal() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Terra', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            ").await);
+        let groups = group_by_dominant_primal(dao.clone()).await.expect("1")
+            .into_iter()
+            .map(|(p, g)| (p.get_name(), g.iter().map(|e| e.get_name()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        // Flamma cracks into Aer once and Ignis twice, Lux's tie goes to Aer
+        assert_eq!(groups, vec![
+            ("Aer".to_string(), vec!["Lux".to_string()]),
+            ("Ignis".to_string(), vec!["Flamma".to_string()]),
+            ("Terra".to_string(), vec![]),
+        ]);
+    }
+
+    use super::verify_base_values;
+    #[tokio::test]
+    async fn test_verify_base_values() {
+        let dao = Arc::new(crate::dao::tests::memory_dao("
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Aer', 'Thaumcraft', 1.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Ignis', 'Thaumcraft', 2.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Lux', 'Thaumcraft', 3.0);
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Flamma', 'Test', 40.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Lux', 'Aer', 'Ignis');
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Flamma', 'Lux', 'Ignis');
+            ").await);
+        // Lux costs 1.0 + 2.0, Flamma 1.0 + 2.0 * 2 but is valued 40.0
+        let flagged = verify_base_values(dao.clone(), 0.5).await.expect("1");
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].element, ElementHandle::from("Flamma"));
+        assert_eq!(flagged[0].primal_cost, 5.0);
+        assert_eq!(flagged[0].deviation, 7.0);
+        assert_eq!(verify_base_values(dao.clone(), 10.).await.expect("2").len(), 0);
+    }
+
+    use super::score_pathes;
+    #[tokio::test]
+    async fn test_score_pathes() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let pathes = calc_path_order_by_weight(dao.clone(), &ElementHandle::from("Aer"), &ElementHandle::from("Victus"), 2, &WeightConfig::default())
+            .await.expect("1");
+        let scores = score_pathes(&pathes).into_iter().map(|a| a.expect("2")).collect::<Vec<_>>();
+        assert_eq!(scores[0], 100.);
+        assert!(scores.windows(2).all(|w| w[0] >= w[1] && w[1] > 0.), "{scores:?}");
+        assert_eq!(score_pathes(&[Path::new(ElementHandle::from("Aer"), ElementHandle::from("Lux"))]), vec![None]);
+        // every weight 0, nothing to be relative to.
+        let mut zero = pathes[0].clone();
+        zero.cached_weight = Some(0.);
+        assert_eq!(score_pathes(&[zero.clone(), zero]), vec![None, None]);
+    }
+
+    use super::distinct_intermediates;
+    #[tokio::test]
+    async fn test_distinct_intermediates() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(crate::dao::tests::ASPECTS_4_2_3_5).await);
+        let pathes = calc_path_order_by_weight(dao.clone(), &ElementHandle::from("Aer"), &ElementHandle::from("Victus"), 2, &WeightConfig::default())
+            .await.expect("1");
+        assert_eq!(pathes.len(), 5);
+        let names = distinct_intermediates(&pathes).iter().map(|e| e.get_name()).collect::<Vec<_>>();
+        assert_eq!(names, ["Aqua", "Arbor", "Bestia", "Fames", "Herba", "Motus", "Sensus", "Spiritus",
+            "Tempestas", "Vacuos"]);
+        assert!(distinct_intermediates(&[]).is_empty());
+    }
+
+    use super::calc_path_order_by_weight_lazy;
+    #[tokio::test]
+    async fn test_calc_path_order_by_weight_lazy() {
+        // the compound aspects are rarer than the primary ones
+        let seed = format!("{}
+            UPDATE elements SET base_value = 2.0 WHERE name IN (SELECT name FROM recipes);
+            UPDATE elements SET base_value = 3.0 WHERE name IN ('Spiritus', 'Bestia');
+            ", crate::dao::tests::ASPECTS_4_2_3_5);
+        let dao = Arc::new(crate::dao::tests::memory_dao(&seed).await);
+        for (from, to) in [("Motus", "Mortuus"), ("Perditio", "Motus")] {
+            let (from, to) = (ElementHandle::from(from), ElementHandle::from(to));
+            let full = calc_path_order_by_weight_filtered(dao.clone(), &from, &to, 3,
+                &SearchFilter::default(), &WeightConfig::default()).await.expect("1");
+            let lazy = calc_path_order_by_weight_lazy(dao.clone(), &from, &to, 3,
+                &SearchFilter::default(), &WeightConfig::default(), 1).await.expect("1");
+            assert_eq!(lazy.len(), 1);
+            assert_eq!(lazy[0].cached_weight, full[0].cached_weight, "{lazy:?} vs {:?}", full[0]);
         }
     }
+
+    use super::rank_changes;
+    #[test]
+    fn test_rank_changes() {
+        let path = |a: &str| {
+            let mut p = Path::new(ElementHandle::from("Aer"), ElementHandle::from("Ignis"));
+            p.push(ElementHandle::from(a));
+            p
+        };
+        let before = [path("A"), path("B"), path("C"), path("D")];
+        let after = [path("C"), path("A"), path("B"), path("E")];
+        let changes = rank_changes(&before, &after)
+            .into_iter()
+            .map(|a| (a.path.path[0].get_name(), a.before, a.after, a.delta()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![
+            ("C".to_string(), 3, 1, 2),
+            ("A".to_string(), 1, 2, -1),
+            ("B".to_string(), 2, 3, -1),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_weight_config_alpha() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            UPDATE elements_holding SET num = 500 WHERE name = 'Lux';
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let lux = ElementHandle::from("Lux");
+        let low = super::calc_weight_single(dao.clone(), &lux, &WeightConfig::with_alpha(0.2).unwrap())
+            .await.expect("1");
+        let high = super::calc_weight_single(dao.clone(), &lux, &WeightConfig::with_alpha(0.8).unwrap())
+            .await.expect("1");
+        // M(500) = alpha * 500 / 1000
+        assert!((low - 0.1).abs() < 1e-9 && (high - 0.4).abs() < 1e-9, "{low} {high}");
+        assert!(WeightConfig::with_alpha(1.0).is_err());
+    }
 }
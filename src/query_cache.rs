@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::errors;
+
+/// The on-disk layout of the sidecar file.
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    db_mtime: u64,
+    entries: HashMap<String, String>,
+}
+
+/// Query results persisted next to the database, valid while the database's
+/// last-modified time stays the same. It's kept out of the database itself,
+/// writing into it would bump the mtime it's keyed on.
+pub struct QueryCache {
+    path: PathBuf,
+    db_mtime: u64,
+    entries: HashMap<String, String>,
+}
+
+async fn modified_nanos(path: &Path) -> errors::Result<u64> {
+    let modified = tokio::fs::metadata(path).await.context(errors::IoSnafu)?
+        .modified().context(errors::IoSnafu)?;
+    Ok(modified.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0))
+}
+
+impl QueryCache {
+    /// The sidecar path used for `db_path`.
+    pub fn sidecar_of(db_path: &Path) -> PathBuf {
+        let mut name = db_path.as_os_str().to_owned();
+        name.push(".cache.json");
+        PathBuf::from(name)
+    }
+
+    /// Read the cache at `path`, dropping every entry if `db_path` was modified
+    /// since they were stored. A missing or corrupt cache file is an empty cache.
+    pub async fn load(path: impl Into<PathBuf>, db_path: &Path) -> errors::Result<Self> {
+        let path = path.into();
+        let db_mtime = modified_nanos(db_path).await?;
+        let stored = tokio::fs::read_to_string(&path).await
+            .ok()
+            .and_then(|s| serde_json::from_str::<CacheFile>(&s).ok())
+            .unwrap_or_default();
+        let entries = if stored.db_mtime == db_mtime {
+            stored.entries
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { path, db_mtime, entries })
+    }
+
+    /// `key` should carry the command and all its parameters.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.entries.get(key)
+            .and_then(|s| serde_json::from_str(s).ok())
+    }
+
+    pub fn put<T: Serialize>(&mut self, key: &str, value: &T) {
+        if let Ok(s) = serde_json::to_string(value) {
+            self.entries.insert(key.to_string(), s);
+        }
+    }
+
+    pub async fn save(&self) -> errors::Result<()> {
+        let file = CacheFile {
+            db_mtime: self.db_mtime,
+            entries: self.entries.clone(),
+        };
+        let content = serde_json::to_string(&file).expect("Serialize query cache failed.");
+        tokio::fs::write(&self.path, content).await.context(errors::IoSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use super::QueryCache;
+    use crate::dao::tests::temp_path;
+
+    fn set_mtime(path: &str, t: SystemTime) {
+        std::fs::File::options().write(true).open(path).expect("open")
+            .set_modified(t).expect("set_modified");
+    }
+
+    #[tokio::test]
+    async fn test_query_cache_invalidated_by_mtime() {
+        let db = temp_path("query_cache_db");
+        let db_path = Path::new(&db);
+        let sidecar = QueryCache::sidecar_of(db_path);
+        std::fs::write(&db, "db").expect("1");
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        set_mtime(&db, t0);
+
+        let mut cache = QueryCache::load(&sidecar, db_path).await.expect("2");
+        assert_eq!(cache.get::<Vec<(String, usize)>>("tiers"), None);
+        cache.put("tiers", &vec![("Aer".to_string(), 0usize)]);
+        cache.save().await.expect("3");
+
+        // second run, the database is unchanged
+        let cache = QueryCache::load(&sidecar, db_path).await.expect("4");
+        assert_eq!(cache.get::<Vec<(String, usize)>>("tiers"),
+            Some(vec![("Aer".to_string(), 0usize)]));
+
+        // the database was written to
+        std::fs::write(&db, "db changed").expect("5");
+        set_mtime(&db, t0 + Duration::from_secs(1));
+        let cache = QueryCache::load(&sidecar, db_path).await.expect("6");
+        assert_eq!(cache.get::<Vec<(String, usize)>>("tiers"), None);
+
+        let _ = std::fs::remove_file(&db);
+        let _ = std::fs::remove_file(&sidecar);
+    }
+}
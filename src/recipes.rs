@@ -38,6 +38,9 @@ impl Hash for ElementHandle {
     }
 }
 
+/// The primal aspects of Thaumcraft 4, the only ones expected to have no recipe.
+pub const PRIMAL_ASPECTS: [&str; 6] = ["Aer", "Aqua", "Ignis", "Ordo", "Perditio", "Terra"];
+
 pub struct Element {
     pub(crate) name: String,
     pub(crate) belongs_to_mod: Option<String>,
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::dao::DAO;
+use crate::errors::{self, DatabaseSnafu};
+use crate::pathes;
+
+#[derive(Serialize)]
+pub struct Stats {
+    pub elements: usize,
+    pub recipes: usize,
+    pub mods: usize,
+}
+
+#[derive(Serialize)]
+pub struct Tier {
+    pub tier: usize,
+    pub aspects: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ModRow {
+    pub mod_name: String,
+    pub connected: usize,
+    pub total: usize,
+}
+
+/// The database's characteristics, gathered from the individual analyses.
+#[derive(Serialize)]
+pub struct Report {
+    pub stats: Stats,
+    pub primals: Vec<String>,
+    pub tiers: Vec<Tier>,
+    /// see `pathes::graph_diameter`.
+    pub diameter: Option<usize>,
+    pub terminal_aspects: Vec<String>,
+    /// the mods' aspects connecting to the base ones within `integration_steps` steps.
+    pub integration_steps: usize,
+    pub mod_integration: Vec<ModRow>,
+}
+
+pub async fn build_report(dao: Arc<DAO>, integration_steps: usize) -> errors::Result<Report> {
+    let stats = Stats {
+        elements: dao.list_elements().await.context(DatabaseSnafu)?.len(),
+        recipes: dao.list_recipes().await.context(DatabaseSnafu)?.len(),
+        mods: dao.list_mods().await.context(DatabaseSnafu)?.len(),
+    };
+    let mut primals = dao.get_primary_elements().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|a| a.get_name())
+        .collect::<Vec<_>>();
+    primals.sort();
+
+    let mut grouped: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (ele, tier) in pathes::aspect_tiers(dao.clone()).await? {
+        grouped.entry(tier).or_default().push(ele.get_name());
+    }
+    let tiers = grouped.into_iter()
+        .map(|(tier, mut aspects)| {
+            aspects.sort();
+            Tier { tier, aspects }
+        })
+        .collect();
+
+    let mut terminal_aspects = dao.find_terminal_products().await.context(DatabaseSnafu)?
+        .into_iter()
+        .map(|a| a.get_name())
+        .collect::<Vec<_>>();
+    terminal_aspects.sort();
+
+    let mod_integration = pathes::mod_integration_report(dao.clone(), integration_steps).await?
+        .into_iter()
+        .map(|m| ModRow { mod_name: m.mod_name, connected: m.connected, total: m.total })
+        .collect();
+
+    Ok(Report {
+        stats,
+        primals,
+        tiers,
+        diameter: pathes::graph_diameter(&dao.load_full_adjacency().await.context(DatabaseSnafu)?),
+        terminal_aspects,
+        integration_steps,
+        mod_integration,
+    })
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Aspects report\n");
+        let _ = writeln!(out, "## Stats\n");
+        let _ = writeln!(out, "- elements: {}", self.stats.elements);
+        let _ = writeln!(out, "- recipes: {}", self.stats.recipes);
+        let _ = writeln!(out, "- mods: {}\n", self.stats.mods);
+        let _ = writeln!(out, "## Primals\n");
+        let _ = writeln!(out, "{}\n", self.primals.join(", "));
+        let _ = writeln!(out, "## Tiers\n");
+        for t in &self.tiers {
+            let _ = writeln!(out, "- Tier {}: {}", t.tier, t.aspects.join(", "));
+        }
+        let _ = writeln!(out, "\n## Diameter\n");
+        match self.diameter {
+            Some(d) => { let _ = writeln!(out, "{d} steps\n"); },
+            None => { let _ = writeln!(out, "no two aspects are connected\n"); },
+        }
+        let _ = writeln!(out, "## Terminal aspects\n");
+        let _ = writeln!(out, "{}\n", self.terminal_aspects.join(", "));
+        let _ = writeln!(out, "## Mod integration\n");
+        let _ = writeln!(out, "Within {} steps of the {} aspects.\n", self.integration_steps, pathes::BASE_MOD);
+        let _ = writeln!(out, "| mod | connected | total |");
+        let _ = writeln!(out, "| --- | --- | --- |");
+        for m in &self.mod_integration {
+            let _ = writeln!(out, "| {} | {} | {} |", m.mod_name, m.connected, m.total);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::build_report;
+
+    #[tokio::test]
+    async fn test_report_sections() {
+        let dao = Arc::new(crate::dao::tests::memory_dao(&format!("{}
+            INSERT INTO elements(name, belongs_to_mod, base_value) VALUES('Fulgur', 'Test', 1.0);
+            INSERT INTO recipes(name, component_a, component_b) VALUES('Fulgur', 'Potentia', 'Aer');
+            ", crate::dao::tests::ASPECTS_4_2_3_5)).await);
+        let report = build_report(dao.clone(), 1).await.expect("1");
+        assert_eq!(report.primals, ["Aer", "Aqua", "Ignis", "Ordo", "Perditio", "Terra"]);
+        assert_eq!(report.tiers[0].aspects, report.primals);
+        assert!(report.terminal_aspects.contains(&"Fulgur".to_string()));
+        assert!(report.diameter.is_some());
+
+        let md = report.to_markdown();
+        for section in ["## Stats", "## Primals", "## Tiers", "## Diameter", "## Terminal aspects",
+            "## Mod integration"] {
+            assert!(md.contains(section), "{section} is missing");
+        }
+        assert!(md.contains("| Test | 1 | 1 |"));
+
+        let json = serde_json::to_value(&report).expect("2");
+        for key in ["stats", "primals", "tiers", "diameter", "terminal_aspects", "mod_integration"] {
+            assert!(json.get(key).is_some(), "{key} is missing");
+        }
+    }
+}